@@ -1,10 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tree::*;
 
+#[allow(dead_code)]
+#[path = "src/terminal/shell/files/mime.rs"]
+mod mime;
+
+/// Where the filesystem is read from
+const ROOT: &str = "src/terminal/shell/filesystem";
+
+/// Optional changes to what's read from `ROOT`. See `read_manifest`.
+const MANIFEST: &str = "src/terminal/shell/filesystem.toml";
+
+/// Owner of everything outside of users' home directories
+const SYSTEM_OWNER: &str = "cold";
+
+/// When everything compiled in was made, in milliseconds since the Unix epoch
+static BUILD_TIME: LazyLock<u128> = LazyLock::new(|| {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis())
+});
+
 // See: src/terminal/shell/files.rs
 pub enum FileType {
     Program(String),
@@ -37,86 +59,416 @@ impl FileType {
     }
 }
 
+// See: src/terminal/shell/files.rs
+pub struct Permissions {
+    owner: String,
+    group: String,
+    mode: u32,
+}
+
+impl Permissions {
+    /// Files in a user's home directory belong to them, and everything else to
+    /// `SYSTEM_OWNER`. Folders and programs can be entered or run by anyone, as can
+    /// files that are executable in the source tree. `MANIFEST` may say otherwise.
+    fn for_path(path: &Path, executable: bool) -> Self {
+        let relative = path.strip_prefix(ROOT).unwrap_or(path);
+        let owner = match relative.strip_prefix("home") {
+            Ok(home) => home
+                .components()
+                .next()
+                .and_then(|x| x.as_os_str().to_str())
+                .unwrap_or(SYSTEM_OWNER),
+            Err(_) => SYSTEM_OWNER,
+        };
+        let mode = if executable { 0o755 } else { 0o644 };
+
+        Permissions {
+            owner: owner.into(),
+            group: owner.into(),
+            mode,
+        }
+    }
+
+    /// Overrides the owner, group and mode with any the manifest gives
+    fn apply(&mut self, changes: &Entry) {
+        if let Some(ref owner) = changes.owner {
+            self.owner = owner.clone();
+        }
+        if let Some(ref group) = changes.group {
+            self.group = group.clone();
+        }
+        if let Some(mode) = changes.mode {
+            self.mode = mode;
+        }
+    }
+
+    fn instructions(&self) -> String {
+        format!(
+            "Permissions::new(\"{}\", \"{}\", {:#o})",
+            self.owner, self.group, self.mode
+        )
+    }
+}
+
 pub enum FSObject {
     File {
         name: String,
         contents: FileType,
+        kind: &'static str,
+        permissions: Permissions,
     },
     Folder {
         name: String,
         contents: HashMap<String, usize>, // Lookup table for child indices
+        permissions: Permissions,
+    },
+    Symlink {
+        name: String,
+        target: String,
+        permissions: Permissions,
     },
-    // Symlink(Weak<Node<FSObject>>),
 }
 
 impl FSObject {
     fn instructions(&self) -> String {
         match self {
-            Self::File { name, contents } => {
+            Self::File {
+                name,
+                contents,
+                kind,
+                permissions,
+            } => {
                 format!(
                     "FSObject::File {{
     name: \"{}\".into(),
-    contents: {}
+    contents: {},
+    kind: {:?},
+    permissions: {},
+    times: Times::at({})
 }}",
                     name,
-                    contents.instructions()
+                    contents.instructions(),
+                    kind,
+                    permissions.instructions(),
+                    *BUILD_TIME
                 )
             }
-            Self::Folder { name, contents: _ } => {
+            Self::Folder {
+                name,
+                contents: _,
+                permissions,
+            } => {
                 format!(
                     "FSObject::Folder {{
     name: \"{}\".into(),
-    contents: HashMap::new()
+    contents: HashMap::new(),
+    permissions: {},
+    times: Times::at({})
+}}",
+                    name,
+                    permissions.instructions(),
+                    *BUILD_TIME
+                )
+            }
+            Self::Symlink {
+                name,
+                target,
+                permissions,
+            } => {
+                format!(
+                    "FSObject::Symlink {{
+    name: \"{}\".into(),
+    target: {:?}.into(),
+    permissions: {},
+    times: Times::at({})
 }}",
-                    name
+                    name,
+                    target,
+                    permissions.instructions(),
+                    *BUILD_TIME
                 )
             }
         }
     }
 }
 
-fn read_filesystem(filesystem: &mut Tree<FSObject>, dir: &Path) -> io::Result<()> {
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                let mut child = Tree::new(FSObject::Folder {
-                    name: String::from(path.file_name().unwrap().to_str().unwrap()),
-                    contents: HashMap::new(),
-                });
-                read_filesystem(&mut child, &path)?;
-                filesystem.insert_child(child);
-            } else {
-                let ext = path.extension();
-                if let Some(os_str) = ext {
-                    if os_str == "rs" {
-                        let child = Tree::new(FSObject::File {
-                            name: String::from(
-                                path.with_extension("")
-                                    .file_name()
-                                    .unwrap()
-                                    .to_str()
-                                    .unwrap(),
-                            ),
-                            contents: FileType::Program(fs::read_to_string(path)?.to_string()),
-                        });
-                        filesystem.insert_child(child);
-
-                        continue;
-                    }
+/// Changes to one entry of the filesystem, from `MANIFEST`
+#[derive(Default)]
+struct Entry {
+    owner: Option<String>,
+    group: Option<String>,
+    mode: Option<u32>,
+    /// Left out of the filesystem, along with everything in it
+    hidden: bool,
+    /// What it's called in the filesystem, if not what it's called in `ROOT`
+    name: Option<String>,
+    /// Paths of symlinks to add that point to it
+    aliases: Vec<String>,
+    /// Makes it a symlink to this, rather than something read from `ROOT`
+    symlink: Option<String>,
+}
+
+/// Reads `MANIFEST`, if there is one. Its tables are keyed by paths as they are in
+/// `ROOT`, without the `.rs` on programs:
+///
+/// ```toml
+/// ["/root"]
+/// mode = 0o700
+///
+/// ["/bin/cash"]
+/// aliases = ["/bin/sh"]
+///
+/// ["/home/guest/docs"]
+/// symlink = "/usr/share/doc"
+/// ```
+fn read_manifest() -> Result<BTreeMap<PathBuf, Entry>, Vec<String>> {
+    let text = match fs::read_to_string(MANIFEST) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(vec![format!("{MANIFEST}: {e}")]),
+    };
+    let table: toml::Table = text.parse().map_err(|e| vec![format!("{MANIFEST}: {e}")])?;
+
+    let mut entries = BTreeMap::new();
+    let mut errors = Vec::new();
+    for (path, fields) in table {
+        let mut error =
+            |message: String| errors.push(format!("{MANIFEST}: [\"{path}\"]: {message}"));
+        if !path.starts_with('/') {
+            error("paths must be absolute".into());
+            continue;
+        }
+        let Some(fields) = fields.as_table() else {
+            error("should be a table".into());
+            continue;
+        };
+
+        let mut entry = Entry::default();
+        for (key, value) in fields {
+            let string = || value.as_str().map(String::from);
+            let valid = match key.as_str() {
+                "owner" => string().map(|x| entry.owner = Some(x)),
+                "group" => string().map(|x| entry.group = Some(x)),
+                "mode" => value
+                    .as_integer()
+                    .filter(|x| (0..=0o777).contains(x))
+                    .map(|x| entry.mode = Some(x as u32)),
+                "hidden" => value.as_bool().map(|x| entry.hidden = x),
+                "name" => string()
+                    .filter(|x| !x.is_empty() && !x.contains('/'))
+                    .map(|x| entry.name = Some(x)),
+                "aliases" => value
+                    .as_array()
+                    .and_then(|x| {
+                        x.iter()
+                            .map(|x| x.as_str().filter(|x| x.starts_with('/')).map(String::from))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .map(|x| entry.aliases = x),
+                "symlink" => string().map(|x| entry.symlink = Some(x)),
+                _ => {
+                    error(format!(
+                        "unknown key `{key}`, expected one of `owner`, `group`, `mode`, \
+                         `hidden`, `name`, `aliases` or `symlink`"
+                    ));
+                    continue;
                 }
-                let child = Tree::new(FSObject::File {
-                    name: String::from(path.file_name().unwrap().to_str().unwrap()),
-                    contents: FileType::Binary(fs::read(path)?),
-                });
-                filesystem.insert_child(child);
+            };
+            if valid.is_none() {
+                error(format!(
+                    "`{key}` should be {}, not `{value}`",
+                    match key.as_str() {
+                        "mode" => "an integer from 0o000 to 0o777",
+                        "hidden" => "a boolean",
+                        "name" => "a name without slashes in it",
+                        "aliases" => "an array of absolute paths",
+                        _ => "a string",
+                    }
+                ));
+            }
+        }
+        if entry.symlink.is_some()
+            && (entry.hidden || entry.name.is_some() || !entry.aliases.is_empty())
+        {
+            error("a `symlink` can't have `hidden`, `name` or `aliases`".into());
+        }
+        entries.insert(PathBuf::from(&path), entry);
+    }
+
+    if errors.is_empty() {
+        Ok(entries)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Where the entry at `path` in `ROOT` ends up, once it and the folders it's in have
+/// been renamed
+fn renamed(manifest: &BTreeMap<PathBuf, Entry>, path: &Path) -> PathBuf {
+    let mut out = PathBuf::from("/");
+    for ancestor in path
+        .ancestors()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .skip(1)
+    {
+        let name = manifest
+            .get(ancestor)
+            .and_then(|x| x.name.as_deref())
+            .or(ancestor.file_name().and_then(|x| x.to_str()))
+            .unwrap_or_default();
+        out.push(name);
+    }
+    out
+}
+
+/// Symlinks to add, from `symlink` and `aliases` in the manifest, by their paths in
+/// `ROOT`
+fn manifest_links(manifest: &BTreeMap<PathBuf, Entry>) -> BTreeMap<PathBuf, String> {
+    let mut links = BTreeMap::new();
+    for (path, entry) in manifest {
+        if let Some(ref target) = entry.symlink {
+            links.insert(path.clone(), target.clone());
+        }
+        for alias in &entry.aliases {
+            let target = renamed(manifest, path).to_string_lossy().to_string();
+            links.insert(PathBuf::from(alias), target);
+        }
+    }
+    links
+}
+
+/// Reads the folder `dir` into `filesystem`, which is at `at` in the filesystem.
+/// Entries of `manifest` and `links` are taken out as they're used, and problems with
+/// them added to `errors`.
+fn read_filesystem(
+    filesystem: &mut Tree<FSObject>,
+    dir: &Path,
+    at: &Path,
+    manifest: &mut BTreeMap<PathBuf, Entry>,
+    links: &mut BTreeMap<PathBuf, String>,
+    errors: &mut Vec<String>,
+) -> io::Result<()> {
+    let mut names = Vec::new();
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+    entries.sort_by_key(|x| x.file_name());
+    for entry in entries {
+        let path = entry.path();
+        // Only there to keep otherwise empty folders, like mount points, in git
+        if entry.file_name() == ".gitkeep" {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        let program = !file_type.is_dir() && path.extension().is_some_and(|x| x == "rs");
+        let source_name = if program {
+            path.file_stem()
+        } else {
+            path.file_name()
+        };
+        let source_name = source_name
+            .and_then(|x| x.to_str())
+            .unwrap_or_else(|| panic!("{}: file names must be valid UTF-8", path.display()));
+        let virtual_path = at.join(source_name);
+
+        let changes = manifest.remove(&virtual_path).unwrap_or_default();
+        if changes.hidden {
+            continue;
+        }
+        if changes.symlink.is_some() {
+            errors.push(format!(
+                "{MANIFEST}: [\"{}\"]: can't be a `symlink`, as it's already in {ROOT}",
+                virtual_path.display()
+            ));
+            continue;
+        }
+        let name = changes.name.clone().unwrap_or(source_name.into());
+        names.push(name.clone());
+        let permissions = |executable| {
+            let mut permissions = Permissions::for_path(&path, executable);
+            if file_type.is_symlink() {
+                permissions.mode = 0o777;
             }
+            permissions.apply(&changes);
+            permissions
+        };
+
+        // Checked first, as `is_dir` would follow the link
+        let child = if file_type.is_symlink() {
+            Tree::new(FSObject::Symlink {
+                name,
+                target: String::from(fs::read_link(&path)?.to_str().unwrap()),
+                permissions: permissions(true),
+            })
+        } else if path.is_dir() {
+            let mut child = Tree::new(FSObject::Folder {
+                name,
+                contents: HashMap::new(),
+                permissions: permissions(true),
+            });
+            read_filesystem(&mut child, &path, &virtual_path, manifest, links, errors)?;
+            child
+        } else if program {
+            Tree::new(FSObject::File {
+                name,
+                contents: FileType::Program(fs::read_to_string(&path)?.to_string()),
+                kind: mime::PROGRAM,
+                permissions: permissions(true),
+            })
+        } else {
+            let bytes = fs::read(&path)?;
+            Tree::new(FSObject::File {
+                kind: mime::guess(&name, &bytes),
+                name,
+                contents: FileType::Binary(bytes),
+                permissions: permissions(is_executable(&path)),
+            })
+        };
+        filesystem.insert_child(child);
+    }
+
+    // Symlinks from the manifest that go in this folder
+    let here: Vec<PathBuf> = links
+        .keys()
+        .filter(|x| x.parent() == Some(at))
+        .cloned()
+        .collect();
+    for path in here {
+        let target = links.remove(&path).unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if names.contains(&name) {
+            errors.push(format!(
+                "{MANIFEST}: symlink \"{}\" would replace what's already there",
+                path.display()
+            ));
+            continue;
         }
+        let mut permissions = Permissions::for_path(&Path::new(ROOT).join(&path), true);
+        permissions.mode = 0o777;
+        if let Some(changes) = manifest.remove(&path) {
+            permissions.apply(&changes);
+        }
+        names.push(name.clone());
+        filesystem.insert_child(Tree::new(FSObject::Symlink {
+            name,
+            target,
+            permissions,
+        }));
     }
     Ok(())
 }
 
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|x| x.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
+
 fn reconstruct(reconstruction: &mut String, tree: &Tree<'_, FSObject>) {
     let fsobj = tree.get_value();
     let mut as_child = true;
@@ -143,6 +495,14 @@ fn reconstruct(reconstruction: &mut String, tree: &Tree<'_, FSObject>) {
     reconstruction.push_str(".indexed()");
 }
 
+/// Stops the build, explaining why
+fn fail(errors: &[String]) -> ! {
+    for error in errors {
+        eprintln!("error: {error}");
+    }
+    std::process::exit(1);
+}
+
 fn main() -> io::Result<()> {
     let out_dir = env::var_os("OUT_DIR").unwrap();
     // let out_dir: String = "./".into();
@@ -151,9 +511,39 @@ fn main() -> io::Result<()> {
     let mut filesystem: Tree<FSObject> = Tree::new(FSObject::Folder {
         name: "/".into(),
         contents: HashMap::new(),
+        permissions: Permissions::for_path(Path::new(ROOT), true),
     });
 
-    read_filesystem(&mut filesystem, Path::new("src/terminal/shell/filesystem")).ok();
+    let mut manifest = match read_manifest() {
+        Ok(manifest) => manifest,
+        Err(errors) => fail(&errors),
+    };
+    let mut links = manifest_links(&manifest);
+    let mut errors = Vec::new();
+    read_filesystem(
+        &mut filesystem,
+        Path::new(ROOT),
+        Path::new("/"),
+        &mut manifest,
+        &mut links,
+        &mut errors,
+    )?;
+    // Anything left over doesn't match what's in `ROOT`
+    for path in manifest.keys().filter(|x| !links.contains_key(*x)) {
+        errors.push(format!(
+            "{MANIFEST}: [\"{}\"]: not found in {ROOT}",
+            path.display()
+        ));
+    }
+    for path in links.keys() {
+        errors.push(format!(
+            "{MANIFEST}: symlink \"{}\" isn't in a folder in {ROOT}",
+            path.display()
+        ));
+    }
+    if !errors.is_empty() {
+        fail(&errors);
+    }
 
     let mut reconstruction: String = String::new();
 
@@ -3,18 +3,34 @@ use std::{io, sync::mpsc::channel};
 
 use crossterm::{
     cursor,
-    event::{self, KeyCode, KeyModifiers},
+    event::{self, KeyCode, KeyModifiers, MouseEventKind},
     execute,
     style::Print,
     terminal,
 };
 
-use misc_terminal::{create_input_event, create_interrupt, key_events};
+use misc_terminal::{
+    create_input_event, create_interrupt, create_resize, create_scroll_bottom, create_scroll_down,
+    create_scroll_top, create_scroll_up, key_events,
+};
+
+/// How many lines a single mouse wheel notch scrolls
+const WHEEL_LINES: usize = 3;
+
+fn page_lines() -> usize {
+    terminal::size()
+        .map(|(_, rows)| (rows as usize).saturating_sub(1).max(1))
+        .unwrap_or(1)
+}
 
 fn main() -> io::Result<()> {
     terminal::enable_raw_mode()?;
 
-    execute!(std::io::stdout(), terminal::EnterAlternateScreen,)?;
+    execute!(
+        std::io::stdout(),
+        terminal::EnterAlternateScreen,
+        event::EnableMouseCapture
+    )?;
 
     let mut session = misc_terminal::get_session();
     session.output_handler(|display| {
@@ -37,10 +53,28 @@ fn main() -> io::Result<()> {
                         return Some(create_interrupt());
                     }
 
+                    match (key_event.code, key_event.modifiers) {
+                        (KeyCode::PageUp, KeyModifiers::SHIFT) => return Some(create_scroll_top()),
+                        (KeyCode::PageDown, KeyModifiers::SHIFT) => {
+                            return Some(create_scroll_bottom())
+                        }
+                        (KeyCode::PageUp, _) => return Some(create_scroll_up(page_lines())),
+                        (KeyCode::PageDown, _) => return Some(create_scroll_down(page_lines())),
+                        _ => {}
+                    }
+
                     let key_event_internal = key_events::KeyEvent {
                         key_type: match key_event.code {
                             KeyCode::Backspace => key_events::Key::Backspace,
                             KeyCode::Enter => key_events::Key::Enter,
+                            KeyCode::Tab => key_events::Key::Tab,
+                            KeyCode::Delete => key_events::Key::Delete,
+                            KeyCode::Left => key_events::Key::Left,
+                            KeyCode::Right => key_events::Key::Right,
+                            KeyCode::Up => key_events::Key::Up,
+                            KeyCode::Down => key_events::Key::Down,
+                            KeyCode::Home => key_events::Key::Home,
+                            KeyCode::End => key_events::Key::End,
                             KeyCode::Char(x) => key_events::Key::Char(x.to_ascii_lowercase()),
                             _ => return None,
                         },
@@ -54,6 +88,14 @@ fn main() -> io::Result<()> {
 
                     return Some(create_input_event(key_event_internal));
                 }
+                event::Event::Mouse(mouse_event) => match mouse_event.kind {
+                    MouseEventKind::ScrollUp => return Some(create_scroll_up(WHEEL_LINES)),
+                    MouseEventKind::ScrollDown => return Some(create_scroll_down(WHEEL_LINES)),
+                    _ => {}
+                },
+                event::Event::Resize(columns, rows) => {
+                    return Some(create_resize(columns as usize, rows as usize));
+                }
                 event::Event::Paste(x) => {}
                 _ => {}
             }
@@ -61,9 +103,16 @@ fn main() -> io::Result<()> {
         None
     });
 
+    let (columns, rows) = terminal::size()?;
+    session.resize(columns as usize, rows as usize);
+
     session.run();
 
-    execute!(std::io::stdout(), terminal::LeaveAlternateScreen,)?;
+    execute!(
+        std::io::stdout(),
+        event::DisableMouseCapture,
+        terminal::LeaveAlternateScreen
+    )?;
     terminal::disable_raw_mode()?;
 
     Ok(())
@@ -11,6 +11,14 @@ pub enum Key {
     Mod,
     Backspace,
     Enter,
+    Tab,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
 }
 
 #[derive(Debug)]
@@ -18,3 +26,16 @@ pub struct KeyEvent {
     pub key_type: Key,
     pub modifier: Option<Modifier>,
 }
+
+impl KeyEvent {
+    /// The character this event types, if it types one. Ctrl and Alt combinations
+    /// don't type anything.
+    pub fn typed_char(&self) -> Option<char> {
+        match (&self.key_type, &self.modifier) {
+            (Key::Char(_), Some(Modifier::Ctrl | Modifier::Alt)) => None,
+            (Key::Char(ch), Some(Modifier::Shift)) => Some(ch.to_ascii_uppercase()),
+            (Key::Char(ch), _) => Some(ch.to_ascii_lowercase()),
+            _ => None,
+        }
+    }
+}
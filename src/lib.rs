@@ -1,7 +1,7 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use session::{SessionMessage, ShellMessage};
+use session::{SessionMessage, ShellMessage, TerminalMessage};
 mod session;
 pub use session::Session;
 pub mod key_events;
@@ -26,3 +26,27 @@ pub fn create_input_event(key_event: key_events::KeyEvent) -> SessionMessage {
 pub fn create_interrupt() -> SessionMessage {
     SessionMessage::Interrupt
 }
+
+pub fn create_resize(width: usize, height: usize) -> SessionMessage {
+    SessionMessage::Resize(width, height)
+}
+
+pub fn create_scroll_up(lines: usize) -> SessionMessage {
+    SessionMessage::Terminal(TerminalMessage::ScrollUp(lines), None)
+}
+
+pub fn create_scroll_down(lines: usize) -> SessionMessage {
+    SessionMessage::Terminal(TerminalMessage::ScrollDown(lines), None)
+}
+
+pub fn create_scroll_top() -> SessionMessage {
+    SessionMessage::Terminal(TerminalMessage::ScrollTop, None)
+}
+
+pub fn create_scroll_bottom() -> SessionMessage {
+    SessionMessage::Terminal(TerminalMessage::ScrollBottom, None)
+}
+
+pub fn create_set_scrollback(lines: usize) -> SessionMessage {
+    SessionMessage::Terminal(TerminalMessage::SetScrollback(lines), None)
+}
@@ -10,6 +10,7 @@ use crate::{
         },
         DefaultTerminal, Span, Terminal,
     },
+    utils::pos::Size,
 };
 
 use std::{path::PathBuf, sync::mpsc::channel};
@@ -46,6 +47,17 @@ pub enum ShellMessage {
     /// Sent to the shell, asking it to sign in to the given user with the given password hash
     /// Returns a Result<(), SignInError>
     TrySetUser(String, Option<u64>),
+    /// Sent to the shell when the terminal is resized, to pass to the current running program
+    Resize(Size),
+    /// Sent to the shell, asking it to return the last known size of the terminal
+    GetSize,
+    /// Sent to a program with the next chunk of its standard input
+    Stdin(Vec<u8>),
+    /// Sent to a program once its standard input has run out
+    EndOfInput,
+    /// Sent by a program to write straight to the terminal, even when its output is
+    /// redirected. Used to echo what the user types into standard input.
+    Echo(TerminalMessage),
 }
 
 /// Messages sent to the terminal
@@ -67,6 +79,41 @@ pub enum TerminalMessage {
     DeleteSpan(usize),
     /// Send to the terminal to make it send OutputMessage::Display with its contents
     ForceUpdate,
+    /// Scroll the view up by the given number of lines
+    ScrollUp(usize),
+    /// Scroll the view down by the given number of lines
+    ScrollDown(usize),
+    /// Scroll the view to the oldest line in the scrollback
+    ScrollTop,
+    /// Scroll the view back to the newest line
+    ScrollBottom,
+    /// Set how many lines the terminal keeps before dropping the oldest ones
+    SetScrollback(usize),
+    /// Show a blank screen the size of the terminal instead of the buffer, which is left
+    /// as it was. Pushes and `Clear` go to that screen until it's left.
+    EnterAlternateScreen,
+    /// Go back to showing the buffer
+    LeaveAlternateScreen,
+    /// Move the cursor on the alternate screen to `(column, row)`, counting from the top
+    /// left corner. Text pushed after is written from there.
+    MoveCursor(usize, usize),
+    /// Whether to draw the cursor on the alternate screen. It's hidden to start with.
+    ShowCursor(bool),
+}
+
+impl TerminalMessage {
+    /// Whether the message changes the contents of the buffer.
+    pub fn is_write(&self) -> bool {
+        !matches!(
+            self,
+            Self::ForceUpdate
+                | Self::ScrollUp(..)
+                | Self::ScrollDown(..)
+                | Self::ScrollTop
+                | Self::ScrollBottom
+                | Self::SetScrollback(..)
+        )
+    }
 }
 
 /// Messages sent to the output handler
@@ -80,6 +127,7 @@ pub enum OutputMessage {
 pub enum ReturnValue {
     User(Option<User>),
     SignInResult(Result<(), SignInError>),
+    Size(Option<Size>),
 }
 
 /// A message sent between the input and output threads of the `Session`.
@@ -89,6 +137,7 @@ pub enum SessionMessage {
     Shell(ShellMessage, Option<Sender<SessionMessage>>),
     Output(OutputMessage, Option<Sender<SessionMessage>>),
     Interrupt,
+    /// Sent by the frontend when its display changes size. `(columns, rows)`
     Resize(usize, usize),
     Ack(Option<Sender<SessionMessage>>),
     Return(ReturnValue),
@@ -187,6 +236,14 @@ impl Session {
         });
     }
 
+    /// Tells the session the display is now `width` columns by `height` rows.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn resize(&self, width: usize, height: usize) {
+        self.sender_self
+            .send(SessionMessage::Resize(width, height))
+            .unwrap();
+    }
+
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
     pub fn run(self) {
         loop {
@@ -204,7 +261,15 @@ impl Session {
                             output_handler.send(m).unwrap();
                         }
                     }
-                    SessionMessage::Resize(_, _) => todo!(),
+                    SessionMessage::Resize(width, height) => {
+                        self.output.send(m).unwrap();
+                        self.input
+                            .send(SessionMessage::Shell(
+                                ShellMessage::Resize(Size::new(width, height)),
+                                None,
+                            ))
+                            .unwrap();
+                    }
                     SessionMessage::Interrupt => {
                         self.input.send(SessionMessage::Interrupt).unwrap();
                     }
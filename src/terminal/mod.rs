@@ -7,8 +7,10 @@ use std::{
     },
 };
 
+pub mod screen;
 pub mod shell;
 pub mod style;
+pub use screen::Screen;
 pub use style::{Color, Span, SpanSet};
 
 pub trait Terminal<B>: EventLoop + fmt::Display {
@@ -19,10 +21,49 @@ pub trait Terminal<B>: EventLoop + fmt::Display {
     fn clear(&self);
 }
 
+/// How many lines `DefaultTerminal` keeps around unless told otherwise.
+pub const DEFAULT_SCROLLBACK: usize = 1000;
+
 pub struct DefaultTerminal {
     buffer: Arc<Mutex<SpanSet>>,
+    /// How many lines the view is scrolled up from the bottom
     scroll: usize,
+    /// The most lines kept in the buffer before old ones get dropped
+    scrollback: usize,
     size: Option<Size>,
+    /// What's shown instead of the buffer while a full-screen program is running
+    alternate: Option<Screen>,
+}
+
+impl DefaultTerminal {
+    pub fn set_scrollback(&mut self, lines: usize) {
+        self.scrollback = lines.max(1);
+        self.trim_scrollback();
+    }
+
+    fn trim_scrollback(&self) {
+        self.buffer
+            .lock()
+            .map(|mut buff| buff.truncate_front(self.scrollback))
+            .unwrap();
+    }
+
+    /// The furthest the view can be scrolled up, given the current size.
+    fn max_scroll(&self) -> usize {
+        let Some(size) = self.size else {
+            return 0;
+        };
+        let lines = self
+            .buffer
+            .lock()
+            .map(|buff| buff.lines(Some(size.width())).len())
+            .unwrap();
+        lines.saturating_sub(size.height())
+    }
+
+    fn scroll_to(&mut self, scroll: usize) {
+        self.scroll = scroll.min(self.max_scroll());
+    }
 }
 
 impl Terminal<SpanSet> for DefaultTerminal {
@@ -35,7 +76,9 @@ impl Terminal<SpanSet> for DefaultTerminal {
         Self {
             buffer: Arc::new(Mutex::new(span_set)),
             scroll: 0,
+            scrollback: DEFAULT_SCROLLBACK,
             size: None,
+            alternate: None,
         }
     }
 
@@ -67,7 +110,14 @@ impl EventLoop for DefaultTerminal {
         tx: Sender<SessionMessage>,
     ) -> Result<BudgetNever, EventLoopError> {
         loop {
-            match rx.recv() {
+            let message = rx.recv();
+            if let Ok(SessionMessage::Terminal(ref msg, _)) = message {
+                if msg.is_write() {
+                    self.scroll = 0;
+                }
+            }
+
+            match message {
                 Ok(SessionMessage::Terminal(msg, sender)) => match msg {
                     TerminalMessage::ForceUpdate => {
                         tx.send(SessionMessage::Output(
@@ -76,6 +126,20 @@ impl EventLoop for DefaultTerminal {
                         ))
                         .unwrap();
                     }
+                    TerminalMessage::PushLine(x) if self.alternate.is_some() => {
+                        let screen = self.alternate.as_mut().unwrap();
+                        screen.write(&x);
+                        screen.write("\n");
+                    }
+                    TerminalMessage::Push(x) if self.alternate.is_some() => {
+                        self.alternate.as_mut().unwrap().write(&x);
+                    }
+                    TerminalMessage::PushSpan(x) if self.alternate.is_some() => {
+                        self.alternate.as_mut().unwrap().write_span(x);
+                    }
+                    TerminalMessage::Clear if self.alternate.is_some() => {
+                        self.alternate.as_mut().unwrap().clear();
+                    }
                     TerminalMessage::PushLine(x) => {
                         self.get_buffer()
                             .lock()
@@ -86,6 +150,7 @@ impl EventLoop for DefaultTerminal {
                                 }
                             })
                             .unwrap();
+                        self.trim_scrollback();
                     }
                     TerminalMessage::Push(x) => {
                         self.get_buffer()
@@ -96,6 +161,7 @@ impl EventLoop for DefaultTerminal {
                                 }
                             })
                             .unwrap();
+                        self.trim_scrollback();
                     }
                     TerminalMessage::PushSpan(x) => {
                         self.get_buffer()
@@ -104,6 +170,7 @@ impl EventLoop for DefaultTerminal {
                                 buff.push(x);
                             })
                             .unwrap();
+                        self.trim_scrollback();
                     }
                     TerminalMessage::Clear => {
                         self.clear();
@@ -140,7 +207,72 @@ impl EventLoop for DefaultTerminal {
                             })
                             .unwrap();
                     }
+                    TerminalMessage::EnterAlternateScreen => {
+                        let size = self.size.unwrap_or(Size::new(80, 24));
+                        self.alternate = Some(Screen::new(size));
+                    }
+                    TerminalMessage::LeaveAlternateScreen => {
+                        self.alternate = None;
+                    }
+                    TerminalMessage::MoveCursor(column, row) => {
+                        if let Some(screen) = &mut self.alternate {
+                            screen.move_cursor(column, row);
+                        }
+                    }
+                    TerminalMessage::ShowCursor(visible) => {
+                        if let Some(screen) = &mut self.alternate {
+                            screen.cursor_visible = visible;
+                        }
+                    }
+                    TerminalMessage::SetScrollback(lines) => {
+                        self.set_scrollback(lines);
+                        self.scroll_to(self.scroll);
+                    }
+                    TerminalMessage::ScrollUp(lines) => {
+                        self.scroll_to(self.scroll.saturating_add(lines));
+                        tx.send(SessionMessage::Output(
+                            OutputMessage::Display(self.to_string()),
+                            None,
+                        ))
+                        .unwrap();
+                    }
+                    TerminalMessage::ScrollDown(lines) => {
+                        self.scroll_to(self.scroll.saturating_sub(lines));
+                        tx.send(SessionMessage::Output(
+                            OutputMessage::Display(self.to_string()),
+                            None,
+                        ))
+                        .unwrap();
+                    }
+                    TerminalMessage::ScrollTop => {
+                        self.scroll_to(usize::MAX);
+                        tx.send(SessionMessage::Output(
+                            OutputMessage::Display(self.to_string()),
+                            None,
+                        ))
+                        .unwrap();
+                    }
+                    TerminalMessage::ScrollBottom => {
+                        self.scroll_to(0);
+                        tx.send(SessionMessage::Output(
+                            OutputMessage::Display(self.to_string()),
+                            None,
+                        ))
+                        .unwrap();
+                    }
                 },
+                Ok(SessionMessage::Resize(width, height)) => {
+                    self.set_size(Size::new(width, height));
+                    self.scroll_to(self.scroll);
+                    if let Some(screen) = &mut self.alternate {
+                        screen.resize(Size::new(width, height));
+                    }
+                    tx.send(SessionMessage::Output(
+                        OutputMessage::Display(self.to_string()),
+                        None,
+                    ))
+                    .unwrap();
+                }
                 Err(_) => {
                     eprintln!("Output thread disconnected!");
                     return Err(EventLoopError::ChannelClosed);
@@ -153,11 +285,26 @@ impl EventLoop for DefaultTerminal {
 
 impl fmt::Display for DefaultTerminal {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(screen) = &self.alternate {
+            return write!(f, "{screen}");
+        }
         let lock = self.buffer.lock();
         match lock {
-            Ok(x) => {
-                write!(f, "{x}")
-            }
+            Ok(x) => match self.size {
+                Some(size) => {
+                    let lines = x.lines(Some(size.width()));
+                    let end = lines.len().saturating_sub(self.scroll);
+                    let start = end.saturating_sub(size.height());
+                    for (i, line) in lines[start..end].iter().enumerate() {
+                        if i > 0 {
+                            write!(f, "\r\n")?;
+                        }
+                        write!(f, "{line}")?;
+                    }
+                    Ok(())
+                }
+                None => write!(f, "{x}"),
+            },
             Err(e) => panic!("Displaying terminal: Couldn't lock buffer: {e}"),
         }
     }
@@ -0,0 +1,136 @@
+use std::fmt::{self, Display};
+
+use super::style::{Span, SpanSet};
+use crate::utils::pos::Size;
+
+/// The alternate screen, which full-screen programs draw on instead of adding to the
+/// scrollback. Text is written wherever the cursor is, over what was there, and
+/// anything past the edges is dropped.
+#[derive(Clone, Debug)]
+pub struct Screen {
+    /// Each row's characters, with the style each was written in
+    rows: Vec<Vec<(char, Span)>>,
+    size: Size,
+    /// `(column, row)` of where the next character goes
+    cursor: (usize, usize),
+    /// Whether the cursor is drawn
+    pub cursor_visible: bool,
+    /// The style `write` uses, taken from the last span written
+    style: Span,
+}
+
+impl Screen {
+    pub fn new(size: Size) -> Self {
+        Self {
+            rows: vec![Vec::new(); size.height()],
+            size,
+            cursor: (0, 0),
+            cursor_visible: false,
+            style: Span::new(),
+        }
+    }
+
+    /// Changes the size, cutting off or adding rows and columns on the bottom and right
+    pub fn resize(&mut self, size: Size) {
+        self.size = size;
+        self.rows.resize(size.height(), Vec::new());
+        for row in &mut self.rows {
+            row.truncate(size.width());
+        }
+        self.move_cursor(self.cursor.0, self.cursor.1);
+    }
+
+    /// Blanks the screen and puts the cursor in the top left corner
+    pub fn clear(&mut self) {
+        self.rows.iter_mut().for_each(Vec::clear);
+        self.cursor = (0, 0);
+    }
+
+    /// Moves the cursor, keeping it on the screen
+    pub fn move_cursor(&mut self, column: usize, row: usize) {
+        self.cursor = (
+            column.min(self.size.width().saturating_sub(1)),
+            row.min(self.size.height().saturating_sub(1)),
+        );
+    }
+
+    /// Writes `text` in the style of the last span written. A newline goes to the start
+    /// of the next row.
+    pub fn write(&mut self, text: &str) {
+        for c in text.chars() {
+            let (column, row) = self.cursor;
+            match c {
+                '\r' => {}
+                '\n' => self.cursor = (0, row + 1),
+                c => {
+                    if column < self.size.width() {
+                        if let Some(cells) = self.rows.get_mut(row) {
+                            if cells.len() <= column {
+                                cells.resize(column + 1, (' ', Span::new()));
+                            }
+                            cells[column] = (c, self.style.clone());
+                        }
+                    }
+                    self.cursor = (column + 1, row);
+                }
+            }
+        }
+    }
+
+    /// Writes `span`'s text in its style
+    pub fn write_span(&mut self, span: Span) {
+        self.style = span.clone().with_text(String::new());
+        self.style.cursor = None;
+        self.write(&span.text);
+    }
+
+    /// The rows as lines of spans, with characters of the same style joined together
+    pub fn lines(&self) -> Vec<SpanSet> {
+        let (column, row) = self.cursor;
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(i, cells)| {
+                let mut cells = cells.clone();
+                let cursor = (self.cursor_visible && i == row).then_some(column);
+                if let Some(column) = cursor {
+                    if cells.len() <= column {
+                        cells.resize(column + 1, (' ', Span::new()));
+                    }
+                }
+                let mut line = SpanSet::new();
+                for (j, (c, style)) in cells.into_iter().enumerate() {
+                    let joins = line.last().is_some_and(|last: &Span| {
+                        last.fg_color == style.fg_color
+                            && last.bg_color == style.bg_color
+                            && last.decoration == style.decoration
+                            && last.link == style.link
+                            && last.cursor.is_none()
+                            && cursor != Some(j)
+                    });
+                    if !joins {
+                        line.push(style);
+                    }
+                    let span = line.last_mut().unwrap();
+                    if cursor == Some(j) {
+                        span.cursor = Some(span.text.chars().count());
+                    }
+                    span.text.push(c);
+                }
+                line
+            })
+            .collect()
+    }
+}
+
+impl Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.lines().iter().enumerate() {
+            if i > 0 {
+                write!(f, "\r\n")?;
+            }
+            write!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
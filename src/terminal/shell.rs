@@ -1,6 +1,10 @@
+pub mod cash_parse;
 pub mod files;
+pub mod markdown;
+pub mod regex;
+pub mod stdio;
 pub mod user;
-use files::{FSObject, FileSystem, FileType, FILESYSTEM};
+use files::{proc, Environment, FSObject, FileSystem, FileType, FILESYSTEM};
 use std::{
     path::PathBuf,
     sync::{
@@ -14,10 +18,14 @@ use user::{SignInError, User, USERS};
 use crate::{
     key_events::KeyEvent,
     session::{ReturnValue, SessionMessage, ShellMessage},
+    utils::pos::Size,
 };
 
 use super::{BudgetNever, EventLoop, EventLoopError};
 
+/// The program the shell runs on startup, and again whenever it exits
+const STARTUP: &str = "/bin/cash";
+
 pub trait Shell: EventLoop {
     fn new_at_path(path: PathBuf) -> Self;
 
@@ -35,6 +43,8 @@ pub trait Shell: EventLoop {
 }
 
 pub struct RunningProcess {
+    /// Its ID in `/proc`
+    pub pid: usize,
     pub thread: JoinHandle<()>,
     pub sender: Sender<SessionMessage>,
     pub receiver: Receiver<SessionMessage>,
@@ -45,17 +55,26 @@ pub struct DefaultShell {
     user: User,
     running: Option<RunningProcess>,
     startup: fn() -> files::Program,
+    size: Option<Size>,
 }
 
 impl Shell for DefaultShell {
     fn new_at_path(path: PathBuf) -> Self {
+        let user = User::from_name("guest");
+        {
+            let mut session = proc::SESSION.write().unwrap();
+            session.user = user.get_name().into();
+            session.cwd = path.to_string_lossy().into();
+        }
         Self {
             cwd: Mutex::new(path),
-            user: User::from_name("guest"),
+            user,
             running: None,
             startup: {
-                let file_arc = (*FILESYSTEM)
-                    .get_by_path(&PathBuf::from("/bin/cash"))
+                let file_arc = FILESYSTEM
+                    .read()
+                    .unwrap()
+                    .get_by_path(&PathBuf::from(STARTUP), &User::default())
                     .unwrap();
                 let file = file_arc.read().unwrap();
 
@@ -69,11 +88,12 @@ impl Shell for DefaultShell {
                     unreachable!();
                 }
             },
+            size: None,
         }
     }
 
     fn with_user(mut self, user: User) -> Self {
-        self.user = user;
+        self.set_user(user);
         self
     }
 
@@ -89,19 +109,42 @@ impl Shell for DefaultShell {
                         tx.send(SessionMessage::Ack(None)).unwrap();
                     }
                     if let Some(running) = &self.running {
-                        running.sender.send(session_message).unwrap();
+                        running.sender.send(session_message).ok();
+                    }
+                }
+                ShellMessage::ExitCode(..) | ShellMessage::Stdin(..) | ShellMessage::EndOfInput => {
+                    if let Some(tx) = ret {
+                        tx.send(SessionMessage::Ack(None)).unwrap();
+                    }
+                    if let Some(running) = &self.running {
+                        running.sender.send(session_message).ok();
                     }
                 }
-                ShellMessage::ExitCode(..) => {
+                ShellMessage::Resize(size) => {
+                    self.size = Some(*size);
+                    proc::SESSION.write().unwrap().size = Some(*size);
                     if let Some(tx) = ret {
                         tx.send(SessionMessage::Ack(None)).unwrap();
                     }
                     if let Some(running) = &self.running {
-                        running.sender.send(session_message).unwrap();
+                        running.sender.send(session_message).ok();
+                    }
+                }
+                // Unwrapped on the way out of the running program, see `event_loop`
+                ShellMessage::Echo(..) => {
+                    if let Some(tx) = ret {
+                        tx.send(SessionMessage::Ack(None)).unwrap();
+                    }
+                }
+                ShellMessage::GetSize => {
+                    if let Some(tx) = ret {
+                        tx.send(SessionMessage::Return(ReturnValue::Size(self.size)))
+                            .unwrap();
                     }
                 }
                 ShellMessage::ChangeCwd(path) => {
                     *self.cwd.lock().unwrap() = path.clone();
+                    proc::SESSION.write().unwrap().cwd = path.to_string_lossy().into();
 
                     if let Some(tx) = ret {
                         tx.send(SessionMessage::Ack(None)).unwrap();
@@ -118,7 +161,7 @@ impl Shell for DefaultShell {
                 ShellMessage::TrySetUser(username, pswd_hash) => {
                     if let Some(user) = USERS.get(username.as_str()) {
                         if user.check_password(*pswd_hash) {
-                            self.user = user.clone();
+                            self.set_user(user.clone());
                             if let Some(tx) = ret {
                                 tx.send(SessionMessage::Return(ReturnValue::SignInResult(Ok(()))))
                                     .unwrap();
@@ -148,15 +191,32 @@ impl Shell for DefaultShell {
         let (tx_ev, rx_ev) = channel::<SessionMessage>();
         let (tx_sh, rx_sh) = channel::<SessionMessage>();
         let cwd = self.cwd.lock().unwrap().to_string_lossy().to_string();
+        let pid = proc::spawn(None, args.join(" "));
+        let env = Environment::from([
+            ("PID".into(), pid.to_string()),
+            ("HOME".into(), self.user.home_directory()),
+            ("USER".into(), self.user.get_name().into()),
+            ("PATH".into(), self.user.get_path().into()),
+            ("PWD".into(), cwd),
+        ]);
         self.running = Some(RunningProcess {
-            thread: thread::spawn(move || program()([vec![cwd], args].concat(), rx_ev, tx_sh)),
+            pid,
+            thread: thread::spawn(move || program()(args, env, rx_ev, tx_sh)),
             sender: tx_ev,
             receiver: rx_sh,
         })
     }
 
     fn run_startup(&mut self, args: Vec<String>) {
-        self.run_program(self.startup, args);
+        self.run_program(self.startup, [vec![STARTUP.into()], args].concat());
+    }
+}
+
+impl DefaultShell {
+    /// Signs `user` in, which `/proc` shows too
+    fn set_user(&mut self, user: User) {
+        proc::SESSION.write().unwrap().user = user.get_name().into();
+        self.user = user;
     }
 }
 
@@ -185,8 +245,16 @@ impl EventLoop for DefaultShell {
 
             if let Some(process) = &self.running {
                 match process.receiver.try_recv() {
+                    // Nothing is redirecting the startup program's output, so echoes go straight out
+                    Ok(SessionMessage::Shell(ShellMessage::Echo(message), ret)) => {
+                        tx.send(SessionMessage::Terminal(message, ret)).unwrap()
+                    }
                     Ok(message) => tx.send(message).unwrap(),
-                    Err(TryRecvError::Disconnected) => panic!("Shell: No running process"),
+                    // The startup program exited, so start a fresh one next time around
+                    Err(TryRecvError::Disconnected) => {
+                        proc::exit(process.pid);
+                        self.running = None;
+                    }
                     _ => {}
                 }
             }
@@ -0,0 +1,505 @@
+//! How CASH reads command lines and scripts: splitting them into tokens, parsing those
+//! into an `Ast`, and taking the quotes out of words and filling in their variables
+//! when they're run.
+
+use super::files::{glob, Environment};
+
+enum ParsingState {
+    Normal,
+    String,
+    /// Inside single quotes, where nothing is expanded
+    Literal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// A word as it was typed, with its quotes and variables. See `expand_quotes`.
+    Word(String),
+    /// `|`
+    Pipe,
+    /// `<`
+    RedirectIn,
+    /// `>`
+    RedirectOut,
+    /// `>>`
+    RedirectAppend,
+    /// `;`
+    Semicolon,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `(`
+    LeftParen,
+    /// `)`
+    RightParen,
+    /// The end of a line in a script, which separates commands like `;`
+    Newline,
+}
+
+impl Token {
+    /// How the token was written, for error messages
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Word(word) => word,
+            Self::Pipe => "|",
+            Self::RedirectIn => "<",
+            Self::RedirectOut => ">",
+            Self::RedirectAppend => ">>",
+            Self::Semicolon => ";",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::LeftParen => "(",
+            Self::RightParen => ")",
+            Self::Newline => "newline",
+        }
+    }
+}
+
+/// One program in a pipeline, and where its input and output go.
+/// Words are kept as typed until the command runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub args: Vec<String>,
+    /// File to read standard input from
+    pub stdin: Option<String>,
+    /// File to write standard output to, and whether to append to it
+    pub stdout: Option<(String, bool)>,
+}
+
+/// A parsed command line or script
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ast {
+    /// Programs joined by `|`
+    Pipeline(Vec<Command>),
+    /// `a; b`, which runs both
+    Sequence(Box<Ast>, Box<Ast>),
+    /// `a && b`, which only runs `b` if `a` succeeds
+    And(Box<Ast>, Box<Ast>),
+    /// `a || b`, which only runs `b` if `a` fails
+    Or(Box<Ast>, Box<Ast>),
+    /// `if a; then b; else c; fi`. `elif` is another `If` in the `else`.
+    If(Box<Ast>, Box<Ast>, Option<Box<Ast>>),
+    /// `for name in words; do a; done`
+    For(String, Vec<String>, Box<Ast>),
+    /// `while a; do b; done`
+    While(Box<Ast>, Box<Ast>),
+}
+
+/// Words that end a list inside `if`, `for` and `while`
+const TERMINATORS: &[&str] = &["then", "elif", "else", "fi", "do", "done"];
+
+/// What `$` can refer to in a word
+pub struct Variables<'a> {
+    /// Exit code of the last command, as `$?`
+    pub status: u32,
+    /// The script being run and its arguments, as `$0` to `$9`, `$#` and `$@`
+    pub args: &'a [String],
+    pub env: &'a Environment,
+}
+
+/// A word with its quotes taken out and its variables filled in
+pub struct Expanded {
+    pub text: String,
+    /// The word as a pattern for `glob`, with what was quoted or escaped escaped
+    pub pattern: String,
+    /// Whether the word had quotes in it, so it counts even if it's empty
+    pub quoted: bool,
+}
+
+/// Whether `name` can be used as a variable or alias name
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|x| x.is_ascii_alphabetic() || x == '_')
+        && chars.all(|x| x.is_ascii_alphanumeric() || x == '_')
+}
+
+/// Reads a variable name following a `$` and returns its value. Unset variables
+/// are empty, and a `$` that isn't followed by a name is left alone.
+fn expand_variable(
+    iter: &mut std::iter::Peekable<std::str::Chars>,
+    variables: &Variables,
+) -> Result<String, ()> {
+    let name = match iter.peek() {
+        Some('?' | '#' | '@' | '0'..='9') => iter.next().unwrap().to_string(),
+        Some('{') => {
+            iter.next();
+            let mut name = String::new();
+            loop {
+                match iter.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(()),
+                }
+            }
+            name
+        }
+        Some(c) if c.is_ascii_alphabetic() || *c == '_' => {
+            let mut name = String::new();
+            while let Some(c) = iter.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                name.push(c);
+            }
+            name
+        }
+        _ => return Ok("$".into()),
+    };
+    Ok(match name.as_str() {
+        "?" => variables.status.to_string(),
+        "#" => variables.args.len().saturating_sub(1).to_string(),
+        "@" => variables.args.get(1..).unwrap_or_default().join(" "),
+        _ => match name.parse::<usize>() {
+            Ok(n) => variables.args.get(n).cloned().unwrap_or_default(),
+            Err(_) => variables.env.get(&name).cloned().unwrap_or_default(),
+        },
+    })
+}
+
+/// Splits a command line into words and operators. Words keep their quotes,
+/// so they can be expanded when they're run rather than now.
+pub fn tokenize(raw_input: &str) -> Result<Vec<Token>, ()> {
+    let mut state = ParsingState::Normal;
+    let mut out = Vec::new();
+    let mut current = String::new();
+
+    let mut iter = raw_input.chars().peekable();
+
+    while let Some(c) = iter.next() {
+        let operator = match (c, &state) {
+            (' ' | '\t' | '\r', ParsingState::Normal) => None,
+            ('\n', ParsingState::Normal) => Some(Token::Newline),
+            ('#', ParsingState::Normal) if current.is_empty() => {
+                while iter.next_if(|x| *x != '\n').is_some() {}
+                None
+            }
+            ('|', ParsingState::Normal) => Some(match iter.next_if_eq(&'|') {
+                Some(_) => Token::Or,
+                None => Token::Pipe,
+            }),
+            ('&', ParsingState::Normal) if iter.next_if_eq(&'&').is_some() => Some(Token::And),
+            (';', ParsingState::Normal) => Some(Token::Semicolon),
+            ('(', ParsingState::Normal) => Some(Token::LeftParen),
+            (')', ParsingState::Normal) => Some(Token::RightParen),
+            ('<', ParsingState::Normal) => Some(Token::RedirectIn),
+            ('>', ParsingState::Normal) => Some(match iter.next_if_eq(&'>') {
+                Some(_) => Token::RedirectAppend,
+                None => Token::RedirectOut,
+            }),
+            _ => {
+                current.push(c);
+                match (c, &state) {
+                    ('\'', ParsingState::Normal) => state = ParsingState::Literal,
+                    ('\'', ParsingState::Literal) => state = ParsingState::Normal,
+                    ('"', ParsingState::Normal) => state = ParsingState::String,
+                    ('"', ParsingState::String) => {
+                        state = ParsingState::Normal;
+                        if iter.peek().is_some_and(|x| !" \t\r\n|&;()<>".contains(*x)) {
+                            return Err(());
+                        }
+                    }
+                    ('\\', ParsingState::Normal | ParsingState::String) => {
+                        current.push(iter.next().ok_or(())?);
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+        };
+
+        if !current.is_empty() {
+            out.push(Token::Word(std::mem::take(&mut current)));
+        }
+        out.extend(operator);
+    }
+
+    if !matches!(state, ParsingState::Normal) {
+        return Err(());
+    }
+
+    if !current.is_empty() {
+        out.push(Token::Word(current));
+    }
+
+    Ok(out)
+}
+
+/// Takes the quotes out of a word and fills in its variables. Fails if a `${` isn't
+/// closed or the word ends in a backslash.
+pub fn expand_quotes(raw: &str, variables: &Variables) -> Result<Expanded, ()> {
+    let mut state = ParsingState::Normal;
+    let mut word = Expanded {
+        text: String::new(),
+        pattern: String::new(),
+        quoted: false,
+    };
+
+    let mut iter = raw.chars().peekable();
+
+    while let Some(c) = iter.next() {
+        match (c, &state) {
+            ('\'', ParsingState::Normal) => {
+                state = ParsingState::Literal;
+                word.quoted = true;
+            }
+            ('\'', ParsingState::Literal) => {
+                state = ParsingState::Normal;
+            }
+            (_, ParsingState::Literal) => {
+                word.text.push(c);
+                word.pattern.push_str(&glob::escape(&c.to_string()));
+            }
+            // Inside double quotes, only characters that would mean something get escaped
+            ('\\', ParsingState::String) if !matches!(iter.peek(), Some('$' | '"' | '\\')) => {
+                word.text.push(c);
+                word.pattern.push_str(&glob::escape(&c.to_string()));
+            }
+            ('\\', _) => {
+                let c = iter.next().ok_or(())?;
+                word.text.push(c);
+                word.pattern.push_str(&glob::escape(&c.to_string()));
+            }
+            ('$', _) => {
+                let value = expand_variable(&mut iter, variables)?;
+                word.text.push_str(&value);
+                // Wildcards in variables are expanded too, unless they're quoted
+                match state {
+                    ParsingState::Normal => word.pattern.push_str(&value),
+                    _ => word.pattern.push_str(&glob::escape(&value)),
+                }
+            }
+            ('"', ParsingState::Normal) => {
+                state = ParsingState::String;
+                word.quoted = true;
+            }
+            ('"', ParsingState::String) => {
+                state = ParsingState::Normal;
+            }
+            (_, ParsingState::String) => {
+                word.text.push(c);
+                word.pattern.push_str(&glob::escape(&c.to_string()));
+            }
+            (_, ParsingState::Normal) => {
+                word.text.push(c);
+                word.pattern.push(c);
+            }
+        }
+    }
+
+    Ok(word)
+}
+
+type Tokens = std::iter::Peekable<std::vec::IntoIter<Token>>;
+
+fn unexpected(token: Option<&Token>) -> String {
+    format!(
+        "syntax error near unexpected token `{}'",
+        token.map(Token::text).unwrap_or("newline")
+    )
+}
+
+/// Parses a whole command line or script. Returns `None` if it's empty.
+pub fn parse(tokens: Vec<Token>) -> Result<Option<Ast>, String> {
+    let mut tokens = tokens.into_iter().peekable();
+    skip_newlines(&mut tokens);
+    if tokens.peek().is_none() {
+        return Ok(None);
+    }
+
+    let ast = parse_list(&mut tokens)?;
+    match tokens.next() {
+        None => Ok(Some(ast)),
+        token => Err(unexpected(token.as_ref())),
+    }
+}
+
+fn skip_newlines(tokens: &mut Tokens) {
+    while tokens.next_if(|x| matches!(x, Token::Newline)).is_some() {}
+}
+
+/// Whether the next token ends the list being parsed
+fn at_list_end(tokens: &mut Tokens) -> bool {
+    match tokens.peek() {
+        None | Some(Token::RightParen) => true,
+        Some(Token::Word(word)) => TERMINATORS.contains(&word.as_str()),
+        _ => false,
+    }
+}
+
+fn expect_keyword(tokens: &mut Tokens, keyword: &str) -> Result<(), String> {
+    match tokens.next() {
+        Some(Token::Word(word)) if word == keyword => Ok(()),
+        token => Err(unexpected(token.as_ref())),
+    }
+}
+
+/// `a; b; ...` or one command per line, where a trailing `;` is allowed
+fn parse_list(tokens: &mut Tokens) -> Result<Ast, String> {
+    skip_newlines(tokens);
+    let mut ast = parse_and_or(tokens)?;
+    while tokens
+        .next_if(|x| matches!(x, Token::Semicolon | Token::Newline))
+        .is_some()
+    {
+        skip_newlines(tokens);
+        if at_list_end(tokens) {
+            break;
+        }
+        ast = Ast::Sequence(Box::new(ast), Box::new(parse_and_or(tokens)?));
+    }
+    Ok(ast)
+}
+
+/// `a && b || ...`, grouped from the left
+fn parse_and_or(tokens: &mut Tokens) -> Result<Ast, String> {
+    let mut ast = parse_compound(tokens)?;
+    loop {
+        ast = match tokens.next_if(|x| matches!(x, Token::And | Token::Or)) {
+            Some(Token::And) => {
+                skip_newlines(tokens);
+                Ast::And(Box::new(ast), Box::new(parse_compound(tokens)?))
+            }
+            Some(_) => {
+                skip_newlines(tokens);
+                Ast::Or(Box::new(ast), Box::new(parse_compound(tokens)?))
+            }
+            None => return Ok(ast),
+        };
+    }
+}
+
+/// A list in parentheses, an `if`, `for` or `while`, or otherwise a pipeline
+fn parse_compound(tokens: &mut Tokens) -> Result<Ast, String> {
+    let keyword = match tokens.peek() {
+        Some(Token::LeftParen) => "(",
+        Some(Token::Word(word)) => word.as_str(),
+        _ => "",
+    };
+
+    match keyword {
+        "(" => {
+            tokens.next();
+            let ast = parse_list(tokens)?;
+            match tokens.next() {
+                Some(Token::RightParen) => Ok(ast),
+                token => Err(unexpected(token.as_ref())),
+            }
+        }
+        "if" => {
+            tokens.next();
+            parse_if(tokens)
+        }
+        "for" => {
+            tokens.next();
+            parse_for(tokens)
+        }
+        "while" => {
+            tokens.next();
+            let condition = parse_list(tokens)?;
+            expect_keyword(tokens, "do")?;
+            let body = parse_list(tokens)?;
+            expect_keyword(tokens, "done")?;
+            Ok(Ast::While(Box::new(condition), Box::new(body)))
+        }
+        _ => parse_pipeline(tokens).map(Ast::Pipeline),
+    }
+}
+
+/// The rest of an `if` or `elif`, up to and including its `fi`
+fn parse_if(tokens: &mut Tokens) -> Result<Ast, String> {
+    let condition = parse_list(tokens)?;
+    expect_keyword(tokens, "then")?;
+    let then = parse_list(tokens)?;
+
+    let otherwise = match tokens.next() {
+        Some(Token::Word(word)) if word == "fi" => None,
+        Some(Token::Word(word)) if word == "elif" => Some(parse_if(tokens)?),
+        Some(Token::Word(word)) if word == "else" => {
+            let otherwise = parse_list(tokens)?;
+            expect_keyword(tokens, "fi")?;
+            Some(otherwise)
+        }
+        token => return Err(unexpected(token.as_ref())),
+    };
+
+    Ok(Ast::If(
+        Box::new(condition),
+        Box::new(then),
+        otherwise.map(Box::new),
+    ))
+}
+
+/// The rest of a `for`, up to and including its `done`
+fn parse_for(tokens: &mut Tokens) -> Result<Ast, String> {
+    let name = match tokens.next() {
+        Some(Token::Word(name)) if is_valid_name(&name) => name,
+        token => return Err(unexpected(token.as_ref())),
+    };
+    expect_keyword(tokens, "in")?;
+
+    let mut words = Vec::new();
+    loop {
+        match tokens.next() {
+            Some(Token::Word(word)) => words.push(word),
+            Some(Token::Semicolon | Token::Newline) => break,
+            token => return Err(unexpected(token.as_ref())),
+        }
+    }
+
+    skip_newlines(tokens);
+    expect_keyword(tokens, "do")?;
+    let body = parse_list(tokens)?;
+    expect_keyword(tokens, "done")?;
+    Ok(Ast::For(name, words, Box::new(body)))
+}
+
+/// `a | b | ...`
+fn parse_pipeline(tokens: &mut Tokens) -> Result<Vec<Command>, String> {
+    let mut commands = vec![parse_command(tokens)?];
+    while tokens.next_if(|x| matches!(x, Token::Pipe)).is_some() {
+        skip_newlines(tokens);
+        commands.push(parse_command(tokens)?);
+    }
+    Ok(commands)
+}
+
+/// A program's words and redirections
+fn parse_command(tokens: &mut Tokens) -> Result<Command, String> {
+    if at_list_end(tokens) {
+        return Err(unexpected(tokens.peek()));
+    }
+
+    let mut command = Command {
+        args: Vec::new(),
+        stdin: None,
+        stdout: None,
+    };
+
+    loop {
+        match tokens.next_if(|x| {
+            matches!(
+                x,
+                Token::Word(_) | Token::RedirectIn | Token::RedirectOut | Token::RedirectAppend
+            )
+        }) {
+            Some(Token::Word(word)) => command.args.push(word),
+            Some(redirect) => {
+                let target = match tokens.next() {
+                    Some(Token::Word(target)) => target,
+                    token => return Err(unexpected(token.as_ref())),
+                };
+                match redirect {
+                    Token::RedirectIn => command.stdin = Some(target),
+                    Token::RedirectAppend => command.stdout = Some((target, true)),
+                    _ => command.stdout = Some((target, false)),
+                }
+            }
+            None => break,
+        }
+    }
+
+    if command.args.is_empty() && command.stdin.is_none() && command.stdout.is_none() {
+        return Err(unexpected(tokens.peek()));
+    }
+    Ok(command)
+}
@@ -1,47 +1,358 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::path::{Component, Path, PathBuf};
 use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, LazyLock, RwLock};
-use std::{borrow::Borrow, sync::mpsc::Sender};
 use tree::send_tree::*;
 
 use crate::session::SessionMessage;
 
-use crate::terminal::shell::user::User;
+use crate::terminal::shell::user::{User, USERS};
 
-pub static FILESYSTEM: LazyLock<SendTree<FSObject>> = LazyLock::new(|| SendTree::new_filesystem());
+pub mod glob;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod host;
+pub mod mime;
+pub mod overlay;
+pub mod proc;
+pub mod tmpfs;
+pub mod vfs;
 
-pub type Program = Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<SessionMessage>)>;
+use vfs::{Mount, Mounts, Vfs};
+
+/// The filesystem compiled in, with the changes saved from earlier sessions on top.
+/// An empty tmpfs is mounted at `/tmp`, which anyone may add to but only remove their own
+/// things from, and `/proc` shows the session. Natively,
+/// `$CASH_HOST_DIR` is passed through at `/mnt/host` if it's set.
+pub static FILESYSTEM: LazyLock<RwLock<Vfs>> = LazyLock::new(|| {
+    let mut tree = SendTree::new_filesystem();
+    let changed = overlay::load(&mut tree);
+    let vfs = Vfs::new(tree)
+        .with_changed(changed)
+        .with_mount(
+            "/tmp",
+            tmpfs::Tmpfs::new(Permissions::new("cold", "cold", 0o1777)),
+        )
+        .with_mount("/proc", proc::Proc);
+    #[cfg(not(target_arch = "wasm32"))]
+    let vfs = match std::env::var_os("CASH_HOST_DIR") {
+        Some(dir) => vfs.with_mount("/mnt/host", host::Host::new(dir, User::default())),
+        None => vfs,
+    };
+    RwLock::new(vfs)
+});
+
+/// How many symlinks may be followed looking up one path, before giving up on it as a loop
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Environment variables handed to a program alongside its arguments
+pub type Environment = BTreeMap<String, String>;
+
+pub type Program =
+    Box<dyn FnOnce(Vec<String>, Environment, Receiver<SessionMessage>, Sender<SessionMessage>)>;
 pub enum FileType {
     Program(fn() -> Program),
     Binary(Vec<u8>),
 }
 
+/// Something a user may be allowed to do with a file or folder
+#[derive(Clone, Copy, Debug)]
+pub enum Access {
+    Read,
+    Write,
+    /// Running a file, or looking things up in a folder
+    Execute,
+}
+
+impl Access {
+    /// The bit for this in each `rwx` group of a mode
+    fn bit(self) -> u32 {
+        match self {
+            Self::Read => 0o4,
+            Self::Write => 0o2,
+            Self::Execute => 0o1,
+        }
+    }
+}
+
+/// Why a filesystem operation failed. Shown as the message `strerror` would give.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FsError {
+    NotFound,
+    NotADirectory,
+    IsADirectory,
+    /// Not allowed by a file's or folder's mode
+    PermissionDenied,
+    /// Only allowed for the owner or a superuser, whatever the mode
+    NotPermitted,
+    AlreadyExists,
+    NotEmpty,
+    InvalidArgument,
+    /// A path that isn't valid UTF-8, which nothing in the filesystem can be called
+    InvalidUtf8,
+    TooManyLinks,
+    ReadOnly,
+    /// Moving something from one mount to another
+    CrossDevice,
+    /// Moving or removing a mount point
+    Busy,
+    /// Anything else the host's filesystem went wrong with
+    Io,
+}
+
+impl std::fmt::Display for FsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::NotFound => "No such file or directory",
+            Self::NotADirectory => "Not a directory",
+            Self::IsADirectory => "Is a directory",
+            Self::PermissionDenied => "Permission denied",
+            Self::NotPermitted => "Operation not permitted",
+            Self::AlreadyExists => "File exists",
+            Self::NotEmpty => "Directory not empty",
+            Self::InvalidArgument => "Invalid argument",
+            Self::InvalidUtf8 => "Invalid or incomplete multibyte or wide character",
+            Self::TooManyLinks => "Too many levels of symbolic links",
+            Self::ReadOnly => "Read-only file system",
+            Self::CrossDevice => "Invalid cross-device link",
+            Self::Busy => "Device or resource busy",
+            Self::Io => "Input/output error",
+        })
+    }
+}
+
+impl std::error::Error for FsError {}
+
+/// Who owns a file or folder, and what they, its group and everyone else may do with it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Permissions {
+    pub owner: String,
+    pub group: String,
+    /// `rwx` bits for the owner, the group and everyone else, like `0o755`, and
+    /// `STICKY`
+    pub mode: u32,
+}
+
+/// The mode bit that lets only the owner of an entry in a folder, or of the folder,
+/// remove or rename the entry, like on `/tmp`
+pub const STICKY: u32 = 0o1000;
+
+impl Permissions {
+    pub fn new(owner: &str, group: &str, mode: u32) -> Self {
+        Self {
+            owner: owner.into(),
+            group: group.into(),
+            mode,
+        }
+    }
+
+    /// Permissions for something new made by `user`
+    pub fn owned_by(user: &User, mode: u32) -> Self {
+        Self::new(user.get_name(), user.get_group(), mode)
+    }
+
+    /// Superusers may do anything, except run what nobody is allowed to run
+    pub fn allows(&self, user: &User, access: Access) -> bool {
+        if user.is_superuser() {
+            return !matches!(access, Access::Execute) || self.mode & 0o111 != 0;
+        }
+        let shift = if user.get_name() == self.owner {
+            6
+        } else if user.get_group() == self.group {
+            3
+        } else {
+            0
+        };
+        (self.mode >> shift) & access.bit() != 0
+    }
+
+    /// Whether `user` may change these to `new`. Only the owner or a superuser may change
+    /// the mode, and only a superuser the owner or group.
+    pub fn may_change_to(&self, new: &Permissions, user: &User) -> bool {
+        user.is_superuser()
+            || (user.get_name() == self.owner && new.owner == self.owner && new.group == self.group)
+    }
+
+    /// The mode as `ls -l` shows it, like `rwxr-xr-x`
+    pub fn mode_string(&self) -> String {
+        (0..9)
+            .map(|i| {
+                let set = self.mode & (0o400 >> i) != 0;
+                match (i, set) {
+                    (8, true) if self.mode & STICKY != 0 => 't',
+                    (8, false) if self.mode & STICKY != 0 => 'T',
+                    (_, true) => ['r', 'w', 'x'][i % 3],
+                    (_, false) => '-',
+                }
+            })
+            .collect()
+    }
+
+    /// Whether `user` may remove or rename an entry with permissions `entry` from a
+    /// folder with these, as far as `STICKY` goes
+    pub fn may_remove(&self, entry: &Permissions, user: &User) -> bool {
+        self.mode & STICKY == 0
+            || user.is_superuser()
+            || user.get_name() == self.owner
+            || user.get_name() == entry.owner
+    }
+}
+
+/// When something was made, last changed and last read, in milliseconds since the Unix
+/// epoch. Things compiled in were all made when they were built.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Times {
+    pub created: u64,
+    pub modified: u64,
+    pub accessed: u64,
+}
+
+impl Times {
+    pub fn at(time: u64) -> Self {
+        Self {
+            created: time,
+            modified: time,
+            accessed: time,
+        }
+    }
+
+    pub fn now() -> Self {
+        Self::at(now())
+    }
+
+    /// `time` as a date and time in UTC, like `2024-03-01 12:00:00`
+    pub fn format(time: u64) -> String {
+        let seconds = time / 1000;
+        let (hour, minute, second) = (seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+        // Days since 0000-03-01, so leap days fall at the end of each year
+        let days = seconds / 86400 + 719468;
+        let era = days / 146097;
+        let day_of_era = days % 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = era * 400 + year_of_era + u64::from(month <= 2);
+        format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}")
+    }
+}
+
+/// The current time, in milliseconds since the Unix epoch
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> u64 {
+    js_sys::Date::now() as u64
+}
+
+/// The current time, in milliseconds since the Unix epoch
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis() as u64)
+}
+
+/// What `stat` tells about a file, folder or symlink
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metadata {
+    /// A MIME type, like `text/plain` or `inode/directory`
+    pub kind: &'static str,
+    /// Bytes in a file, or in a symlink's target. Folders and programs have none.
+    pub size: usize,
+    pub permissions: Permissions,
+    pub times: Times,
+}
+
 pub enum FSObject {
     File {
         name: String,
         contents: FileType,
+        /// See `mime::guess`
+        kind: &'static str,
+        permissions: Permissions,
+        times: Times,
     },
     Folder {
         name: String,
         contents: HashMap<String, usize>, // Lookup table for child indices
+        permissions: Permissions,
+        times: Times,
+    },
+    Symlink {
+        name: String,
+        /// Path to what the link points to, relative to the folder it's in unless absolute
+        target: String,
+        permissions: Permissions,
+        times: Times,
     },
-    // Symlink(Weak<Node<FSObject>>),
 }
 
 impl FSObject {
-    pub fn get_permissions(&self) -> Option<()> {
+    pub fn get_permissions(&self) -> &Permissions {
+        match self {
+            Self::File { permissions, .. } => permissions,
+            Self::Folder { permissions, .. } => permissions,
+            Self::Symlink { permissions, .. } => permissions,
+        }
+    }
+
+    pub fn get_permissions_mut(&mut self) -> &mut Permissions {
         match self {
-            Self::File { name, contents } => todo!(),
-            Self::Folder { name, contents } => todo!(),
+            Self::File { permissions, .. } => permissions,
+            Self::Folder { permissions, .. } => permissions,
+            Self::Symlink { permissions, .. } => permissions,
+        }
+    }
+
+    pub fn allows(&self, user: &User, access: Access) -> bool {
+        self.get_permissions().allows(user, access)
+    }
+
+    pub fn get_times(&self) -> &Times {
+        match self {
+            Self::File { times, .. } => times,
+            Self::Folder { times, .. } => times,
+            Self::Symlink { times, .. } => times,
+        }
+    }
+
+    pub fn get_times_mut(&mut self) -> &mut Times {
+        match self {
+            Self::File { times, .. } => times,
+            Self::Folder { times, .. } => times,
+            Self::Symlink { times, .. } => times,
+        }
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        let (kind, size) = match self {
+            Self::File {
+                contents: FileType::Binary(bytes),
+                kind,
+                ..
+            } => (*kind, bytes.len()),
+            Self::File { kind, .. } => (*kind, 0),
+            Self::Folder { .. } => (mime::DIRECTORY, 0),
+            Self::Symlink { target, .. } => (mime::SYMLINK, target.len()),
+        };
+        Metadata {
+            kind,
+            size,
+            permissions: self.get_permissions().clone(),
+            times: *self.get_times(),
         }
-        None
     }
 
     pub fn get_name(&self) -> &String {
         match self {
-            Self::File { name, contents: _ } => name,
-            Self::Folder { name, contents: _ } => name,
+            Self::File { name, .. } => name,
+            Self::Folder { name, .. } => name,
+            Self::Symlink { name, .. } => name,
         }
     }
 
@@ -52,6 +363,14 @@ impl FSObject {
     pub fn is_file(&self) -> bool {
         matches!(*self, Self::File { .. })
     }
+
+    fn set_name(&mut self, new_name: String) {
+        match self {
+            Self::File { name, .. } => *name = new_name,
+            Self::Folder { name, .. } => *name = new_name,
+            Self::Symlink { name, .. } => *name = new_name,
+        }
+    }
 }
 
 pub type AsyncFSObject = Arc<RwLock<FSObject>>;
@@ -61,17 +380,140 @@ pub trait FileSystem<'a> {
 
     fn new_filesystem() -> Self;
 
-    fn get_by_path(&'a self, path: &Path) -> Option<AsyncFSObject>;
+    /// Finds what's at `path`, as long as `user` may look in every folder on the way.
+    /// Symlinks are followed, including one at the end of the path.
+    fn get_by_path(&self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError>;
+
+    /// Like `get_by_path`, but a symlink at the end of the path is returned as it is
+    fn get_link_by_path(&self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError>;
+
+    /// Tells about what's at `path`, following symlinks like `get_by_path`
+    fn stat(&self, path: &Path, user: &User) -> Result<Metadata, FsError> {
+        self.get_by_path(path, user)
+            .map(|x| x.read().unwrap().metadata())
+    }
+
+    /// Like `stat`, but tells about a symlink at the end of the path itself
+    fn lstat(&self, path: &Path, user: &User) -> Result<Metadata, FsError> {
+        self.get_link_by_path(path, user)
+            .map(|x| x.read().unwrap().metadata())
+    }
+
+    /// The path of what's at `path` once every symlink on the way is followed
+    fn canonicalize(&self, path: &Path, user: &User) -> Result<PathBuf, FsError>;
+
+    /// The bytes of the file at `path`, if `user` may read it. Marks it as accessed.
+    fn read_file(&self, path: &Path, user: &User) -> Result<Vec<u8>, FsError> {
+        let file = self.get_by_path(path, user)?;
+        let mut file = file.write().unwrap();
+        if !file.is_file() {
+            return Err(FsError::IsADirectory);
+        }
+        if !file.allows(user, Access::Read) {
+            return Err(FsError::PermissionDenied);
+        }
+        // Programs compiled in have no bytes to read
+        let FSObject::File {
+            contents: FileType::Binary(ref bytes),
+            ref mut times,
+            ..
+        } = *file
+        else {
+            return Err(FsError::PermissionDenied);
+        };
+        times.accessed = now();
+        Ok(bytes.clone())
+    }
+
+    /// Creates a file owned by `user` at `path`, which must not exist yet,
+    /// in an existing folder
+    fn create_file(
+        &mut self,
+        path: &Path,
+        contents: FileType,
+        user: &User,
+    ) -> Result<AsyncFSObject, FsError>;
+
+    /// Creates an empty folder owned by `user` at `path`, which must not exist yet,
+    /// in an existing folder
+    fn create_folder(&mut self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError>;
+
+    /// Replaces the contents of the file at `path` with `bytes`, or adds them to the end
+    /// when appending. The file is created if it doesn't exist.
+    fn write_file(
+        &mut self,
+        path: &Path,
+        bytes: &[u8],
+        append: bool,
+        user: &User,
+    ) -> Result<(), FsError>;
+
+    /// Gives what's at `path` new permissions, if `user` may change them to those
+    fn set_permissions(
+        &mut self,
+        path: &Path,
+        permissions: Permissions,
+        user: &User,
+    ) -> Result<(), FsError>;
+
+    /// Moves whatever is at `from` to `to`, replacing a file or empty folder already there
+    fn rename(&mut self, from: &Path, to: &Path, user: &User) -> Result<(), FsError>;
+
+    /// Removes the file or symlink at `path`
+    fn unlink(&mut self, path: &Path, user: &User) -> Result<(), FsError>;
+
+    /// Removes the folder at `path`, which must be empty
+    fn remove_folder(&mut self, path: &Path, user: &User) -> Result<(), FsError>;
 
     fn index_children(&mut self);
 
-    fn expand_tilde(path: &Path, home_dir: &Path) -> PathBuf;
+    fn expand_tilde(path: &Path, home_dir: &Path) -> PathBuf {
+        if path.starts_with("~") {
+            return home_dir.join(path.components().skip(1).collect::<PathBuf>());
+        }
+        path.into()
+    }
+
+    fn make_absolute(path: &Path, cwd: &Path, home_dir: &Path) -> PathBuf {
+        let path = Self::expand_tilde(path, home_dir);
+
+        if path.starts_with("/") {
+            return path;
+        }
 
-    fn make_absolute(path: &Path, cwd: &Path, home_dir: &Path) -> PathBuf;
+        cwd.join(path)
+    }
 
-    fn resolve_path(path: &Path, cwd: &Path, home_dir: &Path) -> Result<PathBuf, ()>;
+    /// Most would call this "canonicalize". I am not most.
+    /// `..` is taken lexically, like a shell's `cd`. Symlinks are left for `get_by_path`,
+    /// and `canonicalize` gives the path with them followed.
+    fn resolve_path(path: &Path, cwd: &Path, home_dir: &Path) -> Result<PathBuf, FsError> {
+        Self::make_absolute(path, cwd, home_dir)
+            .components()
+            .try_fold(PathBuf::new(), |mut acc, comp| {
+                match comp {
+                    Component::RootDir => acc.push("/"),
+                    // Nothing in the filesystem could be called this
+                    Component::Normal(x) if x.to_str().is_none() => {
+                        return Err(FsError::InvalidUtf8)
+                    }
+                    Component::Normal(x) => acc.push(x),
+                    Component::ParentDir => {
+                        acc.pop();
+                    }
+                    _ => {}
+                }
+                Ok(acc)
+            })
+    }
 
-    fn indexed(self) -> Self;
+    fn indexed(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.index_children();
+        self
+    }
 }
 
 impl<'a> FileSystem<'a> for SendTree<'a, FSObject> {
@@ -79,6 +521,8 @@ impl<'a> FileSystem<'a> for SendTree<'a, FSObject> {
         SendTree::new(FSObject::Folder {
             name: "/".into(),
             contents: HashMap::new(),
+            permissions: Permissions::new("cold", "cold", 0o755),
+            times: Times::now(),
         })
     }
 
@@ -86,28 +530,191 @@ impl<'a> FileSystem<'a> for SendTree<'a, FSObject> {
         include!(concat!(env!("OUT_DIR"), "/filesystem.tree"))
     }
 
-    fn get_by_path(&'a self, path: &Path) -> Option<AsyncFSObject> {
-        let mut out: Option<&SendTree<FSObject>> = Some(self);
-        let mut components = path.components();
-        components.next(); // remove "/"
-        for obj in components {
-            out = out.and_then(|current_obj| {
-                let ref_arc = current_obj.get_value();
-                let fsobj = ref_arc.read().expect("Couldn't get lock on folder");
-                if let FSObject::Folder {
-                    name: _,
-                    ref contents,
-                } = *fsobj
-                {
-                    return contents
-                        .get(obj.as_os_str().to_str().unwrap())
-                        .and_then(|index| current_obj.get_child(*index).borrow().to_owned());
+    fn get_by_path(&self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError> {
+        walk(self, &[], path, user, true).map(|(_, node)| node.value())
+    }
+
+    fn get_link_by_path(&self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError> {
+        walk(self, &[], path, user, false).map(|(_, node)| node.value())
+    }
+
+    fn canonicalize(&self, path: &Path, user: &User) -> Result<PathBuf, FsError> {
+        walk(self, &[], path, user, true).map(|(path, _)| path)
+    }
+
+    fn create_file(
+        &mut self,
+        path: &Path,
+        contents: FileType,
+        user: &User,
+    ) -> Result<AsyncFSObject, FsError> {
+        let (parent, name) = split_path(path)?;
+        let kind = match contents {
+            FileType::Program(_) => mime::PROGRAM,
+            FileType::Binary(ref bytes) => mime::guess(name, bytes),
+        };
+        let file = add_child(
+            writable_folder(self, parent, user)?,
+            FSObject::File {
+                name: name.into(),
+                contents,
+                kind,
+                permissions: Permissions::owned_by(user, 0o644),
+                times: Times::now(),
+            },
+        )?;
+        touch_folder(self, parent, user);
+        Ok(file)
+    }
+
+    fn create_folder(&mut self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError> {
+        let (parent, name) = split_path(path)?;
+        let folder = add_child(
+            writable_folder(self, parent, user)?,
+            FSObject::Folder {
+                name: name.into(),
+                contents: HashMap::new(),
+                permissions: Permissions::owned_by(user, 0o755),
+                times: Times::now(),
+            },
+        )?;
+        touch_folder(self, parent, user);
+        Ok(folder)
+    }
+
+    fn write_file(
+        &mut self,
+        path: &Path,
+        bytes: &[u8],
+        append: bool,
+        user: &User,
+    ) -> Result<(), FsError> {
+        let file = match self.get_by_path(path, user) {
+            Ok(file) => file,
+            Err(FsError::NotFound) => {
+                return self
+                    .create_file(path, FileType::Binary(bytes.to_vec()), user)
+                    .map(|_| ());
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut file = file.write().unwrap();
+        if !file.allows(user, Access::Write) {
+            return Err(FsError::PermissionDenied);
+        }
+        match *file {
+            FSObject::Folder { .. } => Err(FsError::IsADirectory),
+            FSObject::File {
+                contents: FileType::Program(_),
+                ..
+            }
+            | FSObject::Symlink { .. } => Err(FsError::PermissionDenied),
+            FSObject::File {
+                ref name,
+                contents: FileType::Binary(ref mut contents),
+                ref mut kind,
+                ref mut times,
+                ..
+            } => {
+                if !append {
+                    contents.clear();
                 }
+                contents.extend_from_slice(bytes);
+                *kind = mime::guess(name, contents);
+                times.modified = now();
+                Ok(())
+            }
+        }
+    }
 
-                None
-            })
+    fn set_permissions(
+        &mut self,
+        path: &Path,
+        permissions: Permissions,
+        user: &User,
+    ) -> Result<(), FsError> {
+        let file = self.get_by_path(path, user)?;
+        let mut file = file.write().unwrap();
+        if !file.get_permissions().may_change_to(&permissions, user) {
+            return Err(FsError::NotPermitted);
+        }
+        *file.get_permissions_mut() = permissions;
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path, user: &User) -> Result<(), FsError> {
+        let (from_parent, from_name) = split_path(from)?;
+        let (to_parent, name) = split_path(to)?;
+        let source = self.get_link_by_path(from, user)?;
+        // Symlinks on the way are followed, but not ones being moved or replaced
+        let from = self.canonicalize(from_parent, user)?.join(from_name);
+        let to = self.canonicalize(to_parent, user)?.join(name);
+        if from == to {
+            return Ok(());
+        }
+        let moving_folder = source.read().unwrap().is_folder();
+        if moving_folder && to.starts_with(&from) {
+            return Err(FsError::InvalidArgument);
+        }
+        writable_folder(self, from_parent, user)?;
+        writable_folder(self, to_parent, user)?;
+
+        if let Ok(target) = self.get_link_by_path(&to, user) {
+            let replaced_folder = match *target.read().unwrap() {
+                FSObject::Folder { ref contents, .. } => {
+                    if !moving_folder {
+                        return Err(FsError::IsADirectory);
+                    }
+                    if !contents.is_empty() {
+                        return Err(FsError::NotEmpty);
+                    }
+                    true
+                }
+                FSObject::File { .. } | FSObject::Symlink { .. } => {
+                    if moving_folder {
+                        return Err(FsError::NotADirectory);
+                    }
+                    false
+                }
+            };
+            if replaced_folder {
+                self.remove_folder(&to, user)?;
+            } else {
+                self.unlink(&to, user)?;
+            }
+        }
+
+        let node = take_child(self, &from, user)?;
+        node.get_value().write().unwrap().set_name(name.into());
+        insert_child(node_mut(self, to_parent, user)?, node);
+        touch_folder(self, from_parent, user);
+        touch_folder(self, to_parent, user);
+        Ok(())
+    }
+
+    fn unlink(&mut self, path: &Path, user: &User) -> Result<(), FsError> {
+        let file = self.get_link_by_path(path, user)?;
+        if file.read().unwrap().is_folder() {
+            return Err(FsError::IsADirectory);
         }
-        out.map(|x| x.get_value().clone())
+        take_child(self, path, user)?;
+        touch_folder(self, split_path(path)?.0, user);
+        Ok(())
+    }
+
+    fn remove_folder(&mut self, path: &Path, user: &User) -> Result<(), FsError> {
+        let folder = self.get_link_by_path(path, user)?;
+        match *folder.read().unwrap() {
+            FSObject::File { .. } | FSObject::Symlink { .. } => return Err(FsError::NotADirectory),
+            FSObject::Folder { ref contents, .. } if !contents.is_empty() => {
+                return Err(FsError::NotEmpty)
+            }
+            FSObject::Folder { .. } => {}
+        }
+        take_child(self, path, user)?;
+        touch_folder(self, split_path(path)?.0, user);
+        Ok(())
     }
 
     fn index_children(&mut self) {
@@ -116,10 +723,10 @@ impl<'a> FileSystem<'a> for SendTree<'a, FSObject> {
         if fs_ref.is_folder() {
             let mut i = 0usize;
             let contents = match *fs_ref {
-                FSObject::File { .. } => unreachable!(),
                 FSObject::Folder {
                     ref mut contents, ..
                 } => contents,
+                _ => unreachable!(),
             };
             contents.clear();
             while let Some(child) = self.get_child(i) {
@@ -131,44 +738,250 @@ impl<'a> FileSystem<'a> for SendTree<'a, FSObject> {
             }
         }
     }
+}
 
-    fn expand_tilde(path: &Path, home_dir: &Path) -> PathBuf {
-        if path.starts_with("~") {
-            return home_dir.join(path.components().skip(1).collect::<PathBuf>());
+/// Splits a path into its parent folder and the name of the entry in it
+fn split_path(path: &Path) -> Result<(&Path, &str), FsError> {
+    let name = path.file_name().ok_or(FsError::InvalidArgument)?;
+    let name = name.to_str().ok_or(FsError::InvalidUtf8)?;
+    Ok((path.parent().unwrap_or(Path::new("/")), name))
+}
+
+/// Index of the child of `folder` called `name`, if `user` may look it up
+fn child_index(folder: &FSObject, name: &str, user: &User) -> Result<usize, FsError> {
+    match folder {
+        FSObject::Folder { contents, .. } => {
+            if !folder.allows(user, Access::Execute) {
+                return Err(FsError::PermissionDenied);
+            }
+            contents.get(name).copied().ok_or(FsError::NotFound)
         }
-        path.into()
+        _ => Err(FsError::NotADirectory),
     }
+}
 
-    fn make_absolute(path: &Path, cwd: &Path, home_dir: &Path) -> PathBuf {
-        let path = Self::expand_tilde(path, home_dir);
+/// Who reads and writes filesystems on everyone's behalf, once permissions are checked
+fn superuser() -> User {
+    USERS
+        .values()
+        .find(|x| x.is_superuser())
+        .cloned()
+        .unwrap_or_default()
+}
 
-        if path.starts_with("/") {
-            return path;
-        }
+/// Where a walk has got to: a node of the tree, or something in a mount
+#[derive(Clone)]
+enum Step<'s, 'a> {
+    Tree(&'s SendTree<'a, FSObject>),
+    /// The mount, the path in it, and what's there
+    Mounted(&'s dyn Mount, PathBuf, AsyncFSObject),
+}
 
-        cwd.join(path)
+impl Step<'_, '_> {
+    fn value(&self) -> AsyncFSObject {
+        match self {
+            Self::Tree(node) => node.get_value(),
+            Self::Mounted(_, _, value) => value.clone(),
+        }
     }
+}
 
-    // TODO: unit error? really?
-    /// Most would call this "canonicalize". I am not most.
-    fn resolve_path(path: &Path, cwd: &Path, home_dir: &Path) -> Result<PathBuf, ()> {
-        Self::make_absolute(path, cwd, home_dir)
-            .components()
-            .try_fold(PathBuf::new(), |mut acc, comp| {
-                match comp {
-                    Component::RootDir => acc.push("/"),
-                    Component::Normal(x) => acc.push(x),
-                    Component::ParentDir => {
-                        acc.pop();
+/// Walks down to the node at `path`, following symlinks on the way, and the one at the
+/// end too if `follow` is set. Reaching a folder in `mounts` carries on in what's
+/// mounted there. Returns the node and its path without symlinks in it.
+fn walk<'s, 'a>(
+    tree: &'s SendTree<'a, FSObject>,
+    mounts: &'s Mounts,
+    path: &Path,
+    user: &User,
+    follow: bool,
+) -> Result<(PathBuf, Step<'s, 'a>), FsError> {
+    let mut remaining = VecDeque::new();
+    push_components(&mut remaining, path);
+    // Folders from the root down to the current node, which is last
+    let mut nodes = vec![Step::Tree(tree)];
+    let mut out = PathBuf::from("/");
+    let mut hops = 0;
+
+    while let Some(component) = remaining.pop_front() {
+        let node = nodes.last().unwrap().clone();
+        match component.as_str() {
+            "/" => {
+                nodes.truncate(1);
+                out = PathBuf::from("/");
+            }
+            ".." => {
+                if nodes.len() > 1 {
+                    nodes.pop();
+                    out.pop();
+                }
+            }
+            name => {
+                let index = child_index(&node.value().read().unwrap(), name, user)?;
+                let path = out.join(name);
+                let child = match (mounts.iter().find(|(at, _)| *at == path), node) {
+                    (Some((_, mount)), _) => {
+                        let root = PathBuf::from("/");
+                        let value = mount.get(&root)?;
+                        Step::Mounted(mount.as_ref(), root, value)
+                    }
+                    (None, Step::Tree(node)) => {
+                        Step::Tree(node.get_child(index).ok_or(FsError::NotFound)?)
+                    }
+                    (None, Step::Mounted(mount, inner, _)) => {
+                        let inner = inner.join(name);
+                        let value = mount.get(&inner)?;
+                        Step::Mounted(mount, inner, value)
+                    }
+                };
+
+                let link = match *child.value().read().unwrap() {
+                    FSObject::Symlink { ref target, .. } if follow || !remaining.is_empty() => {
+                        Some(target.clone())
+                    }
+                    _ => None,
+                };
+                match link {
+                    Some(target) => {
+                        hops += 1;
+                        if hops > MAX_SYMLINK_HOPS {
+                            return Err(FsError::TooManyLinks);
+                        }
+                        // Carry on from the folder the link is in, along the link's target
+                        push_components(&mut remaining, Path::new(&target));
+                    }
+                    None => {
+                        nodes.push(child);
+                        out.push(name);
                     }
-                    _ => {}
                 }
-                Ok(acc)
-            })
+            }
+        }
     }
 
-    fn indexed(mut self) -> Self {
-        self.index_children();
-        self
+    Ok((out, nodes.pop().unwrap()))
+}
+
+/// Puts the components of `path` at the front of `components`, as `"/"`, `".."` or names
+fn push_components(components: &mut VecDeque<String>, path: &Path) {
+    for component in path.components().rev() {
+        match component {
+            Component::RootDir => components.push_front("/".into()),
+            Component::ParentDir => components.push_front("..".into()),
+            Component::Normal(name) => components.push_front(name.to_string_lossy().into()),
+            _ => {}
+        }
+    }
+}
+
+/// Walks down to the node at `path`, as `get_by_path` does
+fn node_mut<'s, 'a>(
+    tree: &'s mut SendTree<'a, FSObject>,
+    path: &Path,
+    user: &User,
+) -> Result<&'s mut SendTree<'a, FSObject>, FsError> {
+    // Symlinks have been followed already, so there are none on the way
+    let (path, _) = walk(tree, &[], path, user, true)?;
+    let mut node = tree;
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            continue;
+        };
+        let index = child_index(
+            &node.get_value().read().unwrap(),
+            &name.to_string_lossy(),
+            user,
+        )?;
+        node = node.get_child_mut(index).ok_or(FsError::NotFound)?;
+    }
+    Ok(node)
+}
+
+/// Walks down to a folder `user` may add entries to and remove them from
+fn writable_folder<'s, 'a>(
+    tree: &'s mut SendTree<'a, FSObject>,
+    path: &Path,
+    user: &User,
+) -> Result<&'s mut SendTree<'a, FSObject>, FsError> {
+    let folder = node_mut(tree, path, user)?;
+    let allowed = match *folder.get_value().read().unwrap() {
+        ref value @ FSObject::Folder { .. } => {
+            value.allows(user, Access::Write) && value.allows(user, Access::Execute)
+        }
+        _ => return Err(FsError::NotADirectory),
+    };
+    if !allowed {
+        return Err(FsError::PermissionDenied);
+    }
+    Ok(folder)
+}
+
+/// Marks the folder at `path` as modified, after an entry in it was added or removed
+fn touch_folder(tree: &SendTree<FSObject>, path: &Path, user: &User) {
+    if let Ok(folder) = tree.get_by_path(path, user) {
+        folder.write().unwrap().get_times_mut().modified = now();
+    }
+}
+
+/// Adds a new entry to a folder, keeping its index up to date
+fn add_child(folder: &mut SendTree<FSObject>, value: FSObject) -> Result<AsyncFSObject, FsError> {
+    match *folder.get_value().read().unwrap() {
+        FSObject::Folder { ref contents, .. } if contents.contains_key(value.get_name()) => {
+            return Err(FsError::AlreadyExists)
+        }
+        FSObject::Folder { .. } => {}
+        _ => return Err(FsError::NotADirectory),
+    }
+    let child = SendTree::new(value);
+    let value = child.get_value();
+    insert_child(folder, child);
+    Ok(value)
+}
+
+/// Inserts a node into a folder known not to have an entry by its name
+fn insert_child<'a>(folder: &mut SendTree<'a, FSObject>, child: SendTree<'a, FSObject>) {
+    let name = child.get_value().read().unwrap().get_name().clone();
+    if let FSObject::Folder {
+        ref mut contents, ..
+    } = *folder.get_value().write().unwrap()
+    {
+        contents.insert(name, folder.count());
+    }
+    folder.insert_child(child);
+}
+
+/// Detaches the node at `path` from its folder, keeping the folder's index up to date.
+/// The last entry takes its place, so only that entry's index changes.
+fn take_child<'a>(
+    tree: &mut SendTree<'a, FSObject>,
+    path: &Path,
+    user: &User,
+) -> Result<SendTree<'a, FSObject>, FsError> {
+    let (parent, name) = split_path(path)?;
+    let folder = writable_folder(tree, parent, user)?;
+    let folder_value = folder.get_value();
+    let mut folder_ref = folder_value.write().unwrap();
+    let folder_permissions = folder_ref.get_permissions().clone();
+    let FSObject::Folder {
+        ref mut contents, ..
+    } = *folder_ref
+    else {
+        return Err(FsError::NotADirectory);
+    };
+
+    let index = *contents.get(name).ok_or(FsError::NotFound)?;
+    let entry = folder
+        .get_child(index)
+        .ok_or(FsError::NotFound)?
+        .get_value();
+    if !folder_permissions.may_remove(entry.read().unwrap().get_permissions(), user) {
+        return Err(FsError::NotPermitted);
+    }
+    contents.remove(name);
+    let node = folder.swap_remove(index);
+    if let Some(moved) = folder.get_child(index) {
+        let moved_name = moved.get_value().read().unwrap().get_name().clone();
+        contents.insert(moved_name, index);
     }
+    Ok(node)
 }
@@ -0,0 +1,296 @@
+//! Shell-style wildcards, for matching names and expanding patterns into paths.
+//!
+//! `*` matches any run of characters and `?` any one character, while `[abc]`, `[a-z]`
+//! and `[!abc]` match one character that is, or isn't, in the set. A backslash makes the
+//! character after it mean itself. In a path, `**` on its own matches any number of
+//! folders.
+
+use std::path::Path;
+
+use tree::send_tree::SendTree;
+
+use super::vfs::Vfs;
+use super::{FSObject, FileSystem};
+use crate::terminal::shell::user::User;
+
+/// Whether `name` is matched by `pattern` as a whole
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    match_from(&pattern, &name)
+}
+
+/// Whether `pattern` has a wildcard in it that isn't escaped
+pub fn has_wildcards(pattern: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let mut i = 0;
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' => i += 1,
+            '*' | '?' => return true,
+            '[' if class(&pattern[i + 1..]).is_some() => return true,
+            _ => {}
+        }
+        i += 1;
+    }
+    false
+}
+
+/// `text` with its wildcards escaped, so it only matches itself
+pub fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if "*?[]\\".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// The paths `pattern` matches, sorted, written the way the pattern is: relative to
+/// `cwd` unless it starts with `/`. Folders `user` may not read are skipped.
+///
+/// Names starting with `.` are only matched by a part of the pattern that starts with
+/// one too, and `**` doesn't go into them or follow symlinks. A pattern ending in `/`
+/// only matches folders.
+pub fn expand(
+    filesystem: &Vfs,
+    pattern: &str,
+    cwd: &Path,
+    home: &Path,
+    user: &User,
+) -> Vec<String> {
+    let resolve = |shown: &str| {
+        SendTree::<FSObject>::resolve_path(
+            Path::new(if shown.is_empty() { "." } else { shown }),
+            cwd,
+            home,
+        )
+    };
+    let components: Vec<&str> = pattern.split('/').filter(|x| !x.is_empty()).collect();
+    let mut found = vec![if pattern.starts_with('/') {
+        "/".to_string()
+    } else {
+        String::new()
+    }];
+
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        let mut next = Vec::new();
+        for shown in &found {
+            let Ok(path) = resolve(shown) else {
+                continue;
+            };
+            if *component == "**" {
+                // Any number of folders, or at the end, everything under them
+                if !last {
+                    next.push(shown.clone());
+                }
+                descendants(filesystem, &path, shown, user, !last, &mut next);
+            } else if !has_wildcards(component) {
+                next.push(join(shown, &unescape(component)));
+            } else {
+                let hidden = component.starts_with('.') || component.starts_with("\\.");
+                for (name, is_folder) in entries(filesystem, &path, user) {
+                    if (hidden || !name.starts_with('.'))
+                        && (last || is_folder)
+                        && matches(component, &name)
+                    {
+                        next.push(join(shown, &name));
+                    }
+                }
+            }
+        }
+        found = next;
+    }
+
+    // Parts without wildcards were taken as they are, so may not be there
+    found.retain(|shown| {
+        resolve(shown)
+            .and_then(|path| filesystem.get_link_by_path(&path, user))
+            .is_ok()
+    });
+    if pattern.ends_with('/') {
+        found.retain(|shown| resolve(shown).is_ok_and(|path| is_folder(filesystem, &path, user)));
+        for shown in &mut found {
+            if !shown.ends_with('/') {
+                shown.push('/');
+            }
+        }
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Matches without recursing, so patterns with many `*`s take time in proportion to
+/// the length of the pattern times the length of the name
+fn match_from(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Where to go back to when what follows the last `*` stops matching: just after the
+    // `*`, and how much of `name` it has taken up so far
+    let mut star = None;
+    while p < pattern.len() || n < name.len() {
+        if pattern.get(p) == Some(&'*') {
+            p += 1;
+            star = Some((p, n));
+            continue;
+        }
+        if let Some(length) = name.get(n).and_then(|c| match_one(&pattern[p..], *c)) {
+            p += length;
+            n += 1;
+            continue;
+        }
+        // Let the last `*` take up one more character and try again from there
+        match star {
+            Some((after, taken)) if taken < name.len() => {
+                star = Some((after, taken + 1));
+                p = after;
+                n = taken + 1;
+            }
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// How many characters at the start of `pattern` match `c`, if they do
+fn match_one(pattern: &[char], c: char) -> Option<usize> {
+    match pattern.first()? {
+        '?' => Some(1),
+        '\\' if pattern.len() > 1 => (pattern[1] == c).then_some(2),
+        '[' => match class(&pattern[1..]) {
+            Some(class) => class.contains(c).then_some(class.length + 1),
+            None => (c == '[').then_some(1),
+        },
+        x => (*x == c).then_some(1),
+    }
+}
+
+/// A set of characters in brackets, like `[a-z]`
+struct Class {
+    /// How many characters it takes up after the `[`, including the `]`
+    length: usize,
+    negated: bool,
+    ranges: Vec<(char, char)>,
+}
+
+impl Class {
+    fn contains(&self, c: char) -> bool {
+        self.ranges
+            .iter()
+            .any(|(low, high)| (*low..=*high).contains(&c))
+            != self.negated
+    }
+}
+
+/// Reads a set like `abc]`, `a-z]` or `!abc]` following a `[`. A `]` straight after the
+/// `[` is part of the set. Without a closing `]`, it isn't a set at all.
+fn class(pattern: &[char]) -> Option<Class> {
+    let negated = matches!(pattern.first(), Some('!' | '^'));
+    let mut i = usize::from(negated);
+    let mut ranges = Vec::new();
+    loop {
+        let mut low = *pattern.get(i)?;
+        if low == ']' && i > usize::from(negated) {
+            return Some(Class {
+                length: i + 1,
+                negated,
+                ranges,
+            });
+        }
+        if low == '\\' {
+            i += 1;
+            low = *pattern.get(i)?;
+        }
+        i += 1;
+        match (pattern.get(i), pattern.get(i + 1)) {
+            (Some('-'), Some(&high)) if high != ']' => {
+                ranges.push((low, high));
+                i += 2;
+            }
+            _ => ranges.push((low, low)),
+        }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `name` in the folder shown as `folder`
+fn join(folder: &str, name: &str) -> String {
+    if folder.is_empty() {
+        name.into()
+    } else if folder.ends_with('/') {
+        format!("{folder}{name}")
+    } else {
+        format!("{folder}/{name}")
+    }
+}
+
+fn is_folder(filesystem: &Vfs, path: &Path, user: &User) -> bool {
+    filesystem
+        .get_by_path(path, user)
+        .is_ok_and(|x| x.read().unwrap().is_folder())
+}
+
+/// The names in the folder at `path`, and whether each is a folder or a symlink to one
+fn entries(filesystem: &Vfs, path: &Path, user: &User) -> Vec<(String, bool)> {
+    let Ok(folder) = filesystem.get_by_path(path, user) else {
+        return Vec::new();
+    };
+    let folder = folder.read().unwrap();
+    match *folder {
+        FSObject::Folder { ref contents, .. } if folder.allows(user, super::Access::Read) => {
+            contents
+                .keys()
+                .map(|name| (name.clone(), is_folder(filesystem, &path.join(name), user)))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Adds everything under the folder at `path`, shown as `shown`, to `out`. Only adds
+/// folders if `folders_only` is set.
+fn descendants(
+    filesystem: &Vfs,
+    path: &Path,
+    shown: &str,
+    user: &User,
+    folders_only: bool,
+    out: &mut Vec<String>,
+) {
+    for (name, _) in entries(filesystem, path, user) {
+        if name.starts_with('.') {
+            continue;
+        }
+        let child = path.join(&name);
+        let is_real_folder = filesystem
+            .get_link_by_path(&child, user)
+            .is_ok_and(|x| x.read().unwrap().is_folder());
+        if is_real_folder || !folders_only {
+            out.push(join(shown, &name));
+        }
+        if is_real_folder {
+            descendants(
+                filesystem,
+                &child,
+                &join(shown, &name),
+                user,
+                folders_only,
+                out,
+            );
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! A folder of the host's own filesystem, for trying things out locally. Only on native
+//! builds, as browsers have nothing to pass through to.
+//!
+//! Symlinks are shown as they are, so absolute ones point into the virtual filesystem
+//! rather than the host's.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::vfs::Mount;
+use super::{mime, AsyncFSObject, FSObject, FileType, FsError, Permissions, Times};
+use crate::terminal::shell::user::User;
+
+pub struct Host {
+    root: PathBuf,
+    /// Who everything in it belongs to, and may change it
+    owner: User,
+}
+
+impl Host {
+    /// Passes through to the host folder at `root`, as if everything in it were `owner`'s
+    pub fn new(root: impl Into<PathBuf>, owner: User) -> Self {
+        Self {
+            root: root.into(),
+            owner,
+        }
+    }
+
+    fn host_path(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// Only the owner and superusers may change anything
+    fn check(&self, user: &User) -> Result<(), FsError> {
+        if user.is_superuser() || user.get_name() == self.owner.get_name() {
+            Ok(())
+        } else {
+            Err(FsError::PermissionDenied)
+        }
+    }
+}
+
+impl Mount for Host {
+    fn get(&self, path: &Path) -> Result<AsyncFSObject, FsError> {
+        let host = self.host_path(path);
+        let metadata = fs::symlink_metadata(&host).map_err(error)?;
+        let name = path
+            .file_name()
+            .map_or("/".into(), |x| x.to_string_lossy().to_string());
+        let permissions = Permissions::new(
+            self.owner.get_name(),
+            self.owner.get_group(),
+            mode(&metadata),
+        );
+        let times = Times {
+            created: millis(metadata.created()),
+            modified: millis(metadata.modified()),
+            accessed: millis(metadata.accessed()),
+        };
+
+        let value = if metadata.is_symlink() {
+            FSObject::Symlink {
+                name,
+                target: fs::read_link(&host)
+                    .map_err(error)?
+                    .to_string_lossy()
+                    .into(),
+                permissions,
+                times,
+            }
+        } else if metadata.is_dir() {
+            FSObject::Folder {
+                name,
+                contents: fs::read_dir(&host)
+                    .map_err(error)?
+                    .filter_map(Result::ok)
+                    .enumerate()
+                    .map(|(i, x)| (x.file_name().to_string_lossy().into(), i))
+                    .collect(),
+                permissions,
+                times,
+            }
+        } else {
+            let bytes = fs::read(&host).map_err(error)?;
+            FSObject::File {
+                kind: mime::guess(&name, &bytes),
+                name,
+                contents: FileType::Binary(bytes),
+                permissions,
+                times,
+            }
+        };
+        Ok(Arc::new(RwLock::new(value)))
+    }
+
+    fn create_file(
+        &self,
+        path: &Path,
+        contents: FileType,
+        user: &User,
+    ) -> Result<AsyncFSObject, FsError> {
+        self.check(user)?;
+        let FileType::Binary(bytes) = contents else {
+            return Err(FsError::NotPermitted);
+        };
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.host_path(path))
+            .and_then(|mut x| x.write_all(&bytes))
+            .map_err(error)?;
+        self.get(path)
+    }
+
+    fn create_folder(&self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError> {
+        self.check(user)?;
+        fs::create_dir(self.host_path(path)).map_err(error)?;
+        self.get(path)
+    }
+
+    fn write_file(
+        &self,
+        path: &Path,
+        bytes: &[u8],
+        append: bool,
+        user: &User,
+    ) -> Result<(), FsError> {
+        self.check(user)?;
+        OpenOptions::new()
+            .create(true)
+            .append(append)
+            .write(true)
+            .truncate(!append)
+            .open(self.host_path(path))
+            .and_then(|mut x| x.write_all(bytes))
+            .map_err(error)
+    }
+
+    /// Only the mode can be changed, as everything belongs to the same owner
+    fn set_permissions(
+        &self,
+        path: &Path,
+        permissions: Permissions,
+        user: &User,
+    ) -> Result<(), FsError> {
+        if permissions.owner != self.owner.get_name() || permissions.group != self.owner.get_group()
+        {
+            return Err(FsError::NotPermitted);
+        }
+        let current = self.get(path)?.read().unwrap().get_permissions().clone();
+        if !current.may_change_to(&permissions, user) {
+            return Err(FsError::NotPermitted);
+        }
+        let host = self.host_path(path);
+        let mut host_permissions = fs::metadata(&host).map_err(error)?.permissions();
+        set_mode(&mut host_permissions, permissions.mode);
+        fs::set_permissions(host, host_permissions).map_err(error)
+    }
+
+    fn rename(&self, from: &Path, to: &Path, user: &User) -> Result<(), FsError> {
+        self.check(user)?;
+        fs::rename(self.host_path(from), self.host_path(to)).map_err(error)
+    }
+
+    fn unlink(&self, path: &Path, user: &User) -> Result<(), FsError> {
+        self.check(user)?;
+        fs::remove_file(self.host_path(path)).map_err(error)
+    }
+
+    fn remove_folder(&self, path: &Path, user: &User) -> Result<(), FsError> {
+        self.check(user)?;
+        fs::remove_dir(self.host_path(path)).map_err(error)
+    }
+}
+
+fn error(e: io::Error) -> FsError {
+    match e.kind() {
+        ErrorKind::NotFound => FsError::NotFound,
+        ErrorKind::AlreadyExists => FsError::AlreadyExists,
+        ErrorKind::PermissionDenied => FsError::PermissionDenied,
+        ErrorKind::NotADirectory => FsError::NotADirectory,
+        ErrorKind::IsADirectory => FsError::IsADirectory,
+        ErrorKind::DirectoryNotEmpty => FsError::NotEmpty,
+        ErrorKind::ReadOnlyFilesystem => FsError::ReadOnly,
+        ErrorKind::CrossesDevices => FsError::CrossDevice,
+        ErrorKind::InvalidFilename => FsError::InvalidArgument,
+        _ => FsError::Io,
+    }
+}
+
+fn millis(time: io::Result<SystemTime>) -> u64 {
+    time.ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |x| x.as_millis() as u64)
+}
+
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o777
+}
+
+#[cfg(not(unix))]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    match (metadata.is_dir(), metadata.permissions().readonly()) {
+        (true, _) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(permissions: &mut fs::Permissions, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    permissions.set_mode(mode);
+}
+
+#[cfg(not(unix))]
+fn set_mode(permissions: &mut fs::Permissions, mode: u32) {
+    permissions.set_readonly(mode & 0o200 == 0);
+}
@@ -0,0 +1,45 @@
+//! Telling what kind of file something is, as a MIME type.
+//!
+//! `build.rs` uses this too, for the files compiled in, so it must not depend on
+//! anything else in the crate.
+
+pub const DIRECTORY: &str = "inode/directory";
+pub const SYMLINK: &str = "inode/symlink";
+pub const PROGRAM: &str = "application/x-executable";
+
+/// Guesses a file's kind from its name, or failing that from what's in it
+pub fn guess(name: &str, bytes: &[u8]) -> &'static str {
+    let extension = name
+        .rsplit_once('.')
+        .filter(|(stem, _)| !stem.is_empty())
+        .map(|(_, x)| x.to_ascii_lowercase());
+    let by_extension = match extension.as_deref() {
+        Some("md") => Some("text/markdown"),
+        Some("txt") => Some("text/plain"),
+        Some("rs") => Some("text/x-rust"),
+        Some("sh") => Some("text/x-shellscript"),
+        Some("toml") => Some("application/toml"),
+        Some("json") => Some("application/json"),
+        Some("html" | "htm") => Some("text/html"),
+        Some("css") => Some("text/css"),
+        Some("js") => Some("text/javascript"),
+        Some("svg") => Some("image/svg+xml"),
+        Some("png") => Some("image/png"),
+        Some("jpg" | "jpeg") => Some("image/jpeg"),
+        Some("gif") => Some("image/gif"),
+        _ => None,
+    };
+    by_extension.unwrap_or_else(|| {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            "image/png"
+        } else if bytes.starts_with(b"\xff\xd8\xff") {
+            "image/jpeg"
+        } else if bytes.starts_with(b"GIF8") {
+            "image/gif"
+        } else if std::str::from_utf8(bytes).is_ok() {
+            "text/plain"
+        } else {
+            "application/octet-stream"
+        }
+    })
+}
@@ -0,0 +1,359 @@
+//! Saving changes to the filesystem, so they outlive the page or the process.
+//!
+//! Only what has changed from the filesystem compiled in by `build.rs` is saved, as an
+//! overlay merged on top of it at startup. `Vfs` keeps track of which paths those are, so
+//! nothing is compared against the compiled-in tree. It's text, one entry per line: removed paths
+//! first, then new or changed folders, files and symlinks, each folder before what's in
+//! it. Each has its owner, group, mode and times, then what's in it: paths are
+//! percent-encoded and file contents are hex. Programs can't be saved, so a moved one
+//! is saved as the path it was compiled in at. Only being read doesn't count as a
+//! change, so access times are saved for what's saved anyway.
+//!
+//! ```text
+//! # filesystem overlay v1
+//! delete /home/guest/README.md
+//! folder /home/guest/notes guest guest 755 1700000000000 1700000000000 1700000000000
+//! file /home/guest/notes/todo guest guest 644 1700000000000 1700000000000 1700000000000 6d696c6b0a
+//! program /bin/list cold cold 755 1700000000000 1700000000000 1700000000000 /bin/ls
+//! symlink /home/guest/b guest guest 777 1700000000000 1700000000000 1700000000000 /bin
+//! ```
+//!
+//! On wasm the overlay is kept in the browser's local storage. That only holds strings,
+//! and only about 5MB of them per site, so with contents in hex a few megabytes of
+//! changed files is as much as can be saved; past that, saving fails and the last
+//! overlay that fitted is kept. Natively it's kept in the file named by
+//! `$CASH_FILESYSTEM`, or else under the user's data directory.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use tree::send_tree::SendTree;
+
+use super::{
+    add_child, mime, split_path, superuser, take_child, writable_folder, FSObject, FileSystem,
+    FileType, FsError, Permissions, Program, Times, FILESYSTEM,
+};
+use crate::terminal::shell::user::User;
+
+const HEADER: &str = "# filesystem overlay v1";
+
+/// Where each program was compiled in. Only these are kept from a compiled-in tree made
+/// just to find them.
+static PROGRAMS: LazyLock<HashMap<PathBuf, fn() -> Program>> = LazyLock::new(|| {
+    let mut programs = HashMap::new();
+    program_paths(&SendTree::new_filesystem(), Path::new("/"), &mut programs);
+    programs
+});
+
+/// Merges the saved overlay, if there is one, on top of `tree`.
+/// A damaged overlay is merged up to the first bad entry.
+/// Returns the paths it changed, which are to be saved again.
+pub fn load(tree: &mut SendTree<FSObject>) -> BTreeSet<PathBuf> {
+    let mut changed = BTreeSet::new();
+    if let Some(overlay) = read_storage() {
+        merge(tree, &overlay, &mut changed).ok();
+    }
+    changed
+}
+
+/// Saves how `FILESYSTEM` differs from the one compiled in, if anything has changed
+/// since it was last saved
+pub fn save() -> Result<(), String> {
+    let mut filesystem = FILESYSTEM.write().unwrap();
+    if !filesystem.take_changed() {
+        return Ok(());
+    }
+    let overlay = serialize(&filesystem.root, &filesystem.changed);
+    drop(filesystem);
+    write_storage(&overlay)
+}
+
+/// The overlay saving what's at each of `paths` in `tree`, or that it's gone
+pub fn serialize(tree: &SendTree<FSObject>, paths: &BTreeSet<PathBuf>) -> String {
+    let user = superuser();
+    let mut lines = vec![HEADER.to_string()];
+    let mut entries = Vec::new();
+    // The root folder is always there, and has no name to save it under
+    for path in paths.iter().filter(|x| x.parent().is_some()) {
+        match tree.get_link_by_path(path, &user) {
+            Ok(value) => entries.extend(entry(path, &value.read().unwrap())),
+            Err(_) => lines.push(format!("delete {}", encode(&path.to_string_lossy()))),
+        }
+    }
+    lines.extend(entries);
+    lines.join("\n") + "\n"
+}
+
+/// Applies an overlay made by `serialize` to `tree`, stopping at the first bad entry.
+/// Adds the path of each entry applied to `changed`.
+pub fn merge(
+    tree: &mut SendTree<FSObject>,
+    overlay: &str,
+    changed: &mut BTreeSet<PathBuf>,
+) -> Result<(), String> {
+    let user = superuser();
+    let mut lines = overlay.lines().enumerate();
+    if lines.next().map(|(_, x)| x) != Some(HEADER) {
+        return Err("not a filesystem overlay".into());
+    }
+    for (number, line) in lines {
+        let path = apply(tree, line, &user).map_err(|e| format!("line {}: {e}", number + 1))?;
+        changed.insert(path);
+    }
+    Ok(())
+}
+
+/// Where each program in `node` is
+fn program_paths(
+    node: &SendTree<FSObject>,
+    path: &Path,
+    out: &mut HashMap<PathBuf, fn() -> Program>,
+) {
+    for child in (0..node.count()).filter_map(|i| node.get_child(i)) {
+        let value = child.get_value();
+        let value = value.read().unwrap();
+        let path = path.join(value.get_name());
+        match *value {
+            FSObject::File {
+                contents: FileType::Program(program),
+                ..
+            } => {
+                out.insert(path, program);
+            }
+            FSObject::Folder { .. } => program_paths(child, &path, out),
+            _ => {}
+        }
+    }
+}
+
+/// The line saving `value`, unless it's a program that wasn't compiled in
+fn entry(path: &Path, value: &FSObject) -> Option<String> {
+    let permissions = value.get_permissions();
+    let times = value.get_times();
+    let head = format!(
+        "{} {} {} {:o} {} {} {}",
+        encode(&path.to_string_lossy()),
+        encode(&permissions.owner),
+        encode(&permissions.group),
+        permissions.mode,
+        times.created,
+        times.modified,
+        times.accessed
+    );
+    Some(match value {
+        FSObject::Folder { .. } => format!("folder {head}"),
+        FSObject::File {
+            contents: FileType::Binary(bytes),
+            ..
+        } => format!("file {head} {}", to_hex(bytes)),
+        FSObject::File {
+            contents: FileType::Program(program),
+            ..
+        } => {
+            let (origin, _) = PROGRAMS
+                .iter()
+                .find(|(_, x)| **x as usize == *program as usize)?;
+            format!("program {head} {}", encode(&origin.to_string_lossy()))
+        }
+        FSObject::Symlink { target, .. } => format!("symlink {head} {}", encode(target)),
+    })
+}
+
+/// Applies one line of an overlay, returning the path it was for
+fn apply(tree: &mut SendTree<FSObject>, line: &str, user: &User) -> Result<PathBuf, String> {
+    let fields: Vec<&str> = line.split(' ').collect();
+    match fields.as_slice() {
+        ["delete", path] => {
+            let path = PathBuf::from(decode(path)?);
+            match take_child(tree, &path, user) {
+                Ok(_) | Err(FsError::NotFound) => Ok(path),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+        [kind, path, owner, group, mode, created, modified, accessed, rest @ ..] => {
+            let path = PathBuf::from(decode(path)?);
+            let name = split_path(&path).map_err(|e| e.to_string())?.1.to_string();
+            let permissions = Permissions::new(
+                &decode(owner)?,
+                &decode(group)?,
+                u32::from_str_radix(mode, 8).map_err(|_| "Invalid mode")?,
+            );
+            let time = |x: &str| x.parse().map_err(|_| "Invalid time");
+            let times = Times {
+                created: time(created)?,
+                modified: time(modified)?,
+                accessed: time(accessed)?,
+            };
+            let value = match (*kind, rest) {
+                ("folder", []) => FSObject::Folder {
+                    name,
+                    contents: HashMap::new(),
+                    permissions,
+                    times,
+                },
+                ("file", [bytes]) => {
+                    let bytes = from_hex(bytes)?;
+                    FSObject::File {
+                        kind: mime::guess(&name, &bytes),
+                        name,
+                        contents: FileType::Binary(bytes),
+                        permissions,
+                        times,
+                    }
+                }
+                ("program", [origin]) => FSObject::File {
+                    name,
+                    contents: FileType::Program(compiled_program(&decode(origin)?)?),
+                    kind: mime::PROGRAM,
+                    permissions,
+                    times,
+                },
+                ("symlink", [target]) => FSObject::Symlink {
+                    name,
+                    target: decode(target)?,
+                    permissions,
+                    times,
+                },
+                _ => return Err("Unknown entry".into()),
+            };
+            replace(tree, &path, value, user).map_err(|e| e.to_string())?;
+            Ok(path)
+        }
+        _ => Err("Unknown entry".into()),
+    }
+}
+
+/// The program compiled in at `path`
+fn compiled_program(path: &str) -> Result<fn() -> Program, String> {
+    PROGRAMS
+        .get(Path::new(path))
+        .copied()
+        .ok_or_else(|| "Not a program".into())
+}
+
+/// Puts `value` at `path`. A folder already there keeps what's in it.
+fn replace(
+    tree: &mut SendTree<FSObject>,
+    path: &Path,
+    value: FSObject,
+    user: &User,
+) -> Result<(), FsError> {
+    let existing = tree.get_link_by_path(path, user);
+    if let Ok(ref existing) = existing {
+        let mut existing = existing.write().unwrap();
+        match (existing.is_folder(), value.is_folder()) {
+            (true, true) => {
+                *existing.get_permissions_mut() = value.get_permissions().clone();
+                *existing.get_times_mut() = *value.get_times();
+                return Ok(());
+            }
+            (false, false) => {
+                *existing = value;
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+    if existing.is_ok() {
+        take_child(tree, path, user)?;
+    }
+    let (parent, _) = split_path(path)?;
+    add_child(writable_folder(tree, parent, user)?, value).map(|_| ())
+}
+
+/// Escapes what would break up an entry
+fn encode(text: &str) -> String {
+    let mut out = String::new();
+    for ch in text.chars() {
+        match ch {
+            '%' | ' ' | '\n' | '\r' | '\t' => out.push_str(&format!("%{:02x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+fn decode(text: &str) -> Result<String, &'static str> {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).ok_or("Invalid escape")?;
+            bytes.extend(from_hex(
+                std::str::from_utf8(hex).map_err(|_| "Invalid escape")?,
+            )?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| "Invalid UTF-8")
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>, &'static str> {
+    if !text.len().is_multiple_of(2) {
+        return Err("Invalid hex");
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            text.get(i..i + 2)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+                .ok_or("Invalid hex")
+        })
+        .collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "cash_filesystem";
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_storage() -> Option<String> {
+    local_storage()?.get_item(STORAGE_KEY).ok().flatten()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_storage(overlay: &str) -> Result<(), String> {
+    local_storage()
+        .ok_or("Local storage isn't available")?
+        .set_item(STORAGE_KEY, overlay)
+        .map_err(|_| format!("Local storage is full ({} bytes to save)", overlay.len()))
+}
+
+/// `$CASH_FILESYSTEM`, or `filesystem.overlay` in the user's data directory
+#[cfg(not(target_arch = "wasm32"))]
+fn storage_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("CASH_FILESYSTEM") {
+        return Some(path.into());
+    }
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|x| Path::new(&x).join(".local/share")))?;
+    Some(data.join("misc-terminal").join("filesystem.overlay"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_storage() -> Option<String> {
+    std::fs::read_to_string(storage_path()?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_storage(overlay: &str) -> Result<(), String> {
+    let path = storage_path().ok_or("Neither $CASH_FILESYSTEM nor $HOME is set")?;
+    let error = |e: std::io::Error| format!("{}: {e}", path.display());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(error)?;
+    }
+    std::fs::write(&path, overlay).map_err(error)
+}
@@ -0,0 +1,183 @@
+//! `/proc`, which shows what's going on in the session as read-only files:
+//!
+//! - `cwd`, a symlink to the shell's working directory
+//! - `size`, the terminal's width and height
+//! - `uptime`, seconds since the session started
+//! - `user`, who's signed in
+//! - a folder for each running process, named by its ID, with its `cmdline` and a
+//!   `status` of `Name: value` lines, including its parent's ID as `PPid`
+
+use std::collections::HashMap;
+use std::path::{Component, Path};
+use std::sync::{Arc, LazyLock, RwLock};
+
+use super::vfs::Mount;
+use super::{now, AsyncFSObject, FSObject, FileType, FsError, Permissions, Times};
+use crate::utils::pos::Size;
+
+/// What `/proc` tells about the session, kept up to date by the shell and by CASH
+pub struct Session {
+    /// When it started, in milliseconds since the Unix epoch
+    pub started: u64,
+    /// Who's signed in
+    pub user: String,
+    pub cwd: String,
+    pub size: Option<Size>,
+    processes: Vec<Process>,
+    next_pid: usize,
+}
+
+pub struct Process {
+    pub pid: usize,
+    /// The process that started it, unless the shell did
+    pub parent: Option<usize>,
+    pub command: String,
+    /// When it started, in milliseconds since the Unix epoch
+    pub started: u64,
+}
+
+pub static SESSION: LazyLock<RwLock<Session>> = LazyLock::new(|| {
+    RwLock::new(Session {
+        started: now(),
+        user: String::new(),
+        cwd: "/".into(),
+        size: None,
+        processes: Vec::new(),
+        next_pid: 1,
+    })
+});
+
+/// Adds a process started by `parent` to the tree, and returns its ID
+pub fn spawn(parent: Option<usize>, command: String) -> usize {
+    let mut session = SESSION.write().unwrap();
+    let pid = session.next_pid;
+    session.next_pid += 1;
+    session.processes.push(Process {
+        pid,
+        parent,
+        command,
+        started: now(),
+    });
+    pid
+}
+
+/// Takes a process out of the tree once it has exited
+pub fn exit(pid: usize) {
+    SESSION.write().unwrap().processes.retain(|x| x.pid != pid);
+}
+
+/// Makes what's in a file in `/proc`
+type Contents = fn(&Session) -> String;
+
+/// The files in `/proc` about the whole session, and what's in them
+const FILES: &[(&str, Contents)] = &[
+    ("size", |x| match x.size {
+        Some(size) => format!("{} {}\n", size.width(), size.height()),
+        None => String::new(),
+    }),
+    ("uptime", |x| {
+        format!("{:.2}\n", now().saturating_sub(x.started) as f64 / 1000.0)
+    }),
+    ("user", |x| format!("{}\n", x.user)),
+];
+
+/// Makes what's in a file in a process's folder
+type ProcessContents = fn(&Process) -> String;
+
+/// The files in each process's folder
+const PROCESS_FILES: &[(&str, ProcessContents)] = &[
+    ("cmdline", |x| format!("{}\n", x.command)),
+    ("status", |x| {
+        format!(
+            "Name:\t{}\nPid:\t{}\nPPid:\t{}\nStarted:\t{}\n",
+            x.command.split(' ').next().unwrap_or_default(),
+            x.pid,
+            x.parent.unwrap_or(0),
+            x.started
+        )
+    }),
+];
+
+pub struct Proc;
+
+impl Mount for Proc {
+    fn get(&self, path: &Path) -> Result<AsyncFSObject, FsError> {
+        let session = SESSION.read().unwrap();
+        let names: Vec<&str> = path
+            .components()
+            .filter_map(|x| match x {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+        let process = |pid: &str| {
+            session
+                .processes
+                .iter()
+                .find(|x| x.pid.to_string() == pid)
+                .ok_or(FsError::NotFound)
+        };
+
+        let value = match names.as_slice() {
+            [] => {
+                let entries = ["cwd".to_string()]
+                    .into_iter()
+                    .chain(FILES.iter().map(|(name, _)| name.to_string()))
+                    .chain(session.processes.iter().map(|x| x.pid.to_string()));
+                folder("/", entries, session.started)
+            }
+            ["cwd"] => FSObject::Symlink {
+                name: "cwd".into(),
+                target: session.cwd.clone(),
+                permissions: Permissions::new("cold", "cold", 0o777),
+                times: Times::at(session.started),
+            },
+            [name] => match FILES.iter().find(|(x, _)| x == name) {
+                Some((name, contents)) => file(name, contents(&session), session.started),
+                None => {
+                    let process = process(name)?;
+                    let entries = PROCESS_FILES.iter().map(|(name, _)| name.to_string());
+                    folder(name, entries, process.started)
+                }
+            },
+            [pid, name] => {
+                let process = process(pid)?;
+                let (name, contents) = PROCESS_FILES
+                    .iter()
+                    .find(|(x, _)| x == name)
+                    .ok_or(FsError::NotFound)?;
+                file(name, contents(process), process.started)
+            }
+            _ => return Err(FsError::NotFound),
+        };
+        Ok(Arc::new(RwLock::new(value)))
+    }
+}
+
+fn folder(name: &str, entries: impl Iterator<Item = String>, started: u64) -> FSObject {
+    FSObject::Folder {
+        name: name.into(),
+        contents: entries
+            .enumerate()
+            .map(|(i, x)| (x, i))
+            .collect::<HashMap<_, _>>(),
+        permissions: Permissions::new("cold", "cold", 0o555),
+        times: Times::at(started),
+    }
+}
+
+/// A file made when `started`, but always up to date
+fn file(name: &str, contents: String, started: u64) -> FSObject {
+    let now = now();
+    FSObject::File {
+        name: name.into(),
+        contents: FileType::Binary(contents.into_bytes()),
+        kind: "text/plain",
+        permissions: Permissions::new("cold", "cold", 0o444),
+        times: Times {
+            created: started,
+            modified: now,
+            accessed: now,
+        },
+    }
+}
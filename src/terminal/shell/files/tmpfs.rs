@@ -0,0 +1,87 @@
+//! A filesystem kept in memory, which is gone once the page or process is.
+
+use std::path::Path;
+use std::sync::RwLock;
+
+use tree::send_tree::SendTree;
+
+use super::vfs::Mount;
+use super::{superuser, AsyncFSObject, FSObject, FileSystem, FileType, FsError, Permissions};
+use crate::terminal::shell::user::User;
+
+pub struct Tmpfs {
+    tree: RwLock<SendTree<'static, FSObject>>,
+}
+
+impl Tmpfs {
+    /// An empty filesystem, whose root folder has `permissions`
+    pub fn new(permissions: Permissions) -> Self {
+        let tree = SendTree::new_root();
+        *tree.get_value().write().unwrap().get_permissions_mut() = permissions;
+        Self {
+            tree: RwLock::new(tree),
+        }
+    }
+}
+
+impl Mount for Tmpfs {
+    fn get(&self, path: &Path) -> Result<AsyncFSObject, FsError> {
+        self.tree
+            .read()
+            .unwrap()
+            .get_link_by_path(path, &superuser())
+    }
+
+    fn create_file(
+        &self,
+        path: &Path,
+        contents: FileType,
+        user: &User,
+    ) -> Result<AsyncFSObject, FsError> {
+        self.tree.write().unwrap().create_file(path, contents, user)
+    }
+
+    fn create_folder(&self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError> {
+        self.tree.write().unwrap().create_folder(path, user)
+    }
+
+    fn write_file(
+        &self,
+        path: &Path,
+        bytes: &[u8],
+        append: bool,
+        user: &User,
+    ) -> Result<(), FsError> {
+        self.tree
+            .write()
+            .unwrap()
+            .write_file(path, bytes, append, user)
+    }
+
+    fn set_permissions(
+        &self,
+        path: &Path,
+        permissions: Permissions,
+        user: &User,
+    ) -> Result<(), FsError> {
+        let file = self.get(path)?;
+        let mut file = file.write().unwrap();
+        if !file.get_permissions().may_change_to(&permissions, user) {
+            return Err(FsError::NotPermitted);
+        }
+        *file.get_permissions_mut() = permissions;
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path, user: &User) -> Result<(), FsError> {
+        self.tree.write().unwrap().rename(from, to, user)
+    }
+
+    fn unlink(&self, path: &Path, user: &User) -> Result<(), FsError> {
+        self.tree.write().unwrap().unlink(path, user)
+    }
+
+    fn remove_folder(&self, path: &Path, user: &User) -> Result<(), FsError> {
+        self.tree.write().unwrap().remove_folder(path, user)
+    }
+}
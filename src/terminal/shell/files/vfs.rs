@@ -0,0 +1,300 @@
+//! Filesystems mounted over folders of the one compiled in.
+//!
+//! A `Vfs` is the compiled-in tree plus a table of mounts. Looking a path up walks the
+//! tree until it reaches a mount point, then carries on in what's mounted there, so
+//! symlinks may point in and out of mounts. Changes are handed to whichever filesystem
+//! the path ends up in.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use tree::send_tree::SendTree;
+
+use super::{
+    split_path, superuser, walk, AsyncFSObject, FSObject, FileSystem, FileType, FsError,
+    Permissions, Step,
+};
+use crate::terminal::shell::user::User;
+
+/// A filesystem that can be mounted over a folder.
+///
+/// Paths are from the root of the mount, like `/notes/todo`, with every symlink on the way
+/// already followed. Everything but looking things up fails as read-only unless
+/// implemented. Permissions are checked by the caller while looking things up, and by
+/// the mount when changing them.
+pub trait Mount: Send + Sync {
+    /// What's at `path`, without following a symlink there. A folder's entries must be
+    /// in its contents, though the indices don't matter.
+    fn get(&self, path: &Path) -> Result<AsyncFSObject, FsError>;
+
+    fn create_file(
+        &self,
+        _path: &Path,
+        _contents: FileType,
+        _user: &User,
+    ) -> Result<AsyncFSObject, FsError> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn create_folder(&self, _path: &Path, _user: &User) -> Result<AsyncFSObject, FsError> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn write_file(
+        &self,
+        _path: &Path,
+        _bytes: &[u8],
+        _append: bool,
+        _user: &User,
+    ) -> Result<(), FsError> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn set_permissions(
+        &self,
+        _path: &Path,
+        _permissions: Permissions,
+        _user: &User,
+    ) -> Result<(), FsError> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path, _user: &User) -> Result<(), FsError> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn unlink(&self, _path: &Path, _user: &User) -> Result<(), FsError> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn remove_folder(&self, _path: &Path, _user: &User) -> Result<(), FsError> {
+        Err(FsError::ReadOnly)
+    }
+}
+
+/// Mount points, and what's mounted on them
+pub type Mounts = [(PathBuf, Box<dyn Mount>)];
+
+/// The filesystem compiled in, with other filesystems mounted over some of its folders.
+/// As a `FileSystem`, it looks paths up and changes things across mount points.
+pub struct Vfs {
+    /// The compiled-in tree, without what's mounted over it
+    pub root: SendTree<'static, FSObject>,
+    mounts: Vec<(PathBuf, Box<dyn Mount>)>,
+    /// Paths in `root` that may differ from the compiled-in tree: whatever's been made,
+    /// changed or removed there, and the folders that were added to or removed from.
+    /// `overlay` saves just these.
+    pub changed: BTreeSet<PathBuf>,
+    /// Whether `changed` has grown since `take_changed` was last called
+    unsaved: bool,
+}
+
+impl Vfs {
+    pub fn new(root: SendTree<'static, FSObject>) -> Self {
+        Self {
+            root,
+            mounts: Vec::new(),
+            changed: BTreeSet::new(),
+            unsaved: false,
+        }
+    }
+
+    /// Whether anything in `root` has been changed since this was last called
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.unsaved)
+    }
+
+    /// Passes `result` on. If it's `Ok`, each of `paths` that's in `root` is noted as
+    /// changed, along with the folder it's in.
+    fn note<T>(&mut self, result: Result<T, FsError>, paths: &[&Path]) -> Result<T, FsError> {
+        if result.is_ok() {
+            let paths: Vec<&Path> = paths
+                .iter()
+                .copied()
+                .filter(|x| self.mount_of(x).is_none())
+                .collect();
+            for path in paths {
+                self.changed.insert(path.to_path_buf());
+                self.changed.extend(path.parent().map(Path::to_path_buf));
+                self.unsaved = true;
+            }
+        }
+        result
+    }
+
+    /// Notes the paths saved by an earlier session as changed, so they're saved again
+    pub fn with_changed(mut self, paths: BTreeSet<PathBuf>) -> Self {
+        self.changed.extend(paths);
+        self
+    }
+
+    /// Mounts `mount` over the folder at `at`, which hides what's in it
+    pub fn with_mount(mut self, at: impl Into<PathBuf>, mount: impl Mount + 'static) -> Self {
+        self.mounts.push((at.into(), Box::new(mount)));
+        self
+    }
+
+    /// `path` with the symlinks in its folder followed, but not one at the end
+    fn resolve_parent(&self, path: &Path, user: &User) -> Result<PathBuf, FsError> {
+        let (parent, name) = split_path(path)?;
+        Ok(self.canonicalize(parent, user)?.join(name))
+    }
+
+    /// The mount `path` is in, if any, and the path in it
+    fn mount_of(&self, path: &Path) -> Option<(&dyn Mount, PathBuf)> {
+        self.mounts
+            .iter()
+            .filter(|(at, _)| path.starts_with(at))
+            .max_by_key(|(at, _)| at.components().count())
+            .map(|(at, mount)| {
+                let inner = path.strip_prefix(at).unwrap_or(path);
+                (mount.as_ref(), Path::new("/").join(inner))
+            })
+    }
+
+    /// Mount points, and folders with them in, can't be moved or removed
+    fn check_busy(&self, path: &Path) -> Result<(), FsError> {
+        if self.mounts.iter().any(|(at, _)| at.starts_with(path)) {
+            return Err(FsError::Busy);
+        }
+        Ok(())
+    }
+}
+
+impl FileSystem<'static> for Vfs {
+    fn new_root() -> Self {
+        Self::new(SendTree::new_root())
+    }
+
+    fn new_filesystem() -> Self {
+        Self::new(SendTree::new_filesystem())
+    }
+
+    fn get_by_path(&self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError> {
+        walk(&self.root, &self.mounts, path, user, true).map(|(_, x)| x.value())
+    }
+
+    fn get_link_by_path(&self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError> {
+        walk(&self.root, &self.mounts, path, user, false).map(|(_, x)| x.value())
+    }
+
+    fn canonicalize(&self, path: &Path, user: &User) -> Result<PathBuf, FsError> {
+        walk(&self.root, &self.mounts, path, user, true).map(|(path, _)| path)
+    }
+
+    fn create_file(
+        &mut self,
+        path: &Path,
+        contents: FileType,
+        user: &User,
+    ) -> Result<AsyncFSObject, FsError> {
+        let path = self.resolve_parent(path, user)?;
+        let result = match self.mount_of(&path) {
+            Some((mount, path)) => mount.create_file(&path, contents, user),
+            None => self.root.create_file(&path, contents, user),
+        };
+        self.note(result, &[&path])
+    }
+
+    fn create_folder(&mut self, path: &Path, user: &User) -> Result<AsyncFSObject, FsError> {
+        let path = self.resolve_parent(path, user)?;
+        let result = match self.mount_of(&path) {
+            Some((mount, path)) => mount.create_folder(&path, user),
+            None => self.root.create_folder(&path, user),
+        };
+        self.note(result, &[&path])
+    }
+
+    fn write_file(
+        &mut self,
+        path: &Path,
+        bytes: &[u8],
+        append: bool,
+        user: &User,
+    ) -> Result<(), FsError> {
+        // Following a symlink at the end too, unless there's nothing there yet
+        let path = match self.canonicalize(path, user) {
+            Ok(path) => path,
+            Err(FsError::NotFound) => self.resolve_parent(path, user)?,
+            Err(e) => return Err(e),
+        };
+        let result = match self.mount_of(&path) {
+            Some((mount, path)) => mount.write_file(&path, bytes, append, user),
+            None => self.root.write_file(&path, bytes, append, user),
+        };
+        self.note(result, &[&path])
+    }
+
+    fn set_permissions(
+        &mut self,
+        path: &Path,
+        permissions: Permissions,
+        user: &User,
+    ) -> Result<(), FsError> {
+        let path = self.canonicalize(path, user)?;
+        let result = match self.mount_of(&path) {
+            Some((mount, path)) => mount.set_permissions(&path, permissions, user),
+            None => self.root.set_permissions(&path, permissions, user),
+        };
+        self.note(result, &[&path])
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path, user: &User) -> Result<(), FsError> {
+        let from = self.resolve_parent(from, user)?;
+        let to = self.resolve_parent(to, user)?;
+        self.check_busy(&from)?;
+        self.check_busy(&to)?;
+        let result = match (self.mount_of(&from), self.mount_of(&to)) {
+            (None, None) => self.root.rename(&from, &to, user),
+            (Some((x, from)), Some((y, to))) if std::ptr::addr_eq(x, y) => {
+                x.rename(&from, &to, user)
+            }
+            _ => Err(FsError::CrossDevice),
+        };
+        // Everything moved is somewhere new
+        let mut moved = Vec::new();
+        if let (Ok(_), Ok((_, Step::Tree(node)))) =
+            (&result, walk(&self.root, &[], &to, &superuser(), false))
+        {
+            descendants(node, &to, &mut moved);
+        }
+        let paths: Vec<&Path> = [from.as_path(), &to]
+            .into_iter()
+            .chain(moved.iter().map(PathBuf::as_path))
+            .collect();
+        self.note(result, &paths)
+    }
+
+    fn unlink(&mut self, path: &Path, user: &User) -> Result<(), FsError> {
+        let path = self.resolve_parent(path, user)?;
+        self.check_busy(&path)?;
+        let result = match self.mount_of(&path) {
+            Some((mount, path)) => mount.unlink(&path, user),
+            None => self.root.unlink(&path, user),
+        };
+        self.note(result, &[&path])
+    }
+
+    fn remove_folder(&mut self, path: &Path, user: &User) -> Result<(), FsError> {
+        let path = self.resolve_parent(path, user)?;
+        self.check_busy(&path)?;
+        let result = match self.mount_of(&path) {
+            Some((mount, path)) => mount.remove_folder(&path, user),
+            None => self.root.remove_folder(&path, user),
+        };
+        self.note(result, &[&path])
+    }
+
+    fn index_children(&mut self) {
+        self.root.index_children();
+    }
+}
+
+/// The paths of everything in `node`, which is at `path`, however deep
+fn descendants(node: &SendTree<FSObject>, path: &Path, out: &mut Vec<PathBuf>) {
+    for child in (0..node.count()).filter_map(|i| node.get_child(i)) {
+        let path = path.join(child.get_value().read().unwrap().get_name());
+        descendants(child, &path, out);
+        out.push(path);
+    }
+}
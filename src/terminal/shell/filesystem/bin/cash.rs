@@ -1,25 +1,122 @@
 // CASH -- Cold's Awful SHell
 // Name derived from CA$H, the R.A.M Demo's (second) hardest challenge.
-fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<SessionMessage>)> {
+fn run() -> Program {
     use crate::key_events::*;
     use crate::session::SessionMessage;
-    use crate::session::{ReturnValue, ShellMessage, TerminalMessage};
+    use crate::session::{ShellMessage, TerminalMessage};
+    use crate::terminal::shell::cash_parse::{
+        expand_quotes, is_valid_name, parse, tokenize, Ast, Command, Token, Variables,
+    };
+    use crate::terminal::shell::files::{overlay, proc};
+    use crate::terminal::shell::stdio::output_text;
     use crate::terminal::style::{Color, Span};
+    use std::collections::{BTreeMap, VecDeque};
     use std::sync::mpsc::channel;
     use std::sync::mpsc::TryRecvError;
-    use std::thread::{self, JoinHandle};
+    use std::thread;
 
+    /// One program of the pipeline being run
     struct CashProcess {
-        pub thread: JoinHandle<()>,
+        /// Its ID in `/proc`, unless it's a builtin
+        pub pid: Option<usize>,
         pub sender: Sender<SessionMessage>,
+        /// Everything the program sends, to be routed by `executing_state`
+        pub output: Receiver<SessionMessage>,
+        pub stdout: Stdout,
+        /// Exit code, once it has finished
+        pub status: Option<u32>,
+        /// Whether key presses are passed on to it
+        pub keyboard: bool,
+    }
+
+    /// Where a running program's output goes
+    enum Stdout {
+        Terminal,
+        /// Into the standard input of the next program in the pipeline
+        Pipe,
+        /// Onto the end of the file at a path
+        File(PathBuf),
+    }
+
+    /// How many commands are remembered, in memory and in `~/.cash_history`
+    const HISTORY_SIZE: usize = 500;
+
+    /// How many new commands are kept before `~/.cash_history` is written. It's saved with
+    /// the rest of the filesystem, which isn't worth doing for every command.
+    const HISTORY_BATCH: usize = 10;
+
+    /// State of a Ctrl+R reverse incremental search
+    struct HistorySearch {
+        query: String,
+        /// Index into `history` of the current match
+        index: Option<usize>,
     }
 
     struct CashShellData {
         input: String,
+        /// Character index into `input`
+        cursor: usize,
         cwd: String,
-        running: Option<CashProcess>,
+        running: Vec<CashProcess>,
+        /// What's left of the command line once `running` finishes, innermost last
+        pending: Vec<Pending>,
+        history: VecDeque<String>,
+        /// Which history entry is being shown, if the user has pressed Up
+        history_index: Option<usize>,
+        /// What was typed before the user started moving through history
+        history_draft: String,
+        /// Commands added since `~/.cash_history` was last written
+        history_unsaved: usize,
+        search: Option<HistorySearch>,
+        /// Set when the last key was a Tab that couldn't complete anything
+        tab_pending: bool,
+        /// Variables passed on to every program CASH runs
+        env: Environment,
+        aliases: BTreeMap<String, String>,
+        /// Whether a pattern that matches nothing is an error, rather than left as it is.
+        /// See `builtin_set`.
+        failglob: bool,
+        /// Exit code of the last command, as `$?`
+        status: u32,
+        /// The script being run and its arguments, or just CASH's name when interactive
+        args: Vec<String>,
+        /// Whether CASH is running a script, and should exit once it's done
+        script: bool,
+        /// Code to exit with once `CashState::Interrupting` is reached
+        exit_code: u32,
+        /// Who files are read, written and run as. Updated before each pipeline, as
+        /// programs like `ssh` can change it.
+        user: User,
+        /// CASH's own ID in `/proc`, from `$PID`, which programs it starts are children of
+        pid: Option<usize>,
+        /// Set on the copy a builtin in a pipeline runs in, which mustn't change the
+        /// session or files on CASH's behalf. See `subshell`.
+        subshell: bool,
+    }
+
+    /// What became of running a command
+    enum Started {
+        /// A program, with its ID in `/proc`, which sends its exit code once it's done
+        Program(usize),
+        /// A builtin or nothing at all, which is already done with this exit code
+        Finished(u32),
     }
 
+    /// Run when CASH starts, before the user's `CASHRC`
+    const PROFILE: &str = "/etc/profile";
+
+    /// Run when CASH starts, from the user's home directory
+    const CASHRC: &str = ".cashrc";
+
+    /// Used when `PS1` isn't set. See `expand_prompt` for the escapes.
+    const DEFAULT_PS1: &str = "[\\u@\\h:\\w]\\$ ";
+
+    /// Names completed alongside the programs on the user's path
+    const BUILTINS: &[&str] = &[
+        ".", "alias", "cd", "exit", "export", "false", "history", "pwd", "set", "source", "true",
+        "unalias", "unset",
+    ];
+
     #[derive(Clone, Copy, Debug)]
     enum CashState {
         /// Display MOTD, for example
@@ -34,34 +131,265 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
         Interrupting,
     }
 
-    fn get_prefix(shell_tx: &Sender<SessionMessage>, data: &CashShellData) -> Span {
-        let path = &data.cwd;
-
-        let (tx, rx) = channel();
+    fn get_user(shell_tx: &Sender<SessionMessage>) -> User {
+        User::current(shell_tx)
+    }
 
+    fn push_line(shell_tx: &Sender<SessionMessage>, line: String) {
         shell_tx
-            .send(SessionMessage::Shell(
-                ShellMessage::GetCurrentUser,
-                Some(tx),
+            .send(SessionMessage::Terminal(
+                TerminalMessage::PushLine(line),
+                None,
             ))
             .unwrap();
+    }
+
+    /// Expands the bash-style escapes in a prompt string: `\u` (user), `\h` (host),
+    /// `\w` (CWD, with the home directory as `~`), `\W` (last part of the CWD),
+    /// `\$` and `\\`.
+    fn expand_prompt(ps1: &str, user: &User, cwd: &str) -> String {
+        let home = user.home_directory();
+        let home = home.trim_end_matches('/');
+        let short_cwd = match cwd.strip_prefix(home) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("~{rest}"),
+            _ => cwd.to_string(),
+        };
+
+        let mut out = String::new();
+        let mut chars = ps1.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('u') => out.push_str(user.get_name()),
+                Some('h') => out.push_str("deep-freezer"),
+                Some('w') => out.push_str(&short_cwd),
+                Some('W') => out.push_str(
+                    Path::new(&short_cwd)
+                        .file_name()
+                        .map(|x| x.to_string_lossy())
+                        .unwrap_or(short_cwd.as_str().into())
+                        .as_ref(),
+                ),
+                Some('$') => out.push('$'),
+                Some(x) => {
+                    out.push('\\');
+                    out.push(x);
+                }
+                None => out.push('\\'),
+            }
+        }
+        out
+    }
+
+    fn get_prefix(shell_tx: &Sender<SessionMessage>, data: &CashShellData) -> Span {
+        let user = get_user(shell_tx);
+        let ps1 = data
+            .env
+            .get("PS1")
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_PS1);
+
+        Span::new()
+            .with_text(expand_prompt(ps1, &user, &data.cwd))
+            .with_fg_color(Color::new_rgb(20, 160, 190))
+            .bold()
+    }
+
+    fn history_path(user: &User) -> PathBuf {
+        Path::new(&user.home_directory()).join(".cash_history")
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn history_storage_key(user: &User) -> String {
+        format!("cash_history:{}", user.get_name())
+    }
+
+    fn load_history(shell_tx: &Sender<SessionMessage>, data: &mut CashShellData) {
+        let user = get_user(shell_tx);
+
+        #[cfg(target_arch = "wasm32")]
+        let stored = web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .and_then(|storage| storage.get_item(&history_storage_key(&user)).ok().flatten());
+        #[cfg(not(target_arch = "wasm32"))]
+        let stored: Option<String> = None;
+
+        let stored = stored.or_else(|| {
+            FILESYSTEM
+                .read()
+                .unwrap()
+                .get_by_path(&history_path(&user), &user)
+                .ok()
+                .and_then(|file| match *file.read().unwrap() {
+                    FSObject::File {
+                        contents: FileType::Binary(ref bytes),
+                        ..
+                    } => Some(String::from_utf8_lossy(bytes).to_string()),
+                    _ => None,
+                })
+        });
+
+        data.history = stored
+            .iter()
+            .flat_map(|x| x.lines())
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect();
+        while data.history.len() > HISTORY_SIZE {
+            data.history.pop_front();
+        }
+    }
+
+    fn history_text(data: &CashShellData) -> String {
+        let mut text = Vec::from(data.history.clone()).join("\n");
+        text.push('\n');
+        text
+    }
 
-        if let SessionMessage::Return(ReturnValue::User(Some(user))) =
-            rx.recv().expect("Couldn't get user for CASH prefix")
+    /// Keeps the history in the browser's local storage, apart from the filesystem, which
+    /// is cheap enough to do after every command
+    #[cfg(target_arch = "wasm32")]
+    fn store_history(shell_tx: &Sender<SessionMessage>, data: &CashShellData) {
+        if let Some(storage) =
+            web_sys::window().and_then(|window| window.local_storage().ok().flatten())
         {
-            Span::new()
-                .with_text(format!(
-                    "[{}@deep-freezer:{}]$ ",
-                    user.get_name(),
-                    &path.replacen(format!("/home/{}", user.get_name()).as_str(), "~", 1)
-                ))
-                .with_fg_color(Color::new_rgb(20, 160, 190))
-                .bold()
+            storage
+                .set_item(
+                    &history_storage_key(&get_user(shell_tx)),
+                    &history_text(data),
+                )
+                .ok();
+        }
+    }
+
+    fn save_history(shell_tx: &Sender<SessionMessage>, data: &mut CashShellData) {
+        #[cfg(target_arch = "wasm32")]
+        store_history(shell_tx, data);
+
+        let user = get_user(shell_tx);
+        FILESYSTEM
+            .write()
+            .unwrap()
+            .write_file(
+                &history_path(&user),
+                history_text(data).as_bytes(),
+                false,
+                &user,
+            )
+            .ok();
+        data.history_unsaved = 0;
+    }
+
+    /// Saves the filesystem, if anything has changed, saying so if that fails
+    fn save_filesystem(shell_tx: &Sender<SessionMessage>) {
+        if let Err(e) = overlay::save() {
+            push_line(shell_tx, format!("cash: couldn't save the filesystem: {e}"));
+        }
+    }
+
+    fn add_history(shell_tx: &Sender<SessionMessage>, data: &mut CashShellData, command: &str) {
+        data.history_index = None;
+        data.history_draft.clear();
+
+        if command.trim().is_empty() || data.history.back().is_some_and(|x| x == command) {
+            return;
+        }
+
+        data.history.push_back(command.into());
+        while data.history.len() > HISTORY_SIZE {
+            data.history.pop_front();
+        }
+        data.history_unsaved += 1;
+        if data.history_unsaved >= HISTORY_BATCH {
+            save_history(shell_tx, data);
         } else {
-            unreachable!("get_prefix did not receive a return message");
+            #[cfg(target_arch = "wasm32")]
+            store_history(shell_tx, data);
         }
     }
 
+    fn history_up(data: &mut CashShellData) {
+        let index = match data.history_index {
+            _ if data.history.is_empty() => return,
+            None => {
+                data.history_draft = data.input.clone();
+                data.history.len() - 1
+            }
+            Some(i) => i.saturating_sub(1),
+        };
+        data.history_index = Some(index);
+        data.input = data.history[index].clone();
+        data.cursor = data.input.chars().count();
+    }
+
+    fn history_down(data: &mut CashShellData) {
+        let Some(index) = data.history_index else {
+            return;
+        };
+        if index + 1 < data.history.len() {
+            data.history_index = Some(index + 1);
+            data.input = data.history[index + 1].clone();
+        } else {
+            data.history_index = None;
+            data.input = std::mem::take(&mut data.history_draft);
+        }
+        data.cursor = data.input.chars().count();
+    }
+
+    /// Finds the newest history entry before `before` containing `query`
+    fn search_history(history: &VecDeque<String>, query: &str, before: usize) -> Option<usize> {
+        (0..before.min(history.len()))
+            .rev()
+            .find(|i| history[*i].contains(query))
+    }
+
+    /// Handles a key press during a Ctrl+R search. Returns false once the search is over,
+    /// leaving the match in `data.input`.
+    fn search_input(data: &mut CashShellData, key_event: &KeyEvent) -> bool {
+        let Some(search) = &mut data.search else {
+            return false;
+        };
+
+        match (&key_event.key_type, &key_event.modifier) {
+            (Key::Char('r'), Some(Modifier::Ctrl)) => {
+                let before = search.index.unwrap_or(data.history.len());
+                if let Some(i) = search_history(&data.history, &search.query, before) {
+                    search.index = Some(i);
+                }
+            }
+            (Key::Char('g'), Some(Modifier::Ctrl)) => {
+                data.search = None;
+                return false;
+            }
+            _ if key_event.typed_char().is_some() => {
+                search.query.extend(key_event.typed_char());
+                search.index = search_history(&data.history, &search.query, data.history.len());
+            }
+            (Key::Backspace, _) => {
+                search.query.pop();
+                search.index = search_history(&data.history, &search.query, data.history.len());
+            }
+            _ => {
+                if let Some(i) = search.index {
+                    data.input = data.history[i].clone();
+                    data.cursor = data.input.chars().count();
+                }
+                data.search = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    fn search_span(data: &CashShellData) -> Span {
+        let search = data.search.as_ref().unwrap();
+        let found = search.index.map(|i| data.history[i].as_str()).unwrap_or("");
+        Span::from(format!("(reverse-i-search)`{}': {}", search.query, found).as_str())
+    }
+
     fn state_transition(
         shell_tx: &Sender<SessionMessage>,
         state: Option<CashState>,
@@ -78,7 +406,7 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
                     .unwrap();
                 shell_tx
                     .send(SessionMessage::Terminal(
-                        TerminalMessage::PushSpan(Span::new()),
+                        TerminalMessage::PushSpan(Span::new().with_cursor(0)),
                         None,
                     ))
                     .unwrap();
@@ -97,6 +425,25 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
         data: &mut CashShellData,
         events: &Receiver<SessionMessage>,
     ) -> () {
+        if data.script {
+            let path = data.args[0].clone();
+            *state = state_transition(shell_tx, *state, data, CashState::Executing);
+            match read_script(data, &path) {
+                Ok(ast) => run_ast(shell_tx, state, data, ast),
+                Err(e) => {
+                    push_line(shell_tx, format!("cash: {e}"));
+                    data.exit_code = 127;
+                    *state = Some(CashState::Interrupting);
+                }
+            }
+            return;
+        }
+
+        data.env
+            .entry("PS1".into())
+            .or_insert_with(|| DEFAULT_PS1.into());
+        load_history(shell_tx, data);
+
         shell_tx
             .send(SessionMessage::Terminal(
                 TerminalMessage::PushSpan(
@@ -109,7 +456,245 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
             ))
             .unwrap();
 
-        *state = state_transition(shell_tx, *state, data, CashState::Input);
+        // Run the startup scripts as if they were `source`d, so they can set variables
+        // and aliases
+        let user = get_user(shell_tx);
+        let scripts = [
+            PathBuf::from(PROFILE),
+            Path::new(&user.home_directory()).join(CASHRC),
+        ];
+        for path in scripts.iter().rev() {
+            if FILESYSTEM.read().unwrap().get_by_path(path, &user).is_err() {
+                continue;
+            }
+            match read_script(data, &path.to_string_lossy()) {
+                Ok(Some(ast)) => data.pending.push(Pending::Next(Condition::Always, ast)),
+                Ok(None) => {}
+                Err(e) => push_line(shell_tx, format!("cash: {e}")),
+            }
+        }
+
+        *state = state_transition(shell_tx, *state, data, CashState::Executing);
+        run_ast(shell_tx, state, data, None);
+    }
+
+    /// Byte offset of the `cursor`th character of `input`
+    fn byte_index(input: &str, cursor: usize) -> usize {
+        input
+            .char_indices()
+            .nth(cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(input.len())
+    }
+
+    /// Character index of the start of the word before the cursor, readline style.
+    fn word_start(input: &str, cursor: usize) -> usize {
+        let chars: Vec<char> = input.chars().take(cursor).collect();
+        let mut i = chars.len();
+        while i > 0 && chars[i - 1] == ' ' {
+            i -= 1;
+        }
+        while i > 0 && chars[i - 1] != ' ' {
+            i -= 1;
+        }
+        i
+    }
+
+    fn edit_input(data: &mut CashShellData, key_event: &KeyEvent) {
+        let len = data.input.chars().count();
+        data.cursor = data.cursor.min(len);
+
+        match (&key_event.key_type, &key_event.modifier) {
+            (Key::Char('a'), Some(Modifier::Ctrl)) | (Key::Home, _) => {
+                data.cursor = 0;
+            }
+            (Key::Char('e'), Some(Modifier::Ctrl)) | (Key::End, _) => {
+                data.cursor = len;
+            }
+            (Key::Char('w'), Some(Modifier::Ctrl)) => {
+                let start = word_start(&data.input, data.cursor);
+                let range = byte_index(&data.input, start)..byte_index(&data.input, data.cursor);
+                data.input.replace_range(range, "");
+                data.cursor = start;
+            }
+            (Key::Char('u'), Some(Modifier::Ctrl)) => {
+                data.input
+                    .replace_range(..byte_index(&data.input, data.cursor), "");
+                data.cursor = 0;
+            }
+            (Key::Char('k'), Some(Modifier::Ctrl)) => {
+                data.input.truncate(byte_index(&data.input, data.cursor));
+            }
+            (Key::Char(_), _) => {
+                if let Some(ch) = key_event.typed_char() {
+                    data.input.insert(byte_index(&data.input, data.cursor), ch);
+                    data.cursor += 1;
+                }
+            }
+            (Key::Backspace, _) if data.cursor > 0 => {
+                data.cursor -= 1;
+                data.input.remove(byte_index(&data.input, data.cursor));
+            }
+            (Key::Delete, _) if data.cursor < len => {
+                data.input.remove(byte_index(&data.input, data.cursor));
+            }
+            (Key::Left, _) => {
+                data.cursor = data.cursor.saturating_sub(1);
+            }
+            (Key::Right, _) => {
+                data.cursor = (data.cursor + 1).min(len);
+            }
+            _ => {}
+        }
+    }
+
+    /// Entries of the folder at `path` as `(name, is_folder)`, sorted by name
+    fn folder_entries(path: &Path, user: &User) -> Vec<(String, bool)> {
+        let Ok(folder) = FILESYSTEM.read().unwrap().get_by_path(path, user) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<(String, bool)> = match *folder.read().unwrap() {
+            ref x @ FSObject::Folder { ref contents, .. } if x.allows(user, Access::Read) => {
+                contents
+                    .keys()
+                    .map(|name| {
+                        let is_folder = FILESYSTEM
+                            .read()
+                            .unwrap()
+                            .get_by_path(&path.join(name), user)
+                            .is_ok_and(|x| x.read().unwrap().is_folder());
+                        (name.clone(), is_folder)
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        entries.sort();
+        entries
+    }
+
+    fn is_program(path: &Path, user: &User) -> bool {
+        matches!(program_at(path, user), Ok(Some(_)))
+    }
+
+    /// Possible replacements for `word`, as the first word of a command
+    fn command_completions(data: &CashShellData, user: &User, word: &str) -> Vec<String> {
+        let mut out: Vec<String> = BUILTINS
+            .iter()
+            .filter(|x| x.starts_with(word))
+            .map(|x| format!("{x} "))
+            .collect();
+        let path = data.env.get("PATH").map(String::as_str).unwrap_or_default();
+        for root in path.split(':').filter(|root| !root.is_empty()) {
+            for (name, _) in folder_entries(Path::new(root), user) {
+                if name.starts_with(word) && is_program(&Path::new(root).join(&name), user) {
+                    out.push(format!("{name} "));
+                }
+            }
+        }
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    /// Possible replacements for `word`, as a path relative to the CWD
+    fn path_completions(user: &User, cwd: &str, word: &str) -> Vec<String> {
+        let (dir, prefix) = match word.rfind('/') {
+            Some(i) => word.split_at(i + 1),
+            None => ("", word),
+        };
+        let Ok(folder) = SendTree::resolve_path(
+            Path::new(if dir.is_empty() { "." } else { dir }),
+            Path::new(cwd),
+            Path::new(&user.home_directory()),
+        ) else {
+            return Vec::new();
+        };
+
+        folder_entries(&folder, user)
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .filter(|(name, _)| prefix.starts_with('.') || !name.starts_with('.'))
+            .map(|(name, is_folder)| format!("{dir}{name}{}", if is_folder { "/" } else { " " }))
+            .collect()
+    }
+
+    fn common_prefix(candidates: &[String]) -> String {
+        let mut prefix: Vec<char> = candidates[0].chars().collect();
+        for candidate in &candidates[1..] {
+            let len = prefix
+                .iter()
+                .zip(candidate.chars())
+                .take_while(|(a, b)| **a == *b)
+                .count();
+            prefix.truncate(len);
+        }
+        prefix.into_iter().collect()
+    }
+
+    /// Completes the word before the cursor. If it's ambiguous, the second Tab in a row
+    /// lists the candidates under the prompt.
+    fn complete(
+        shell_tx: &Sender<SessionMessage>,
+        state: &mut Option<CashState>,
+        data: &mut CashShellData,
+        tabbed: bool,
+    ) {
+        let before: String = data.input.chars().take(data.cursor).collect();
+        let start = before.rfind(' ').map(|i| i + 1).unwrap_or(0);
+        let word = &before[start..];
+        let user = get_user(shell_tx);
+
+        let candidates = if before[..start].trim().is_empty() && !word.contains('/') {
+            command_completions(data, &user, word)
+        } else {
+            path_completions(&user, &data.cwd, word)
+        };
+        if candidates.is_empty() {
+            return;
+        }
+
+        let completed = if candidates.len() == 1 {
+            candidates[0].clone()
+        } else {
+            common_prefix(&candidates)
+        };
+
+        if completed.len() > word.len() {
+            let end = byte_index(&data.input, data.cursor);
+            data.input.replace_range(start..end, &completed);
+            data.cursor = before[..start].chars().count() + completed.chars().count();
+        } else if tabbed {
+            let names: Vec<&str> = candidates
+                .iter()
+                .map(|x| x.trim_end_matches(' '))
+                .map(|x| match x.trim_end_matches('/').rfind('/') {
+                    Some(i) => &x[i + 1..],
+                    None => x,
+                })
+                .collect();
+            shell_tx
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::SetSpan(0, data.input.as_str().into()),
+                    None,
+                ))
+                .unwrap();
+            shell_tx
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushSpan("\r\n".into()),
+                    None,
+                ))
+                .unwrap();
+            shell_tx
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(names.join("  ")),
+                    None,
+                ))
+                .unwrap();
+            *state = state_transition(shell_tx, *state, data, CashState::Input);
+        } else {
+            data.tab_pending = true;
+        }
     }
 
     fn input_state(
@@ -120,18 +705,42 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
     ) -> () {
         match events.recv() {
             Ok(SessionMessage::Shell(ShellMessage::InputKeyEvent(key_event), _)) => {
-                match key_event.key_type {
-                    Key::Char(ch) => {
-                        if let Some(Modifier::Shift) = key_event.modifier {
-                            data.input.push(ch.to_ascii_uppercase());
-                        } else {
-                            data.input.push(ch.to_ascii_lowercase());
-                        }
-                    }
-                    Key::Backspace => {
-                        data.input.pop();
+                if data.search.is_some() && search_input(data, &key_event) {
+                    shell_tx
+                        .send(SessionMessage::Terminal(
+                            TerminalMessage::SetSpan(0, search_span(data)),
+                            None,
+                        ))
+                        .unwrap();
+                    shell_tx
+                        .send(SessionMessage::Terminal(TerminalMessage::ForceUpdate, None))
+                        .unwrap();
+                    return;
+                }
+
+                let tabbed = std::mem::take(&mut data.tab_pending);
+
+                match (key_event.key_type, key_event.modifier) {
+                    (Key::Tab, _) => complete(shell_tx, state, data, tabbed),
+                    (Key::Char('r'), Some(Modifier::Ctrl)) => {
+                        data.search = Some(HistorySearch {
+                            query: String::new(),
+                            index: None,
+                        });
+                        shell_tx
+                            .send(SessionMessage::Terminal(
+                                TerminalMessage::SetSpan(0, search_span(data)),
+                                None,
+                            ))
+                            .unwrap();
+                        shell_tx
+                            .send(SessionMessage::Terminal(TerminalMessage::ForceUpdate, None))
+                            .unwrap();
+                        return;
                     }
-                    Key::Enter => {
+                    (Key::Up, _) => history_up(data),
+                    (Key::Down, _) => history_down(data),
+                    (Key::Enter, _) => {
                         shell_tx
                             .send(SessionMessage::Terminal(
                                 TerminalMessage::SetSpan(0, data.input.as_str().into()),
@@ -144,10 +753,12 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
                                 None,
                             ))
                             .unwrap();
+                        let command = data.input.clone();
+                        add_history(shell_tx, data, &command);
                         *state = state_transition(shell_tx, *state, data, CashState::Evaluating);
                         return;
                     }
-                    _ => {}
+                    (key_type, modifier) => edit_input(data, &KeyEvent { key_type, modifier }),
                 }
             }
             _ => {}
@@ -155,7 +766,10 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
 
         shell_tx
             .send(SessionMessage::Terminal(
-                TerminalMessage::SetSpan(0, data.input.as_str().into()),
+                TerminalMessage::SetSpan(
+                    0,
+                    Span::from(data.input.as_str()).with_cursor(data.cursor),
+                ),
                 None,
             ))
             .unwrap();
@@ -165,60 +779,379 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
             .unwrap();
     }
 
-    enum ParsingState {
-        Normal,
-        String,
+    /// When to run the rest of a command line, once the part before it has finished
+    enum Condition {
+        Always,
+        Success,
+        Failure,
     }
 
-    fn args_parser(raw_input: &str) -> Result<Vec<String>, ()> {
-        let mut state = ParsingState::Normal;
-        let mut out = Vec::new();
-        let mut current = String::new();
+    /// Something left to do once the running pipeline finishes. See `pop_pending`.
+    enum Pending {
+        Next(Condition, Ast),
+        /// The branches of an `if`, waiting on its condition
+        Branch(Ast, Option<Ast>),
+        /// A `while` loop waiting on its condition, with the exit code of the last time
+        /// its body ran
+        WhileCondition(Ast, Ast, u32),
+        /// A `while` loop waiting on its body
+        WhileBody(Ast, Ast),
+        /// A `for` loop's variable, the values it has left (last first) and its body
+        For(String, Vec<String>, Ast),
+    }
 
-        let mut iter = raw_input.chars().peekable();
+    /// Removes the quotes from a word and fills in its variables. An unquoted word
+    /// that expands to nothing disappears. If it has wildcards outside quotes, it's
+    /// replaced by the paths they match, or left as it is if there are none.
+    fn expand_word(raw: &str, data: &CashShellData) -> Result<Vec<String>, String> {
+        let variables = Variables {
+            status: data.status,
+            args: &data.args,
+            env: &data.env,
+        };
+        let word =
+            expand_quotes(raw, &variables).map_err(|_| format!("{raw}: bad substitution"))?;
 
-        while let Some(c) = iter.next() {
-            match (c, &state) {
-                (' ', ParsingState::Normal) => {
-                    out.push(current);
-                    current = String::new();
+        if word.text.is_empty() && !word.quoted {
+            return Ok(Vec::new());
+        }
+        if glob::has_wildcards(&word.pattern) {
+            let home = data.env.get("HOME").cloned().unwrap_or_default();
+            let found = glob::expand(
+                &FILESYSTEM.read().unwrap(),
+                &word.pattern,
+                Path::new(&data.cwd),
+                Path::new(&home),
+                &data.user,
+            );
+            if !found.is_empty() {
+                return Ok(found);
+            }
+            if data.failglob {
+                return Err(format!("no match: {raw}"));
+            }
+        }
+        Ok(vec![word.text])
+    }
+
+    fn builtin_history(
+        shell_tx: &Sender<SessionMessage>,
+        out: &Sender<SessionMessage>,
+        data: &mut CashShellData,
+        args: &[String],
+    ) -> u32 {
+        let count = match args.first().map(String::as_str) {
+            Some("-c") => {
+                data.history.clear();
+                if !data.subshell {
+                    save_history(shell_tx, data);
                 }
-                (' ', ParsingState::String) => {
-                    current.push(c);
+                return 0;
+            }
+            Some(x) => match x.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => {
+                    push_line(shell_tx, format!("history: {x}: numeric argument required"));
+                    return 1;
                 }
-                ('\\', _) => {
-                    current.push(c);
-                    if let Some(ch) = iter.next() {
-                        current.push(ch);
-                    } else {
-                        return Err(());
-                    }
+            },
+            None => data.history.len(),
+        };
+
+        let skip = data.history.len().saturating_sub(count);
+        for (i, command) in data.history.iter().enumerate().skip(skip) {
+            push_line(out, format!("{:>5}  {}", i + 1, command));
+        }
+        0
+    }
+
+    fn builtin_cd(
+        shell_tx: &Sender<SessionMessage>,
+        out: &Sender<SessionMessage>,
+        data: &mut CashShellData,
+        args: &[String],
+    ) -> u32 {
+        let user = get_user(shell_tx);
+        let target = match args.first().map(String::as_str) {
+            None => user.home_directory(),
+            Some("-") => match data.env.get("OLDPWD") {
+                Some(x) => {
+                    push_line(out, x.clone());
+                    x.clone()
                 }
-                ('"', ParsingState::Normal) => {
-                    state = ParsingState::String;
+                None => {
+                    push_line(shell_tx, "cd: OLDPWD not set".into());
+                    return 1;
                 }
-                ('"', ParsingState::String) => {
-                    state = ParsingState::Normal;
-                    let peeked = iter.peek();
-                    if peeked.is_none() || peeked != Some(&' ') {
-                        return Err(());
-                    }
+            },
+            Some(x) => x.to_string(),
+        };
+
+        let path = SendTree::resolve_path(
+            Path::new(&target),
+            Path::new(&data.cwd),
+            Path::new(&user.home_directory()),
+        )
+        .and_then(|path| {
+            let folder = FILESYSTEM.read().unwrap().get_by_path(&path, &user)?;
+            let folder = folder.read().unwrap();
+            if !folder.is_folder() {
+                Err(FsError::NotADirectory)
+            } else if !folder.allows(&user, Access::Execute) {
+                Err(FsError::PermissionDenied)
+            } else {
+                Ok(path)
+            }
+        });
+        let path = match path {
+            Ok(path) => path,
+            Err(e) => {
+                push_line(shell_tx, format!("cd: {target}: {e}"));
+                return 1;
+            }
+        };
+
+        let cwd = path.to_string_lossy().to_string();
+        let old = std::mem::replace(&mut data.cwd, cwd.clone());
+        data.env.insert("OLDPWD".into(), old);
+        data.env.insert("PWD".into(), cwd);
+
+        if !data.subshell {
+            let (tx, rx) = channel();
+            shell_tx
+                .send(SessionMessage::Shell(
+                    ShellMessage::ChangeCwd(path),
+                    Some(tx),
+                ))
+                .unwrap();
+            rx.recv().ok();
+        }
+        0
+    }
+
+    fn builtin_exit(
+        shell_tx: &Sender<SessionMessage>,
+        state: &mut Option<CashState>,
+        data: &mut CashShellData,
+        args: &[String],
+    ) -> u32 {
+        let code = match args.first().map(|x| x.parse::<u32>()) {
+            None => 0,
+            Some(Ok(code)) => code,
+            Some(Err(_)) => {
+                push_line(
+                    shell_tx,
+                    format!("exit: {}: numeric argument required", args[0]),
+                );
+                2
+            }
+        };
+        data.exit_code = code;
+        *state = Some(CashState::Interrupting);
+        code
+    }
+
+    fn builtin_export(
+        shell_tx: &Sender<SessionMessage>,
+        out: &Sender<SessionMessage>,
+        data: &mut CashShellData,
+        args: &[String],
+    ) -> u32 {
+        if args.is_empty() {
+            for (name, value) in &data.env {
+                push_line(out, format!("export {name}=\"{value}\""));
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for arg in args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            if !is_valid_name(name) {
+                push_line(shell_tx, format!("export: `{arg}': not a valid identifier"));
+                status = 1;
+                continue;
+            }
+            let value = value
+                .map(String::from)
+                .or_else(|| data.env.get(name).cloned())
+                .unwrap_or_default();
+            data.env.insert(name.into(), value);
+        }
+        status
+    }
+
+    fn builtin_unset(data: &mut CashShellData, args: &[String]) -> u32 {
+        for name in args {
+            data.env.remove(name);
+        }
+        0
+    }
+
+    /// `set -o NAME` turns a shell option on and `set +o NAME` turns it off, and
+    /// `set -o` lists them. There's only `failglob`.
+    fn builtin_set(
+        shell_tx: &Sender<SessionMessage>,
+        out: &Sender<SessionMessage>,
+        data: &mut CashShellData,
+        args: &[String],
+    ) -> u32 {
+        match args {
+            [] => {
+                push_line(
+                    out,
+                    format!("failglob\t{}", if data.failglob { "on" } else { "off" }),
+                );
+                0
+            }
+            [flag] if flag == "-o" || flag == "+o" => builtin_set(shell_tx, out, data, &[]),
+            [flag, name] if flag == "-o" || flag == "+o" => {
+                if name != "failglob" {
+                    push_line(shell_tx, format!("set: {name}: invalid option name"));
+                    return 1;
                 }
-                _ => {
-                    current.push(c);
+                data.failglob = flag == "-o";
+                0
+            }
+            _ => {
+                push_line(shell_tx, "set: usage: set [-o|+o] [option]".into());
+                2
+            }
+        }
+    }
+
+    fn builtin_alias(
+        shell_tx: &Sender<SessionMessage>,
+        out: &Sender<SessionMessage>,
+        data: &mut CashShellData,
+        args: &[String],
+    ) -> u32 {
+        if args.is_empty() {
+            for (name, value) in &data.aliases {
+                push_line(out, format!("alias {name}='{value}'"));
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, value)) if is_valid_name(name) => {
+                    data.aliases.insert(name.into(), value.into());
                 }
+                Some(_) => {
+                    push_line(shell_tx, format!("alias: `{arg}': invalid alias name"));
+                    status = 1;
+                }
+                None => match data.aliases.get(arg) {
+                    Some(value) => push_line(out, format!("alias {arg}='{value}'")),
+                    None => {
+                        push_line(shell_tx, format!("alias: {arg}: not found"));
+                        status = 1;
+                    }
+                },
             }
         }
+        status
+    }
 
-        if let ParsingState::String = state {
-            return Err(());
+    fn builtin_unalias(
+        shell_tx: &Sender<SessionMessage>,
+        data: &mut CashShellData,
+        args: &[String],
+    ) -> u32 {
+        let mut status = 0;
+        for name in args {
+            if data.aliases.remove(name).is_none() {
+                push_line(shell_tx, format!("unalias: {name}: not found"));
+                status = 1;
+            }
         }
+        status
+    }
 
-        if current.len() > 0 {
-            out.push(current);
+    /// Replaces the first word of each command with its alias, if it has one.
+    /// Aliases can refer to other aliases, but not to themselves.
+    fn expand_aliases(data: &CashShellData, tokens: Vec<Token>) -> Vec<Token> {
+        let mut out = Vec::new();
+        let mut queue = VecDeque::from(tokens);
+        let mut command_start = true;
+        let mut seen = Vec::new();
+
+        while let Some(token) = queue.pop_front() {
+            match token {
+                Token::Word(word) if command_start => {
+                    let expanded = data
+                        .aliases
+                        .get(&word)
+                        .filter(|_| !seen.contains(&word))
+                        .and_then(|value| tokenize(value).ok())
+                        .filter(|expanded| !expanded.is_empty());
+                    if let Some(expanded) = expanded {
+                        seen.push(word);
+                        for token in expanded.into_iter().rev() {
+                            queue.push_front(token);
+                        }
+                        continue;
+                    }
+                    seen.clear();
+                    // Reserved words are followed by another command
+                    command_start =
+                        ["if", "then", "elif", "else", "do", "while"].contains(&word.as_str());
+                    out.push(Token::Word(word));
+                }
+                Token::Word(_) | Token::RightParen => out.push(token),
+                // The file name after a redirect is never an alias
+                Token::RedirectIn | Token::RedirectOut | Token::RedirectAppend => {
+                    out.push(token);
+                    out.extend(queue.pop_front());
+                }
+                _ => {
+                    command_start = true;
+                    out.push(token);
+                }
+            }
         }
+        out
+    }
 
-        Ok(out)
+    /// Runs `args` if it names a shell builtin, sending its output to `out`.
+    /// Returns its exit code if it did.
+    fn run_builtin(
+        shell_tx: &Sender<SessionMessage>,
+        out: &Sender<SessionMessage>,
+        state: &mut Option<CashState>,
+        data: &mut CashShellData,
+        args: &[String],
+    ) -> Option<u32> {
+        Some(match args[0].as_str() {
+            // Otherwise handled by `run_ast`, since the script runs in CASH itself
+            "." | "source" => {
+                push_line(
+                    shell_tx,
+                    format!("{}: cannot be part of a pipeline", args[0]),
+                );
+                1
+            }
+            "alias" => builtin_alias(shell_tx, out, data, &args[1..]),
+            "cd" => builtin_cd(shell_tx, out, data, &args[1..]),
+            "exit" => builtin_exit(shell_tx, state, data, &args[1..]),
+            "export" => builtin_export(shell_tx, out, data, &args[1..]),
+            "false" => 1,
+            "history" => builtin_history(shell_tx, out, data, &args[1..]),
+            "pwd" => {
+                push_line(out, data.cwd.clone());
+                0
+            }
+            "set" => builtin_set(shell_tx, out, data, &args[1..]),
+            "true" => 0,
+            "unalias" => builtin_unalias(shell_tx, data, &args[1..]),
+            "unset" => builtin_unset(data, &args[1..]),
+            _ => return None,
+        })
     }
 
     fn evaluating_state(
@@ -226,9 +1159,10 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
         state: &mut Option<CashState>,
         data: &mut CashShellData,
     ) -> () {
-        let args = args_parser(&data.input);
+        let tokens = tokenize(&data.input);
         data.input = String::new();
-        let Ok(mut args) = args else {
+        data.cursor = 0;
+        let Ok(tokens) = tokens else {
             shell_tx
                 .send(SessionMessage::Terminal(
                     TerminalMessage::PushLine("Error parsing input".into()),
@@ -239,71 +1173,503 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
             return;
         };
 
-        if args.len() == 0 {
-            *state = state_transition(shell_tx, *state, data, CashState::Input);
-            return;
+        let ast = match parse(expand_aliases(data, tokens)) {
+            Ok(Some(ast)) => ast,
+            Ok(None) => {
+                *state = state_transition(shell_tx, *state, data, CashState::Input);
+                return;
+            }
+            Err(e) => {
+                push_line(shell_tx, format!("cash: {e}"));
+                data.status = 2;
+                *state = state_transition(shell_tx, *state, data, CashState::Input);
+                return;
+            }
+        };
+
+        *state = state_transition(shell_tx, *state, data, CashState::Executing);
+        run_ast(shell_tx, state, data, Some(ast));
+    }
+
+    /// Runs `ast`, then whatever is pending, until a pipeline has been started for
+    /// `executing_state` to wait on. Once there's nothing left to run, goes back to
+    /// the prompt, or exits if running a script.
+    fn run_ast(
+        shell_tx: &Sender<SessionMessage>,
+        state: &mut Option<CashState>,
+        data: &mut CashShellData,
+        ast: Option<Ast>,
+    ) {
+        let mut next = ast;
+        loop {
+            let Some(ast) = next.take().or_else(|| pop_pending(data)) else {
+                if data.script {
+                    data.exit_code = data.status;
+                    *state = Some(CashState::Interrupting);
+                } else {
+                    *state = state_transition(shell_tx, *state, data, CashState::Input);
+                }
+                return;
+            };
+
+            match ast {
+                Ast::Pipeline(commands) if is_source(&commands) => {
+                    match source(data, &commands[0]) {
+                        Ok(script) => next = script,
+                        Err(e) => {
+                            push_line(shell_tx, format!("cash: {e}"));
+                            data.status = 1;
+                        }
+                    }
+                }
+                Ast::Pipeline(commands) => {
+                    spawn_pipeline(shell_tx, state, data, commands);
+                    return;
+                }
+                Ast::Sequence(first, rest) => {
+                    data.pending.push(Pending::Next(Condition::Always, *rest));
+                    next = Some(*first);
+                }
+                Ast::And(first, rest) => {
+                    data.pending.push(Pending::Next(Condition::Success, *rest));
+                    next = Some(*first);
+                }
+                Ast::Or(first, rest) => {
+                    data.pending.push(Pending::Next(Condition::Failure, *rest));
+                    next = Some(*first);
+                }
+                Ast::If(condition, then, otherwise) => {
+                    data.pending
+                        .push(Pending::Branch(*then, otherwise.map(|x| *x)));
+                    next = Some(*condition);
+                }
+                Ast::While(condition, body) => {
+                    data.pending
+                        .push(Pending::WhileCondition(*condition.clone(), *body, 0));
+                    next = Some(*condition);
+                }
+                Ast::For(name, words, body) => match expand_words(data, &words) {
+                    Ok(mut values) => {
+                        values.reverse();
+                        data.status = 0;
+                        data.pending.push(Pending::For(name, values, *body));
+                    }
+                    Err(e) => {
+                        push_line(shell_tx, format!("cash: {e}"));
+                        data.status = 1;
+                    }
+                },
+            }
         }
+    }
 
-        let (tx, rx) = channel();
+    /// Takes the next thing that should run from `data.pending`, given how the last
+    /// pipeline went. Parts of the command line that shouldn't run are skipped.
+    fn pop_pending(data: &mut CashShellData) -> Option<Ast> {
+        while let Some(pending) = data.pending.pop() {
+            match pending {
+                Pending::Next(condition, ast) => {
+                    let run = match condition {
+                        Condition::Always => true,
+                        Condition::Success => data.status == 0,
+                        Condition::Failure => data.status != 0,
+                    };
+                    if run {
+                        return Some(ast);
+                    }
+                }
+                Pending::Branch(then, otherwise) => {
+                    if data.status == 0 {
+                        return Some(then);
+                    }
+                    if otherwise.is_some() {
+                        return otherwise;
+                    }
+                    data.status = 0;
+                }
+                Pending::WhileCondition(condition, body, status) => {
+                    if data.status != 0 {
+                        data.status = status;
+                        continue;
+                    }
+                    data.pending
+                        .push(Pending::WhileBody(condition, body.clone()));
+                    return Some(body);
+                }
+                Pending::WhileBody(condition, body) => {
+                    data.pending.push(Pending::WhileCondition(
+                        condition.clone(),
+                        body,
+                        data.status,
+                    ));
+                    return Some(condition);
+                }
+                Pending::For(name, mut values, body) => {
+                    if let Some(value) = values.pop() {
+                        data.env.insert(name.clone(), value);
+                        data.pending.push(Pending::For(name, values, body.clone()));
+                        return Some(body);
+                    }
+                }
+            }
+        }
+        None
+    }
 
-        shell_tx
-            .send(SessionMessage::Shell(
-                ShellMessage::GetCurrentUser,
-                Some(tx),
-            ))
-            .unwrap();
+    /// Whether a pipeline is a `source` or `.`, which runs in CASH itself
+    fn is_source(commands: &[Command]) -> bool {
+        commands.len() == 1
+            && matches!(
+                commands[0].args.first().map(String::as_str),
+                Some("source" | ".")
+            )
+    }
 
-        if let Ok(SessionMessage::Return(ReturnValue::User(Some(user)))) = rx.recv() {
-            for root in user.get_path().split(':') {
-                let program = args.remove(0);
-                let path = Path::new(root).join(&program);
+    /// Reads the script a `source` command names, to run it in this shell
+    fn source(data: &CashShellData, command: &Command) -> Result<Option<Ast>, String> {
+        let args = expand_words(data, &command.args)?;
+        match args.get(1) {
+            Some(path) => read_script(data, path),
+            None => Err(format!("{}: filename argument required", args[0])),
+        }
+    }
 
-                if let Some(p) =
-                    FILESYSTEM
-                        .get_by_path(&path)
-                        .and_then(|x| match *x.read().unwrap() {
-                            FSObject::File {
-                                contents: FileType::Program(p),
-                                ..
-                            } => Some(p),
-                            _ => None,
-                        })
-                {
-                    let cwd = data.cwd.clone();
-                    let child_shell_tx = shell_tx.clone();
-
-                    let (child_tx, child_rx) = channel();
-
-                    *state = state_transition(shell_tx, *state, data, CashState::Executing);
-                    data.running = Some(CashProcess {
-                        thread: thread::spawn(move || {
-                            p()([vec![cwd], args].concat(), child_rx, child_shell_tx)
-                        }),
-                        sender: child_tx,
-                    });
-                    return;
+    /// Reads and parses a script from the filesystem
+    fn read_script(data: &CashShellData, path: &str) -> Result<Option<Ast>, String> {
+        let text = String::from_utf8(read_input(data, path)?)
+            .map_err(|_| format!("{path}: cannot execute binary file"))?;
+        let tokens = tokenize(&text).map_err(|_| format!("{path}: Error parsing script"))?;
+        parse(expand_aliases(data, tokens)).map_err(|e| format!("{path}: {e}"))
+    }
+
+    /// Expands each word of `words`, as in `expand_word`
+    fn expand_words(data: &CashShellData, words: &[String]) -> Result<Vec<String>, String> {
+        let mut out = Vec::new();
+        for raw in words {
+            out.extend(expand_word(raw, data)?);
+        }
+        Ok(out)
+    }
+
+    /// Starts every command of a pipeline. Each gets its own channel to send its
+    /// output down, which `executing_state` routes to the terminal, the next command
+    /// or a file. Builtins run straight away.
+    fn spawn_pipeline(
+        shell_tx: &Sender<SessionMessage>,
+        state: &mut Option<CashState>,
+        data: &mut CashShellData,
+        commands: Vec<Command>,
+    ) {
+        data.user = get_user(shell_tx);
+        let count = commands.len();
+        // Key presses go to whichever end of the pipeline reads the terminal
+        let keyboard = match commands[0].stdin {
+            Some(_) => count - 1,
+            None => 0,
+        };
+        for (i, command) in commands.into_iter().enumerate() {
+            let (out_tx, out_rx) = channel();
+            let (tx, rx) = channel();
+            let mut stdout = if i + 1 < count {
+                Stdout::Pipe
+            } else {
+                Stdout::Terminal
+            };
+
+            let started = match redirect(data, command, &tx) {
+                Ok((args, file)) => {
+                    if let Some(file) = file {
+                        stdout = Stdout::File(file);
+                    }
+                    if count > 1 {
+                        // Each part of a pipeline runs in a subshell, so builtins like
+                        // `cd` and `exit` don't change CASH itself
+                        run_command(shell_tx, &out_tx, &mut None, &mut subshell(data), args, rx)
+                    } else {
+                        run_command(shell_tx, &out_tx, state, data, args, rx)
+                    }
                 }
+                Err(e) => {
+                    push_line(shell_tx, format!("cash: {e}"));
+                    Started::Finished(1)
+                }
+            };
 
-                shell_tx
-                    .send(SessionMessage::Terminal(
-                        TerminalMessage::PushLine(
-                            format!("{}: not an executable file", program).into(),
-                        ),
-                        None,
-                    ))
-                    .unwrap();
-                *state = state_transition(shell_tx, *state, data, CashState::Input);
-                return;
+            let pid = match started {
+                Started::Program(pid) => Some(pid),
+                Started::Finished(status) => {
+                    out_tx
+                        .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                        .unwrap();
+                    None
+                }
+            };
+
+            data.running.push(CashProcess {
+                pid,
+                sender: tx,
+                output: out_rx,
+                stdout,
+                status: None,
+                keyboard: i == keyboard,
+            });
+        }
+    }
+
+    /// A copy of CASH's variables, aliases, history and where it is, with nothing
+    /// running or left to run
+    fn subshell(data: &CashShellData) -> CashShellData {
+        CashShellData {
+            input: String::new(),
+            cursor: 0,
+            cwd: data.cwd.clone(),
+            running: Vec::new(),
+            pending: Vec::new(),
+            history: data.history.clone(),
+            history_index: None,
+            history_draft: String::new(),
+            history_unsaved: 0,
+            search: None,
+            tab_pending: false,
+            env: data.env.clone(),
+            aliases: data.aliases.clone(),
+            failglob: data.failglob,
+            status: data.status,
+            args: data.args.clone(),
+            script: data.script,
+            exit_code: data.exit_code,
+            user: data.user.clone(),
+            pid: data.pid,
+            subshell: true,
+        }
+    }
+
+    /// Expands a command's words and sets up its redirections, sending the contents
+    /// of any input file to `stdin`. Returns its arguments and the file its output
+    /// goes to, if it isn't going to the terminal or a pipe.
+    fn redirect(
+        data: &CashShellData,
+        command: Command,
+        stdin: &Sender<SessionMessage>,
+    ) -> Result<(Vec<String>, Option<PathBuf>), String> {
+        let target = |raw: &str| match expand_words(data, &[raw.into()])?.as_slice() {
+            [path] => Ok(path.clone()),
+            _ => Err(format!("{raw}: ambiguous redirect")),
+        };
+
+        let args = expand_words(data, &command.args)?;
+
+        if let Some(source) = &command.stdin {
+            let bytes = read_input(data, &target(source)?)?;
+            stdin
+                .send(SessionMessage::Shell(ShellMessage::Stdin(bytes), None))
+                .unwrap();
+            stdin
+                .send(SessionMessage::Shell(ShellMessage::EndOfInput, None))
+                .unwrap();
+        }
+
+        let file = match &command.stdout {
+            Some((raw, append)) => Some(open_output(data, &target(raw)?, *append)?),
+            None => None,
+        };
+
+        Ok((args, file))
+    }
+
+    /// Runs a builtin, or starts a program on its own thread
+    ///
+    /// Leading `NAME=value` words set variables: only for the program if there is one,
+    /// otherwise in CASH itself.
+    fn run_command(
+        shell_tx: &Sender<SessionMessage>,
+        out: &Sender<SessionMessage>,
+        state: &mut Option<CashState>,
+        data: &mut CashShellData,
+        mut args: Vec<String>,
+        events: Receiver<SessionMessage>,
+    ) -> Started {
+        let assignments = args
+            .iter()
+            .take_while(|x| {
+                x.split_once('=')
+                    .is_some_and(|(name, _)| is_valid_name(name))
+            })
+            .count();
+        let variables: Vec<(String, String)> = args
+            .drain(..assignments)
+            .filter_map(|x| {
+                x.split_once('=')
+                    .map(|(name, value)| (name.into(), value.into()))
+            })
+            .collect();
+
+        // Only assignments or redirections, like `> file`
+        if args.is_empty() {
+            data.env.extend(variables);
+            return Started::Finished(0);
+        }
+
+        if BUILTINS.contains(&args[0].as_str()) {
+            data.env.extend(variables);
+            // Always some exit code, as it's a builtin
+            let status = run_builtin(shell_tx, out, state, data, &args);
+            return Started::Finished(status.unwrap_or_default());
+        }
+
+        match find_program(data, &mut args) {
+            Ok(program) => {
+                let pid = proc::spawn(data.pid, args.join(" "));
+                let mut env = data.env.clone();
+                env.extend(variables);
+                env.insert("PID".into(), pid.to_string());
+                let out = out.clone();
+                thread::spawn(move || program()(args, env, events, out));
+                Started::Program(pid)
+            }
+            Err(e) => {
+                push_line(shell_tx, e);
+                Started::Finished(127)
             }
         }
+    }
 
-        shell_tx
-            .send(SessionMessage::Terminal(
-                TerminalMessage::PushLine(format!("{}: command not found", args[0]).into()),
-                None,
-            ))
-            .unwrap();
-        *state = state_transition(shell_tx, *state, data, CashState::Input);
+    /// Resolves a path given on the command line against the CWD
+    fn resolve(data: &CashShellData, path: &str) -> Result<PathBuf, String> {
+        let home = data.env.get("HOME").cloned().unwrap_or_default();
+        SendTree::resolve_path(Path::new(path), Path::new(&data.cwd), Path::new(&home))
+            .map_err(|e| format!("{path}: {e}"))
+    }
+
+    /// Finds the file to redirect output to, emptying it unless appending.
+    /// The file is created if it doesn't exist.
+    fn open_output(data: &CashShellData, target: &str, append: bool) -> Result<PathBuf, String> {
+        let path = resolve(data, target)?;
+        FILESYSTEM
+            .write()
+            .unwrap()
+            .write_file(&path, &[], append, &data.user)
+            .map_err(|e| format!("{target}: {e}"))?;
+        Ok(path)
+    }
+
+    /// Reads the file to redirect input from
+    fn read_input(data: &CashShellData, source: &str) -> Result<Vec<u8>, String> {
+        let file = FILESYSTEM
+            .read()
+            .unwrap()
+            .get_by_path(&resolve(data, source)?, &data.user)
+            .map_err(|e| format!("{source}: {e}"))?;
+
+        let contents = match *file.write().unwrap() {
+            FSObject::Folder { .. } | FSObject::Symlink { .. } => {
+                Err(format!("{source}: {}", FsError::IsADirectory))
+            }
+            ref x if !x.allows(&data.user, Access::Read) => {
+                Err(format!("{source}: {}", FsError::PermissionDenied))
+            }
+            FSObject::File {
+                contents: FileType::Program(_),
+                ..
+            } => Err(format!("{source}: {}", FsError::PermissionDenied)),
+            FSObject::File {
+                contents: FileType::Binary(ref bytes),
+                ref mut times,
+                ..
+            } => {
+                times.accessed = now();
+                Ok(bytes.clone())
+            }
+        };
+        contents
+    }
+
+    /// A program to run, and its path if it's running a script for `#!`
+    type Runnable = (fn() -> Program, Option<String>);
+
+    /// Finds the program `args[0]` refers to: a path if it has a slash in it,
+    /// otherwise the first match in one of the folders in `PATH`. For a script, that's
+    /// the program on its `#!` line, and the script's path is added to `args`.
+    fn find_program(
+        data: &CashShellData,
+        args: &mut Vec<String>,
+    ) -> Result<fn() -> Program, String> {
+        let name = args[0].clone();
+        let (path, (program, interpreter)) = if name.contains('/') {
+            let path = resolve(data, &name)?;
+            match program_at(&path, &data.user) {
+                Ok(Some(found)) => (path, found),
+                Ok(None) => return Err(format!("{name}: not an executable file")),
+                Err(e) => return Err(format!("{name}: {e}")),
+            }
+        } else {
+            // Folders in `PATH` that are missing, or don't have it, are skipped. If it's
+            // only found where it can't be run, that's what's reported.
+            let mut error = None;
+            let path = data.env.get("PATH").map(String::as_str).unwrap_or_default();
+            path.split(':')
+                .filter(|root| !root.is_empty())
+                .find_map(|root| {
+                    let path = Path::new(root).join(&name);
+                    match program_at(&path, &data.user) {
+                        Ok(found) => found.map(|found| (path, found)),
+                        Err(FsError::NotFound | FsError::NotADirectory) => None,
+                        Err(e) => {
+                            error.get_or_insert(e);
+                            None
+                        }
+                    }
+                })
+                .ok_or_else(|| match error {
+                    Some(e) => format!("{name}: {e}"),
+                    None => format!("{name}: command not found"),
+                })?
+        };
+
+        if let Some(interpreter) = interpreter {
+            args[0] = path.to_string_lossy().into();
+            args.insert(0, interpreter);
+        }
+        Ok(program)
+    }
+
+    /// The program to run what's at `path` with, if it can be run. That's the file
+    /// itself for programs, and for scripts starting with a `#!` line, the program named
+    /// on it, along with its path. Either way `user` must be allowed to run the file.
+    fn program_at(path: &Path, user: &User) -> Result<Option<Runnable>, FsError> {
+        let file = FILESYSTEM.read().unwrap().get_by_path(path, user)?;
+        let interpreter = match *file.read().unwrap() {
+            ref x @ FSObject::File { .. } if !x.allows(user, Access::Execute) => {
+                return Err(FsError::PermissionDenied)
+            }
+            FSObject::File {
+                contents: FileType::Program(p),
+                ..
+            } => return Ok(Some((p, None))),
+            FSObject::File {
+                contents: FileType::Binary(ref bytes),
+                ..
+            } => shebang(bytes),
+            FSObject::Folder { .. } => return Err(FsError::IsADirectory),
+            FSObject::Symlink { .. } => None,
+        };
+
+        Ok(interpreter.and_then(
+            |interpreter| match program_at(Path::new(&interpreter), user) {
+                Ok(Some((p, None))) => Some((p, Some(interpreter))),
+                _ => None,
+            },
+        ))
+    }
+
+    /// The path on a script's `#!` line, like `/bin/cash`
+    fn shebang(bytes: &[u8]) -> Option<String> {
+        let line = bytes.strip_prefix(b"#!")?.split(|x| *x == b'\n').next()?;
+        std::str::from_utf8(line)
+            .ok()?
+            .split_whitespace()
+            .next()
+            .map(String::from)
     }
 
     fn executing_state(
@@ -313,33 +1679,161 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
         events: &Receiver<SessionMessage>,
     ) -> () {
         match events.try_recv() {
-            Ok(m) => {
-                if let SessionMessage::Shell(ShellMessage::ExitCode(_), _) = m {
-                    data.running = None;
-                    *state = state_transition(&shell_tx, *state, data, CashState::Input);
-                    return;
+            Ok(SessionMessage::Shell(ShellMessage::Resize(size), _)) => {
+                for process in &data.running {
+                    process
+                        .sender
+                        .send(SessionMessage::Shell(ShellMessage::Resize(size), None))
+                        .ok();
                 }
-                if let Some(running) = &data.running {
-                    if let Err(_) = running.sender.send(m) {
-                        data.running = None;
-                        *state = state_transition(&shell_tx, *state, data, CashState::Input);
+            }
+            Ok(m) => {
+                // Ctrl+C stops the rest of the command line, like the next turn of a loop
+                if let SessionMessage::Shell(ShellMessage::InputKeyEvent(ref key_event), _) = m {
+                    if let (Key::Char('c'), Some(Modifier::Ctrl)) =
+                        (&key_event.key_type, &key_event.modifier)
+                    {
+                        data.pending.clear();
                     }
                 }
+                if let Some(process) = data.running.iter().find(|x| x.keyboard) {
+                    process.sender.send(m).ok();
+                }
             }
             Err(TryRecvError::Disconnected) => {
-                data.running = None;
+                for pid in data.running.drain(..).filter_map(|x| x.pid) {
+                    proc::exit(pid);
+                }
+                data.pending.clear();
                 *state = state_transition(&shell_tx, *state, data, CashState::Input);
+                return;
             }
             _ => {}
         }
+
+        for i in 0..data.running.len() {
+            while data.running[i].status.is_none() {
+                let message = match data.running[i].output.try_recv() {
+                    Ok(message) => message,
+                    Err(TryRecvError::Empty) => break,
+                    // Finished without saying how it went
+                    Err(TryRecvError::Disconnected) => {
+                        SessionMessage::Shell(ShellMessage::ExitCode(0), None)
+                    }
+                };
+
+                match message {
+                    SessionMessage::Shell(ShellMessage::ExitCode(code), _) => {
+                        data.running[i].status = Some(code);
+                        if let Some(pid) = data.running[i].pid {
+                            proc::exit(pid);
+                        }
+                        if let Some(next) = data.running.get(i + 1) {
+                            next.sender
+                                .send(SessionMessage::Shell(ShellMessage::EndOfInput, None))
+                                .ok();
+                        }
+                        // Then key presses go to the end of the pipeline, which is what's on
+                        // the screen, like a pager
+                        if std::mem::take(&mut data.running[i].keyboard) {
+                            if let Some(last) =
+                                data.running.iter_mut().rev().find(|x| x.status.is_none())
+                            {
+                                last.keyboard = true;
+                            }
+                        }
+                    }
+                    SessionMessage::Terminal(message, ret) => {
+                        route_output(shell_tx, data, i, message, ret);
+                    }
+                    // Echoes included, which are shown by whatever runs the outermost shell
+                    message => shell_tx.send(message).unwrap(),
+                }
+            }
+        }
+
+        if data.running.iter().all(|x| x.status.is_some()) {
+            data.status = data.running.last().and_then(|x| x.status).unwrap_or(0);
+            data.running.clear();
+            save_filesystem(shell_tx);
+            // A full-screen program that stopped without leaving its screen mustn't keep it
+            shell_tx
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::LeaveAlternateScreen,
+                    None,
+                ))
+                .unwrap();
+            // `exit` may have run as part of the pipeline
+            if matches!(state, Some(CashState::Interrupting)) {
+                return;
+            }
+            run_ast(shell_tx, state, data, None);
+        }
+    }
+
+    /// Sends something the `i`th program of the pipeline wrote to wherever its output goes
+    fn route_output(
+        shell_tx: &Sender<SessionMessage>,
+        data: &CashShellData,
+        i: usize,
+        message: TerminalMessage,
+        ret: Option<Sender<SessionMessage>>,
+    ) {
+        match &data.running[i].stdout {
+            Stdout::Terminal => {
+                shell_tx
+                    .send(SessionMessage::Terminal(message, ret))
+                    .unwrap();
+            }
+            Stdout::Pipe => {
+                if let (Some(text), Some(next)) = (output_text(&message), data.running.get(i + 1)) {
+                    next.sender
+                        .send(SessionMessage::Shell(
+                            ShellMessage::Stdin(text.into_bytes()),
+                            None,
+                        ))
+                        .ok();
+                }
+            }
+            Stdout::File(path) => {
+                if let Some(text) = output_text(&message) {
+                    FILESYSTEM
+                        .write()
+                        .unwrap()
+                        .write_file(path, text.as_bytes(), true, &data.user)
+                        .ok();
+                }
+            }
+        }
     }
 
-    Box::new(move |args, events, shell_tx| {
+    Box::new(move |args, env, events, shell_tx| {
         let mut state: Option<CashState> = Some(CashState::Initial);
         let mut data = CashShellData {
-            cwd: args.first().unwrap().to_string(),
+            cwd: env.get("PWD").cloned().unwrap_or("/".into()),
+            pid: env.get("PID").and_then(|x| x.parse().ok()),
+            subshell: false,
             input: String::new(),
-            running: None,
+            cursor: 0,
+            running: Vec::new(),
+            pending: Vec::new(),
+            history: VecDeque::new(),
+            history_index: None,
+            history_draft: String::new(),
+            history_unsaved: 0,
+            search: None,
+            tab_pending: false,
+            env,
+            aliases: BTreeMap::new(),
+            failglob: false,
+            status: 0,
+            exit_code: 0,
+            user: get_user(&shell_tx),
+            script: args.len() > 1,
+            args: match args.len() {
+                0 | 1 => args,
+                _ => args[1..].to_vec(),
+            },
         };
 
         loop {
@@ -358,8 +1852,15 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
                 }
             }
         }
+        if data.history_unsaved > 0 {
+            save_history(&shell_tx, &mut data);
+            save_filesystem(&shell_tx);
+        }
         shell_tx
-            .send(SessionMessage::Shell(ShellMessage::ExitCode(0), None))
+            .send(SessionMessage::Shell(
+                ShellMessage::ExitCode(data.exit_code),
+                None,
+            ))
             .unwrap();
     })
 }
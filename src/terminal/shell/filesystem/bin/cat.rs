@@ -0,0 +1,50 @@
+fn run() -> Program {
+    use crate::terminal::shell::stdio::read_stdin;
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+        let mut files = args[1..].to_vec();
+        if files.is_empty() {
+            files.push("-".into());
+        }
+
+        let user = User::current(&sender);
+        let mut status = 0;
+        for name in files {
+            let contents = if name == "-" {
+                match read_stdin(&receiver, &sender) {
+                    Some(x) => Ok(x),
+                    None => return,
+                }
+            } else {
+                SendTree::resolve_path(Path::new(&name), Path::new(&cwd), Path::new(&home))
+                    .and_then(|path| FILESYSTEM.read().unwrap().read_file(&path, &user))
+            };
+
+            match contents {
+                Ok(bytes) => sender
+                    .send(SessionMessage::Terminal(
+                        TerminalMessage::Push(String::from_utf8_lossy(&bytes).into()),
+                        None,
+                    ))
+                    .unwrap(),
+                Err(e) => {
+                    sender
+                        .send(SessionMessage::Terminal(
+                            TerminalMessage::PushLine(format!("cat: {name}: {e}")),
+                            None,
+                        ))
+                        .unwrap();
+                    status = 1;
+                }
+            }
+        }
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+            .unwrap();
+    })
+}
@@ -0,0 +1,116 @@
+fn run() -> Program {
+    use crate::terminal::shell::files::STICKY;
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    /// Applies a mode like `755`, or `u+x,go-w,+t`, to `mode`
+    fn parse_mode(text: &str, mode: u32) -> Option<u32> {
+        if text.chars().all(|x| x.is_ascii_digit()) {
+            return u32::from_str_radix(text, 8).ok().filter(|x| *x <= 0o1777);
+        }
+
+        let mut mode = mode;
+        for clause in text.split(',') {
+            let split = clause.find(['+', '-', '='])?;
+            let (who, rest) = clause.split_at(split);
+            let mut who_mask = 0;
+            for x in who.chars() {
+                who_mask |= match x {
+                    'u' => 0o700,
+                    'g' => 0o070,
+                    'o' => 0o007,
+                    'a' => 0o777,
+                    _ => return None,
+                };
+            }
+            if who_mask == 0 {
+                who_mask = 0o777;
+            }
+
+            let (op, perms) = rest.split_at(1);
+            let mut bits = 0;
+            for x in perms.chars() {
+                bits |= match x {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    't' => STICKY,
+                    _ => return None,
+                };
+            }
+            bits &= who_mask | STICKY;
+            mode = match op {
+                "+" => mode | bits,
+                "-" => mode & !bits,
+                _ => (mode & !who_mask) | bits,
+            };
+        }
+        Some(mode)
+    }
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let error = |message: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("chmod: {message}")),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        let (mode, names) = match &args[1..] {
+            [] => {
+                error("missing operand".into());
+                return exit(1);
+            }
+            [mode] => {
+                error(format!("missing operand after '{mode}'"));
+                return exit(1);
+            }
+            [mode, names @ ..] => (mode, names),
+        };
+        if parse_mode(mode, 0).is_none() {
+            error(format!("invalid mode: '{mode}'"));
+            return exit(1);
+        }
+
+        let user = User::current(&sender);
+        let mut status = 0;
+        for name in names {
+            let file = SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home))
+                .and_then(|path| {
+                    let file = FILESYSTEM.read().unwrap().get_by_path(&path, &user)?;
+                    Ok((path, file))
+                });
+            let (path, file) = match file {
+                Ok(file) => file,
+                Err(e) => {
+                    error(format!("cannot access '{name}': {e}"));
+                    status = 1;
+                    continue;
+                }
+            };
+
+            let mut permissions = file.read().unwrap().get_permissions().clone();
+            permissions.mode = parse_mode(mode, permissions.mode).unwrap_or(permissions.mode);
+            let result = FILESYSTEM
+                .write()
+                .unwrap()
+                .set_permissions(&path, permissions, &user);
+            if let Err(e) = result {
+                error(format!("changing permissions of '{name}': {e}"));
+                status = 1;
+            }
+        }
+
+        exit(status)
+    })
+}
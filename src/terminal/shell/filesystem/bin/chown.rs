@@ -0,0 +1,86 @@
+fn run() -> Program {
+    use crate::terminal::shell::user::USERS;
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let error = |message: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("chown: {message}")),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        let (owner, names) = match &args[1..] {
+            [] => {
+                error("missing operand".into());
+                return exit(1);
+            }
+            [owner] => {
+                error(format!("missing operand after '{owner}'"));
+                return exit(1);
+            }
+            [owner, names @ ..] => (owner, names),
+        };
+
+        // OWNER, OWNER:GROUP or :GROUP. Every user has a group named after them.
+        let (owner, group) = match owner.split_once(':') {
+            Some((owner, group)) => (owner, Some(group)),
+            None => (owner.as_str(), None),
+        };
+        if !owner.is_empty() && !USERS.contains_key(owner) {
+            error(format!("invalid user: '{owner}'"));
+            return exit(1);
+        }
+        if let Some(group) = group.filter(|x| !x.is_empty() && !USERS.contains_key(x)) {
+            error(format!("invalid group: '{group}'"));
+            return exit(1);
+        }
+
+        let user = User::current(&sender);
+        let mut status = 0;
+        for name in names {
+            let file = SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home))
+                .and_then(|path| {
+                    let file = FILESYSTEM.read().unwrap().get_by_path(&path, &user)?;
+                    Ok((path, file))
+                });
+            let (path, file) = match file {
+                Ok(file) => file,
+                Err(e) => {
+                    error(format!("cannot access '{name}': {e}"));
+                    status = 1;
+                    continue;
+                }
+            };
+
+            let mut permissions = file.read().unwrap().get_permissions().clone();
+            if !owner.is_empty() {
+                permissions.owner = owner.into();
+            }
+            if let Some(group) = group.filter(|x| !x.is_empty()) {
+                permissions.group = group.into();
+            }
+            let result = FILESYSTEM
+                .write()
+                .unwrap()
+                .set_permissions(&path, permissions, &user);
+            if let Err(e) = result {
+                error(format!("changing ownership of '{name}': {e}"));
+                status = 1;
+            }
+        }
+
+        exit(status)
+    })
+}
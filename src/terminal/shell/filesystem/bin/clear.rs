@@ -1,10 +1,10 @@
-fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<SessionMessage>)> {
+fn run() -> Program {
     use crate::key_events::*;
     use crate::terminal::{
         style::{Color, Span},
         ShellMessage, TerminalMessage,
     };
-    Box::new(move |args, receiver, sender| {
+    Box::new(move |_args, _env, _receiver, sender| {
         sender
             .send(SessionMessage::Terminal(TerminalMessage::Clear, None))
             .unwrap();
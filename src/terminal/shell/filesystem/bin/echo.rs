@@ -0,0 +1,28 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, _env, _receiver, sender| {
+        let mut args = &args[1..];
+        let newline = args.first().map(String::as_str) != Some("-n");
+        if !newline {
+            args = &args[1..];
+        }
+
+        let text = args.join(" ");
+        sender
+            .send(SessionMessage::Terminal(
+                if newline {
+                    TerminalMessage::PushLine(text)
+                } else {
+                    TerminalMessage::Push(text)
+                },
+                None,
+            ))
+            .unwrap();
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(0), None))
+            .unwrap();
+    })
+}
@@ -0,0 +1,504 @@
+fn run() -> Program {
+    use crate::key_events::*;
+    use crate::terminal::shell::stdio::terminal_size;
+    use crate::terminal::style::{Color, Span};
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+    use crate::utils::pos::Size;
+    use std::sync::mpsc::Receiver;
+
+    /// Rows taken up by the title bar, the status line and the help line
+    const CHROME: usize = 3;
+    const TAB_WIDTH: usize = 4;
+    const HELP: [(&str, &str); 7] = [
+        ("^S", "Save"),
+        ("^O", "Save As"),
+        ("^X", "Exit"),
+        ("^W", "Search"),
+        ("^K", "Cut Line"),
+        ("^Y", "Prev Page"),
+        ("^V", "Next Page"),
+    ];
+
+    struct Editor {
+        lines: Vec<Vec<char>>,
+        /// The name it was opened or last saved as, and where that is
+        file: Option<(String, PathBuf)>,
+        /// Whether the file ended in a newline, which saving keeps
+        trailing_newline: bool,
+        modified: bool,
+        row: usize,
+        column: usize,
+        /// The column moving up and down tries to keep to
+        wanted: usize,
+        /// The first line and column shown
+        top: usize,
+        left: usize,
+        size: Size,
+        /// Shown on the status line until the next key
+        message: String,
+        /// A question being asked on the status line, and what's been typed so far
+        prompt: Option<(String, String)>,
+        search: String,
+    }
+
+    impl Editor {
+        fn text_rows(&self) -> usize {
+            self.size.height().saturating_sub(CHROME).max(1)
+        }
+
+        fn gutter(&self) -> usize {
+            self.lines.len().to_string().len().max(3) + 1
+        }
+
+        /// Scrolls so the cursor is on screen
+        fn follow_cursor(&mut self) {
+            let rows = self.text_rows();
+            let columns = self.size.width().saturating_sub(self.gutter()).max(1);
+            self.top = self
+                .top
+                .min(self.row)
+                .max((self.row + 1).saturating_sub(rows));
+            self.left = self
+                .left
+                .min(self.column)
+                .max((self.column + 1).saturating_sub(columns));
+        }
+
+        fn line_length(&self) -> usize {
+            self.lines[self.row].len()
+        }
+
+        fn contents(&self) -> Vec<u8> {
+            let mut text = self
+                .lines
+                .iter()
+                .map(|x| x.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n");
+            if self.trailing_newline {
+                text.push('\n');
+            }
+            text.into_bytes()
+        }
+    }
+
+    fn send(sender: &Sender<SessionMessage>, message: TerminalMessage) {
+        sender
+            .send(SessionMessage::Terminal(message, None))
+            .unwrap();
+    }
+
+    fn draw(editor: &mut Editor, sender: &Sender<SessionMessage>) {
+        editor.follow_cursor();
+        let width = editor.size.width();
+        let height = editor.size.height();
+        let gutter = editor.gutter();
+        let inverted = |text: String| {
+            Span::from(text.as_str())
+                .with_fg_color(Color::new_rgb(20, 20, 20))
+                .with_bg_color(Color::new_rgb(200, 200, 200))
+        };
+
+        send(sender, TerminalMessage::Clear);
+        let name = editor.file.as_ref().map_or("New Buffer", |(x, _)| x);
+        let title = format!(
+            " edit  {name}{}",
+            if editor.modified { "  [Modified]" } else { "" }
+        );
+        send(
+            sender,
+            TerminalMessage::PushSpan(inverted(format!("{title:<width$}"))),
+        );
+
+        for i in 0..editor.text_rows() {
+            let Some(line) = editor.lines.get(editor.top + i) else {
+                break;
+            };
+            send(sender, TerminalMessage::MoveCursor(0, i + 1));
+            send(
+                sender,
+                TerminalMessage::PushSpan(
+                    Span::from(format!("{:>1$} ", editor.top + i + 1, gutter - 1).as_str())
+                        .with_fg_color(Color::new_rgb(110, 110, 110)),
+                ),
+            );
+            let text: String = line
+                .iter()
+                .skip(editor.left)
+                .take(width.saturating_sub(gutter))
+                .map(|x| if *x == '\t' { ' ' } else { *x })
+                .collect();
+            send(sender, TerminalMessage::PushSpan(Span::from(text.as_str())));
+        }
+
+        send(
+            sender,
+            TerminalMessage::MoveCursor(0, height.saturating_sub(2)),
+        );
+        let cursor = match &editor.prompt {
+            Some((label, input)) => {
+                let text = format!("{label}{input}");
+                let column = text.chars().count();
+                send(
+                    sender,
+                    TerminalMessage::PushSpan(inverted(format!("{text:<width$}"))),
+                );
+                (column, height.saturating_sub(2))
+            }
+            None => {
+                if !editor.message.is_empty() {
+                    let message = format!("[ {} ]", editor.message);
+                    send(sender, TerminalMessage::PushSpan(inverted(message)));
+                }
+                (
+                    gutter + editor.column - editor.left,
+                    editor.row - editor.top + 1,
+                )
+            }
+        };
+
+        send(
+            sender,
+            TerminalMessage::MoveCursor(0, height.saturating_sub(1)),
+        );
+        for (key, action) in HELP {
+            send(sender, TerminalMessage::PushSpan(inverted(key.into())));
+            send(
+                sender,
+                TerminalMessage::PushSpan(Span::from(format!(" {action}  ").as_str())),
+            );
+        }
+
+        send(sender, TerminalMessage::MoveCursor(cursor.0, cursor.1));
+        send(sender, TerminalMessage::ForceUpdate);
+    }
+
+    /// Waits for the next key, redrawing if the terminal changes size meanwhile. `None`
+    /// if the shell has gone.
+    fn next_key(
+        editor: &mut Editor,
+        receiver: &Receiver<SessionMessage>,
+        sender: &Sender<SessionMessage>,
+    ) -> Option<KeyEvent> {
+        loop {
+            match receiver.recv().ok()? {
+                SessionMessage::Shell(ShellMessage::InputKeyEvent(key), _) => return Some(key),
+                SessionMessage::Shell(ShellMessage::Resize(size), _) => {
+                    editor.size = size;
+                    draw(editor, sender);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Asks for a line of text on the status line, starting with `initial`. `None` if
+    /// cancelled with Ctrl+C.
+    fn ask(
+        editor: &mut Editor,
+        label: &str,
+        initial: &str,
+        receiver: &Receiver<SessionMessage>,
+        sender: &Sender<SessionMessage>,
+    ) -> Option<String> {
+        editor.prompt = Some((label.into(), initial.into()));
+        let answer = loop {
+            draw(editor, sender);
+            let Some(key) = next_key(editor, receiver, sender) else {
+                break None;
+            };
+            let input = &mut editor.prompt.as_mut().unwrap().1;
+            match (&key.key_type, &key.modifier) {
+                (Key::Enter, _) => break Some(input.clone()),
+                (Key::Char('c'), Some(Modifier::Ctrl)) => break None,
+                (Key::Backspace, _) => {
+                    input.pop();
+                }
+                _ => input.extend(key.typed_char()),
+            }
+        };
+        editor.prompt = None;
+        answer
+    }
+
+    /// Writes the file out, asking what to call it if it hasn't got a name or `rename`
+    /// is set. Whether it was written.
+    fn save(
+        editor: &mut Editor,
+        rename: bool,
+        env: &Environment,
+        receiver: &Receiver<SessionMessage>,
+        sender: &Sender<SessionMessage>,
+    ) -> bool {
+        let file = match editor.file.clone() {
+            Some(file) if !rename => file,
+            file => {
+                let initial = file.map(|(x, _)| x).unwrap_or_default();
+                let Some(name) = ask(editor, "File Name to Write: ", &initial, receiver, sender)
+                else {
+                    editor.message = "Cancelled".into();
+                    return false;
+                };
+                let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+                let home = env.get("HOME").cloned().unwrap_or_default();
+                match SendTree::resolve_path(Path::new(&name), Path::new(&cwd), Path::new(&home)) {
+                    Ok(path) => (name, path),
+                    Err(e) => {
+                        editor.message = format!("Error writing {name}: {e}");
+                        return false;
+                    }
+                }
+            }
+        };
+
+        let user = User::current(sender);
+        let written =
+            FILESYSTEM
+                .write()
+                .unwrap()
+                .write_file(&file.1, &editor.contents(), false, &user);
+        match written {
+            Ok(()) => {
+                let count = editor.lines.len();
+                editor.message = format!("Wrote {count} line{}", if count == 1 { "" } else { "s" });
+                editor.file = Some(file);
+                editor.modified = false;
+                true
+            }
+            Err(e) => {
+                editor.message = format!("Error writing {}: {e}", file.0);
+                false
+            }
+        }
+    }
+
+    /// Moves to the next place `query` is found after the cursor, going round to the top
+    fn search(editor: &mut Editor, query: &str) {
+        let query: Vec<char> = query.chars().collect();
+        let count = editor.lines.len();
+        // The cursor's own line is looked at again last, before the cursor
+        for i in 0..=count {
+            let row = (editor.row + i) % count;
+            let line = &editor.lines[row];
+            let from = if i == 0 { editor.column + 1 } else { 0 };
+            let found = (from..=line.len().saturating_sub(query.len()))
+                .find(|x| line[*x..].starts_with(&query))
+                .filter(|x| i < count || *x < editor.column);
+            if let Some(column) = found {
+                editor.row = row;
+                editor.column = column;
+                editor.wanted = column;
+                return;
+            }
+        }
+        editor.message = format!("\"{}\" not found", query.iter().collect::<String>());
+    }
+
+    /// Changes the text or moves the cursor for a key that doesn't need asking anything
+    fn edit(editor: &mut Editor, key: &KeyEvent) {
+        let length = editor.line_length();
+        let (row, column) = (editor.row, editor.column);
+        let mut vertical = false;
+        match (&key.key_type, &key.modifier) {
+            (Key::Left, _) if column > 0 => editor.column -= 1,
+            (Key::Left, _) if row > 0 => {
+                editor.row -= 1;
+                editor.column = editor.line_length();
+            }
+            (Key::Right, _) if column < length => editor.column += 1,
+            (Key::Right, _) if row + 1 < editor.lines.len() => {
+                editor.row += 1;
+                editor.column = 0;
+            }
+            (Key::Up, _) => {
+                editor.row = row.saturating_sub(1);
+                vertical = true;
+            }
+            (Key::Down, _) => {
+                editor.row = (row + 1).min(editor.lines.len() - 1);
+                vertical = true;
+            }
+            (Key::Char('y'), Some(Modifier::Ctrl)) => {
+                editor.row = row.saturating_sub(editor.text_rows());
+                vertical = true;
+            }
+            (Key::Char('v'), Some(Modifier::Ctrl)) => {
+                editor.row = (row + editor.text_rows()).min(editor.lines.len() - 1);
+                vertical = true;
+            }
+            (Key::Home, _) | (Key::Char('a'), Some(Modifier::Ctrl)) => editor.column = 0,
+            (Key::End, _) | (Key::Char('e'), Some(Modifier::Ctrl)) => editor.column = length,
+            (Key::Enter, _) => {
+                let rest = editor.lines[row].split_off(column);
+                editor.lines.insert(row + 1, rest);
+                editor.row += 1;
+                editor.column = 0;
+                editor.modified = true;
+            }
+            (Key::Backspace, _) if column > 0 => {
+                editor.lines[row].remove(column - 1);
+                editor.column -= 1;
+                editor.modified = true;
+            }
+            (Key::Backspace, _) if row > 0 => {
+                let line = editor.lines.remove(row);
+                editor.row -= 1;
+                editor.column = editor.line_length();
+                editor.lines[row - 1].extend(line);
+                editor.modified = true;
+            }
+            (Key::Delete, _) if column < length => {
+                editor.lines[row].remove(column);
+                editor.modified = true;
+            }
+            (Key::Delete, _) if row + 1 < editor.lines.len() => {
+                let line = editor.lines.remove(row + 1);
+                editor.lines[row].extend(line);
+                editor.modified = true;
+            }
+            (Key::Char('k'), Some(Modifier::Ctrl)) => {
+                editor.lines.remove(row);
+                if editor.lines.is_empty() {
+                    editor.lines.push(Vec::new());
+                }
+                editor.row = row.min(editor.lines.len() - 1);
+                editor.column = 0;
+                editor.modified = true;
+            }
+            (Key::Tab, _) => {
+                let spaces = TAB_WIDTH - column % TAB_WIDTH;
+                editor.lines[row]
+                    .splice(column..column, [' '; TAB_WIDTH][..spaces].iter().copied());
+                editor.column += spaces;
+                editor.modified = true;
+            }
+            _ => {
+                if let Some(c) = key.typed_char() {
+                    editor.lines[row].insert(column, c);
+                    editor.column += 1;
+                    editor.modified = true;
+                }
+            }
+        }
+        if vertical {
+            editor.column = editor.wanted.min(editor.line_length());
+        } else {
+            editor.wanted = editor.column;
+        }
+    }
+
+    Box::new(move |args, env, receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+        let exit = |status: u32| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+        let error = |message: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("edit: {message}")),
+                    None,
+                ))
+                .unwrap();
+        };
+
+        if args.len() > 2 {
+            error(format!("extra operand '{}'", args[2]));
+            return exit(1);
+        }
+        let user = User::current(&sender);
+        let mut editor = Editor {
+            lines: vec![Vec::new()],
+            file: None,
+            trailing_newline: true,
+            modified: false,
+            row: 0,
+            column: 0,
+            wanted: 0,
+            top: 0,
+            left: 0,
+            size: terminal_size(&sender).unwrap_or(Size::new(80, 24)),
+            message: String::new(),
+            prompt: None,
+            search: String::new(),
+        };
+        if let Some(name) = args.get(1) {
+            let path =
+                match SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home)) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        error(format!("{name}: {e}"));
+                        return exit(1);
+                    }
+                };
+            match FILESYSTEM.read().unwrap().read_file(&path, &user) {
+                Ok(bytes) => {
+                    let text = String::from_utf8_lossy(&bytes);
+                    let text = text.strip_suffix('\n');
+                    editor.trailing_newline = text.is_some() || bytes.is_empty();
+                    let text = text.map_or(String::from_utf8_lossy(&bytes), Into::into);
+                    editor.lines = text.split('\n').map(|x| x.chars().collect()).collect();
+                    let count = editor.lines.len();
+                    editor.message =
+                        format!("Read {count} line{}", if count == 1 { "" } else { "s" });
+                }
+                Err(FsError::NotFound) => editor.message = "New File".into(),
+                Err(e) => {
+                    error(format!("{name}: {e}"));
+                    return exit(1);
+                }
+            }
+            editor.file = Some((name.clone(), path));
+        }
+
+        send(&sender, TerminalMessage::EnterAlternateScreen);
+        send(&sender, TerminalMessage::ShowCursor(true));
+        loop {
+            draw(&mut editor, &sender);
+            let Some(key) = next_key(&mut editor, &receiver, &sender) else {
+                return;
+            };
+            editor.message.clear();
+            match (&key.key_type, &key.modifier) {
+                (Key::Char('s'), Some(Modifier::Ctrl)) => {
+                    save(&mut editor, false, &env, &receiver, &sender);
+                }
+                (Key::Char('o'), Some(Modifier::Ctrl)) => {
+                    save(&mut editor, true, &env, &receiver, &sender);
+                }
+                (Key::Char('w' | 'f'), Some(Modifier::Ctrl)) => {
+                    let last = editor.search.clone();
+                    match ask(&mut editor, "Search: ", &last, &receiver, &sender) {
+                        Some(query) if !query.is_empty() => {
+                            search(&mut editor, &query);
+                            editor.search = query;
+                        }
+                        _ => editor.message = "Cancelled".into(),
+                    }
+                }
+                (Key::Char('x'), Some(Modifier::Ctrl)) => {
+                    if !editor.modified {
+                        break;
+                    }
+                    let question = "Save modified buffer? (y/n, ^C to cancel) ";
+                    match ask(&mut editor, question, "", &receiver, &sender) {
+                        Some(x) if x.eq_ignore_ascii_case("y") => {
+                            if save(&mut editor, false, &env, &receiver, &sender) {
+                                break;
+                            }
+                        }
+                        Some(x) if x.eq_ignore_ascii_case("n") => break,
+                        _ => editor.message = "Cancelled".into(),
+                    }
+                }
+                _ => edit(&mut editor, &key),
+            }
+        }
+
+        send(&sender, TerminalMessage::LeaveAlternateScreen);
+        exit(0);
+    })
+}
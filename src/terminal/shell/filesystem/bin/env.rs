@@ -0,0 +1,19 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |_args, env, _receiver, sender| {
+        for (name, value) in env {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("{name}={value}")),
+                    None,
+                ))
+                .unwrap();
+        }
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(0), None))
+            .unwrap();
+    })
+}
@@ -0,0 +1,170 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    /// Something found, with its path shown the way the starting point was given
+    struct Found {
+        shown: String,
+        name: String,
+        kind: char,
+        /// Why what's in it couldn't be found
+        error: Option<FsError>,
+    }
+
+    fn kind(file: &FSObject) -> char {
+        match file {
+            FSObject::Folder { .. } => 'd',
+            FSObject::Symlink { .. } => 'l',
+            FSObject::File { .. } => 'f',
+        }
+    }
+
+    /// Adds what's in the folder at `path` under `tree`, down to `depth` more folders.
+    /// Symlinks aren't followed.
+    fn branch(
+        tree: &mut SendTree<Found>,
+        path: &Path,
+        shown: &str,
+        depth: Option<usize>,
+        user: &User,
+    ) {
+        if depth == Some(0) {
+            return;
+        }
+        let Ok(folder) = FILESYSTEM.read().unwrap().get_link_by_path(path, user) else {
+            return;
+        };
+        let folder = folder.read().unwrap();
+        let FSObject::Folder { ref contents, .. } = *folder else {
+            return;
+        };
+        if !folder.allows(user, Access::Read) {
+            tree.get_value().write().unwrap().error = Some(FsError::PermissionDenied);
+            return;
+        }
+        let mut names: Vec<&String> = contents.keys().collect();
+        names.sort();
+        for name in names {
+            let child = path.join(name);
+            let Ok(file) = FILESYSTEM.read().unwrap().get_link_by_path(&child, user) else {
+                continue;
+            };
+            let shown = format!("{}/{name}", shown.trim_end_matches('/'));
+            let mut node = SendTree::new(Found {
+                shown: shown.clone(),
+                name: name.clone(),
+                kind: kind(&file.read().unwrap()),
+                error: None,
+            });
+            branch(&mut node, &child, &shown, depth.map(|x| x - 1), user);
+            tree.insert_child(node);
+        }
+    }
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status: u32| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        // Starting points come first, then -name PATTERN, -type f, d or l, and
+        // -maxdepth N
+        let mut starts = Vec::new();
+        let mut name = None;
+        let mut kind_wanted = None;
+        let mut depth = None;
+        let mut args = args[1..].iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') || arg == "-" {
+                starts.push(arg.clone());
+                continue;
+            }
+            let Some(value) = args.next() else {
+                print(format!("find: missing argument to `{arg}'"));
+                return exit(1);
+            };
+            match arg.as_str() {
+                "-name" => name = Some(value.clone()),
+                "-type" => match value.as_str() {
+                    "f" | "d" | "l" => kind_wanted = value.chars().next(),
+                    _ => {
+                        print(format!("find: Unknown argument to -type: {value}"));
+                        return exit(1);
+                    }
+                },
+                "-maxdepth" => match value.parse() {
+                    Ok(x) => depth = Some(x),
+                    Err(_) => {
+                        print(format!(
+                            "find: Expected a positive decimal integer argument to -maxdepth, but got '{value}'"
+                        ));
+                        return exit(1);
+                    }
+                },
+                _ => {
+                    print(format!("find: unknown predicate `{arg}'"));
+                    return exit(1);
+                }
+            }
+        }
+        if starts.is_empty() {
+            starts.push(".".into());
+        }
+
+        let user = User::current(&sender);
+        let mut status = 0;
+        for start in starts {
+            let file = SendTree::resolve_path(Path::new(&start), Path::new(&cwd), Path::new(&home))
+                .and_then(|path| {
+                    let file = FILESYSTEM.read().unwrap().get_link_by_path(&path, &user)?;
+                    Ok((path, file))
+                });
+            let (path, file) = match file {
+                Ok(x) => x,
+                Err(e) => {
+                    print(format!("find: '{start}': {e}"));
+                    status = 1;
+                    continue;
+                }
+            };
+
+            let mut tree = SendTree::new(Found {
+                shown: start.clone(),
+                name: path
+                    .file_name()
+                    .map_or(start.clone(), |x| x.to_string_lossy().into()),
+                kind: kind(&file.read().unwrap()),
+                error: None,
+            });
+            branch(&mut tree, &path, &start, depth, &user);
+
+            for node in tree.dfs_iter() {
+                let found = node.get_value();
+                let found = found.read().unwrap();
+                if name.as_ref().is_none_or(|x| glob::matches(x, &found.name))
+                    && kind_wanted.is_none_or(|x| x == found.kind)
+                {
+                    print(found.shown.clone());
+                }
+                if let Some(e) = found.error {
+                    print(format!("find: '{}': {e}", found.shown));
+                    status = 1;
+                }
+            }
+        }
+
+        exit(status);
+    })
+}
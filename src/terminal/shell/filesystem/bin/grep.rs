@@ -0,0 +1,202 @@
+fn run() -> Program {
+    use crate::terminal::shell::regex::Regex;
+    use crate::terminal::shell::stdio::read_stdin;
+    use crate::terminal::style::{Color, Span};
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    /// The files under `path`, shown as `shown`, sorted. Symlinks in it aren't followed.
+    fn files_under(path: &Path, shown: &str, user: &User, out: &mut Vec<(String, PathBuf)>) {
+        let Ok(file) = FILESYSTEM.read().unwrap().get_by_path(path, user) else {
+            return;
+        };
+        let mut names: Vec<String> = match *file.read().unwrap() {
+            FSObject::Folder { ref contents, .. } => contents.keys().cloned().collect(),
+            _ => return out.push((shown.into(), path.into())),
+        };
+        names.sort();
+        for name in names {
+            let child = path.join(&name);
+            let shown = format!("{}/{name}", shown.trim_end_matches('/'));
+            match FILESYSTEM.read().unwrap().get_link_by_path(&child, user) {
+                Ok(x) if matches!(*x.read().unwrap(), FSObject::Symlink { .. }) => {}
+                _ => files_under(&child, &shown, user, out),
+            }
+        }
+    }
+
+    Box::new(move |args, env, receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let push = |span: Span| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushSpan(span),
+                    None,
+                ))
+                .unwrap();
+        };
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status: u32| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        // -i ignores case, -n numbers lines, -r searches folders, -v shows lines that
+        // don't match and -F takes the pattern as plain text
+        let mut ignore_case = false;
+        let mut numbered = false;
+        let mut recursive = false;
+        let mut invert = false;
+        let mut fixed = false;
+        let mut pattern = None;
+        let mut names = Vec::new();
+        let mut options = true;
+        for arg in &args[1..] {
+            if options && arg == "--" {
+                options = false;
+            } else if options && arg.len() > 1 && arg.starts_with('-') {
+                for flag in arg.chars().skip(1) {
+                    match flag {
+                        'i' => ignore_case = true,
+                        'n' => numbered = true,
+                        'r' | 'R' => recursive = true,
+                        'v' => invert = true,
+                        'F' => fixed = true,
+                        'E' => fixed = false,
+                        _ => {
+                            print(format!("grep: invalid option -- '{flag}'"));
+                            return exit(2);
+                        }
+                    }
+                }
+            } else if pattern.is_none() {
+                pattern = Some(arg.clone());
+            } else {
+                names.push(arg.clone());
+            }
+        }
+        let Some(pattern) = pattern else {
+            print("Usage: grep [OPTION]... PATTERNS [FILE]...".into());
+            return exit(2);
+        };
+        let regex = if fixed {
+            Regex::literal(&pattern, ignore_case)
+        } else {
+            match Regex::new(&pattern, ignore_case) {
+                Ok(x) => x,
+                Err(e) => {
+                    print(format!("grep: {e}"));
+                    return exit(2);
+                }
+            }
+        };
+        if names.is_empty() {
+            names.push(if recursive { "." } else { "-" }.into());
+        }
+
+        let user = User::current(&sender);
+        let mut status = 1;
+        let mut errors = false;
+        let mut files = Vec::new();
+        for name in &names {
+            if name == "-" {
+                files.push(("(standard input)".to_string(), None));
+                continue;
+            }
+            let path =
+                match SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home)) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        print(format!("grep: {name}: {e}"));
+                        errors = true;
+                        continue;
+                    }
+                };
+            let folder = FILESYSTEM
+                .read()
+                .unwrap()
+                .get_by_path(&path, &user)
+                .is_ok_and(|x| x.read().unwrap().is_folder());
+            if folder && recursive {
+                let mut found = Vec::new();
+                files_under(&path, name, &user, &mut found);
+                files.extend(found.into_iter().map(|(x, y)| (x, Some(y))));
+            } else {
+                files.push((name.clone(), Some(path)));
+            }
+        }
+        let prefixed = files.len() > 1 || recursive;
+
+        for (name, path) in files {
+            let contents = match path {
+                None => match read_stdin(&receiver, &sender) {
+                    Some(x) => Ok(x),
+                    None => return,
+                },
+                Some(path) => FILESYSTEM.read().unwrap().read_file(&path, &user),
+            };
+            let contents = match contents {
+                Ok(x) => x,
+                Err(e) => {
+                    print(format!("grep: {name}: {e}"));
+                    errors = true;
+                    continue;
+                }
+            };
+
+            let text = String::from_utf8_lossy(&contents);
+            let binary = contents.contains(&0);
+            for (i, line) in text.lines().enumerate() {
+                if regex.is_match(line) == invert {
+                    continue;
+                }
+                status = 0;
+                if binary {
+                    print(format!("Binary file {name} matches"));
+                    break;
+                }
+                if prefixed {
+                    push(Span::from(name.as_str()).with_fg_color(Color::new_rgb(190, 80, 190)));
+                    push(Span::from(":").with_fg_color(Color::new_rgb(40, 190, 200)));
+                }
+                if numbered {
+                    push(
+                        Span::from((i + 1).to_string().as_str())
+                            .with_fg_color(Color::new_rgb(60, 200, 90)),
+                    );
+                    push(Span::from(":").with_fg_color(Color::new_rgb(40, 190, 200)));
+                }
+                // Matches are highlighted, unless the line is shown for not matching
+                let matches = if invert {
+                    Vec::new()
+                } else {
+                    regex.find_all(line)
+                };
+                let mut shown = 0;
+                for (start, end) in matches {
+                    push(Span::from(&line[shown..start]));
+                    push(
+                        Span::from(&line[start..end])
+                            .with_fg_color(Color::new_rgb(225, 30, 50))
+                            .bold(),
+                    );
+                    shown = end;
+                }
+                push(Span::from(&line[shown..]));
+                print(String::new());
+            }
+        }
+
+        exit(if errors { 2 } else { status });
+    })
+}
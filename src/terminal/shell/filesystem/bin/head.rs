@@ -0,0 +1,108 @@
+fn run() -> Program {
+    use crate::terminal::shell::stdio::read_stdin;
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let push = |text: String| {
+            sender
+                .send(SessionMessage::Terminal(TerminalMessage::Push(text), None))
+                .unwrap();
+        };
+        let error = |message: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("head: {message}")),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status: u32| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        // -n N shows the first N lines, and -c N the first N bytes instead
+        let mut count = 10;
+        let mut bytes = false;
+        let mut files = Vec::new();
+        let mut args = args[1..].iter();
+        while let Some(arg) = args.next() {
+            let (value, lines) = match arg.as_str() {
+                "-n" => (args.next().cloned(), true),
+                "-c" => (args.next().cloned(), false),
+                x if x.starts_with("-n") => (Some(x[2..].to_string()), true),
+                x if x.starts_with("-c") => (Some(x[2..].to_string()), false),
+                x if x.len() > 1 && x.starts_with('-') => (Some(x[1..].to_string()), true),
+                _ => {
+                    files.push(arg.clone());
+                    continue;
+                }
+            };
+            let what = if lines { "lines" } else { "bytes" };
+            let Some(value) = value else {
+                error(format!("option requires an argument -- '{}'", &what[..1]));
+                return exit(1);
+            };
+            match value.parse() {
+                Ok(x) => (count, bytes) = (x, !lines),
+                Err(_) => {
+                    error(format!("invalid number of {what}: '{value}'"));
+                    return exit(1);
+                }
+            }
+        }
+        if files.is_empty() {
+            files.push("-".into());
+        }
+
+        let user = User::current(&sender);
+        let mut status = 0;
+        let mut first = true;
+        for name in &files {
+            let contents = if name == "-" {
+                match read_stdin(&receiver, &sender) {
+                    Some(x) => Ok(x),
+                    None => return,
+                }
+            } else {
+                SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home))
+                    .and_then(|path| FILESYSTEM.read().unwrap().read_file(&path, &user))
+            };
+            let contents = match contents {
+                Ok(x) => x,
+                Err(e) => {
+                    error(format!("cannot open '{name}' for reading: {e}"));
+                    status = 1;
+                    continue;
+                }
+            };
+
+            if files.len() > 1 {
+                let name = if name == "-" { "standard input" } else { name };
+                push(format!("{}==> {name} <==\n", if first { "" } else { "\n" }));
+                first = false;
+            }
+            let end = if bytes {
+                count.min(contents.len())
+            } else if count == 0 {
+                0
+            } else {
+                // Up to and including the count'th newline
+                contents
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, x)| **x == b'\n')
+                    .nth(count - 1)
+                    .map_or(contents.len(), |(i, _)| i + 1)
+            };
+            push(String::from_utf8_lossy(&contents[..end]).into());
+        }
+
+        exit(status);
+    })
+}
@@ -1,4 +1,4 @@
-fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<SessionMessage>)> {
+fn run() -> Program {
     use crate::key_events::*;
     use crate::terminal::{
         style::{Color, Span},
@@ -6,7 +6,7 @@ fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<Session
     };
     use std::sync::mpsc::TryRecvError;
 
-    Box::new(move |args, receiver, sender| {
+    Box::new(move |_args, _env, receiver, sender| {
         let cash: [Span; 4] = [
             Span::new()
                 .with_fg_color(Color::new_rgb(225, 30, 50))
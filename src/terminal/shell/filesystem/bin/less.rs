@@ -0,0 +1,333 @@
+fn run() -> Program {
+    use crate::key_events::*;
+    use crate::terminal::shell::regex::Regex;
+    use crate::terminal::shell::stdio::{read_stdin, terminal_size};
+    use crate::terminal::style::{Color, Span};
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+    use crate::utils::pos::Size;
+    use std::sync::mpsc::Receiver;
+
+    const TAB_WIDTH: usize = 8;
+
+    struct Pager {
+        lines: Vec<String>,
+        /// The lines cut to the width of the screen, each with the line it's part of
+        rows: Vec<(usize, String)>,
+        /// The first row shown
+        top: usize,
+        size: Size,
+        /// Shown on the status line at the top of the text
+        name: Option<String>,
+        /// What's highlighted and looked for with `n` and `N`
+        search: Option<Regex>,
+        /// Shown on the status line until the next key
+        message: String,
+        /// What's been typed after `/`, while it's being typed
+        prompt: Option<String>,
+    }
+
+    impl Pager {
+        /// Rows of text that fit above the status line
+        fn page(&self) -> usize {
+            self.size.height().saturating_sub(1).max(1)
+        }
+
+        /// The furthest down `top` goes, with the last row at the bottom
+        fn bottom(&self) -> usize {
+            self.rows.len().saturating_sub(self.page())
+        }
+
+        fn scroll_to(&mut self, top: usize) {
+            self.top = top.min(self.bottom());
+        }
+
+        /// Cuts the lines up again for the screen's width, keeping the same line at the top
+        fn wrap(&mut self) {
+            let width = self.size.width().max(1);
+            let line = self.rows.get(self.top).map_or(0, |x| x.0);
+            self.rows.clear();
+            for (i, text) in self.lines.iter().enumerate() {
+                let chars: Vec<char> = text.chars().collect();
+                if chars.is_empty() {
+                    self.rows.push((i, String::new()));
+                }
+                for chunk in chars.chunks(width) {
+                    self.rows.push((i, chunk.iter().collect()));
+                }
+            }
+            let top = self.rows.iter().position(|x| x.0 == line).unwrap_or(0);
+            self.scroll_to(top);
+        }
+
+        /// Scrolls to the next row after the top one with a match, or the one before if
+        /// `backwards` is set
+        fn find(&mut self, backwards: bool) {
+            let Some(search) = &self.search else {
+                self.message = "No previous regular expression".into();
+                return;
+            };
+            let found = if backwards {
+                (0..self.top)
+                    .rev()
+                    .find(|x| search.is_match(&self.rows[*x].1))
+            } else {
+                (self.top + 1..self.rows.len()).find(|x| search.is_match(&self.rows[*x].1))
+            };
+            match found {
+                Some(row) => self.scroll_to(row),
+                None => self.message = "Pattern not found".into(),
+            }
+        }
+    }
+
+    fn send(sender: &Sender<SessionMessage>, message: TerminalMessage) {
+        sender
+            .send(SessionMessage::Terminal(message, None))
+            .unwrap();
+    }
+
+    fn draw(pager: &Pager, sender: &Sender<SessionMessage>) {
+        let inverted = |text: &str| {
+            Span::from(text)
+                .with_fg_color(Color::new_rgb(20, 20, 20))
+                .with_bg_color(Color::new_rgb(200, 200, 200))
+        };
+
+        send(sender, TerminalMessage::Clear);
+        for i in 0..pager.page() {
+            send(sender, TerminalMessage::MoveCursor(0, i));
+            let Some((_, row)) = pager.rows.get(pager.top + i) else {
+                send(
+                    sender,
+                    TerminalMessage::PushSpan(
+                        Span::from("~").with_fg_color(Color::new_rgb(110, 110, 110)),
+                    ),
+                );
+                continue;
+            };
+            let matches = pager
+                .search
+                .as_ref()
+                .map(|x| x.find_all(row))
+                .unwrap_or_default();
+            let mut shown = 0;
+            for (start, end) in matches.into_iter().filter(|(start, end)| start < end) {
+                send(
+                    sender,
+                    TerminalMessage::PushSpan(Span::from(&row[shown..start])),
+                );
+                send(
+                    sender,
+                    TerminalMessage::PushSpan(inverted(&row[start..end])),
+                );
+                shown = end;
+            }
+            send(sender, TerminalMessage::PushSpan(Span::from(&row[shown..])));
+        }
+
+        send(
+            sender,
+            TerminalMessage::MoveCursor(0, pager.size.height().saturating_sub(1)),
+        );
+        match &pager.prompt {
+            Some(input) => send(
+                sender,
+                TerminalMessage::PushSpan(Span::from(format!("/{input}").as_str())),
+            ),
+            None if !pager.message.is_empty() => {
+                send(sender, TerminalMessage::PushSpan(inverted(&pager.message)));
+            }
+            None if pager.top >= pager.bottom() => {
+                send(sender, TerminalMessage::PushSpan(inverted("(END)")));
+            }
+            None => match pager.name.as_ref().filter(|_| pager.top == 0) {
+                Some(name) => send(sender, TerminalMessage::PushSpan(inverted(name))),
+                None => send(sender, TerminalMessage::PushSpan(Span::from(":"))),
+            },
+        }
+        send(sender, TerminalMessage::ForceUpdate);
+    }
+
+    /// Waits for the next key, redrawing if the terminal changes size meanwhile. `None`
+    /// if the shell has gone.
+    fn next_key(
+        pager: &mut Pager,
+        receiver: &Receiver<SessionMessage>,
+        sender: &Sender<SessionMessage>,
+    ) -> Option<KeyEvent> {
+        loop {
+            match receiver.recv().ok()? {
+                SessionMessage::Shell(ShellMessage::InputKeyEvent(key), _) => return Some(key),
+                SessionMessage::Shell(ShellMessage::Resize(size), _) => {
+                    pager.size = size;
+                    pager.wrap();
+                    draw(pager, sender);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads a pattern after `/`. `None` if cancelled with Ctrl+C or by deleting the `/`.
+    fn ask(
+        pager: &mut Pager,
+        receiver: &Receiver<SessionMessage>,
+        sender: &Sender<SessionMessage>,
+    ) -> Option<String> {
+        pager.prompt = Some(String::new());
+        let answer = loop {
+            draw(pager, sender);
+            let Some(key) = next_key(pager, receiver, sender) else {
+                break None;
+            };
+            let input = pager.prompt.as_mut().unwrap();
+            match (&key.key_type, &key.modifier) {
+                (Key::Enter, _) => break Some(input.clone()),
+                (Key::Char('c'), Some(Modifier::Ctrl)) => break None,
+                (Key::Backspace, _) if input.is_empty() => break None,
+                (Key::Backspace, _) => {
+                    input.pop();
+                }
+                _ => input.extend(key.typed_char()),
+            }
+        };
+        pager.prompt = None;
+        answer
+    }
+
+    Box::new(move |args, env, receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+        let exit = |status: u32| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+        let error = |message: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("less: {message}")),
+                    None,
+                ))
+                .unwrap();
+        };
+
+        let mut files = Vec::new();
+        for arg in &args[1..] {
+            if arg.len() > 1 && arg.starts_with('-') && arg != "--" {
+                error(format!(
+                    "invalid option -- '{}'",
+                    arg.chars().nth(1).unwrap()
+                ));
+                return exit(1);
+            }
+            if arg != "--" {
+                files.push(arg.clone());
+            }
+        }
+        if files.is_empty() {
+            files.push("-".into());
+        }
+
+        // Several files are shown one after another, each with its name above it
+        let user = User::current(&sender);
+        let mut lines = Vec::new();
+        for name in &files {
+            let contents = if name == "-" {
+                match read_stdin(&receiver, &sender) {
+                    Some(x) => Ok(x),
+                    None => return,
+                }
+            } else {
+                SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home))
+                    .and_then(|path| FILESYSTEM.read().unwrap().read_file(&path, &user))
+            };
+            let contents = match contents {
+                Ok(x) => x,
+                Err(e) => {
+                    error(format!("{name}: {e}"));
+                    return exit(1);
+                }
+            };
+            if files.len() > 1 {
+                lines.extend([
+                    "::::::::::::::".into(),
+                    name.clone(),
+                    "::::::::::::::".into(),
+                ]);
+            }
+            for line in String::from_utf8_lossy(&contents).lines() {
+                let mut expanded = String::new();
+                for c in line.chars() {
+                    match c {
+                        '\t' => {
+                            let spaces = TAB_WIDTH - expanded.chars().count() % TAB_WIDTH;
+                            expanded.extend(std::iter::repeat_n(' ', spaces));
+                        }
+                        c if c.is_control() => {}
+                        c => expanded.push(c),
+                    }
+                }
+                lines.push(expanded);
+            }
+        }
+
+        let mut pager = Pager {
+            lines,
+            rows: Vec::new(),
+            top: 0,
+            size: terminal_size(&sender).unwrap_or(Size::new(80, 24)),
+            name: (files.len() == 1 && files[0] != "-").then(|| files[0].clone()),
+            search: None,
+            message: String::new(),
+            prompt: None,
+        };
+        pager.wrap();
+
+        send(&sender, TerminalMessage::EnterAlternateScreen);
+        send(&sender, TerminalMessage::ShowCursor(false));
+        loop {
+            draw(&pager, &sender);
+            let Some(key) = next_key(&mut pager, &receiver, &sender) else {
+                return;
+            };
+            pager.message.clear();
+            let page = pager.page();
+            let top = pager.top;
+            match (key.typed_char(), &key.key_type, &key.modifier) {
+                (Some('q' | 'Q'), ..) => break,
+                (Some(' ' | 'f'), ..) | (_, Key::Char('v' | 'f'), Some(Modifier::Ctrl)) => {
+                    pager.scroll_to(top + page)
+                }
+                (Some('b'), ..) | (_, Key::Char('b'), Some(Modifier::Ctrl)) => {
+                    pager.scroll_to(top.saturating_sub(page))
+                }
+                (Some('j' | 'e'), ..) | (_, Key::Down | Key::Enter, _) => pager.scroll_to(top + 1),
+                (Some('k' | 'y'), ..) | (_, Key::Up, _) => pager.scroll_to(top.saturating_sub(1)),
+                (Some('d'), ..) => pager.scroll_to(top + page / 2),
+                (Some('u'), ..) => pager.scroll_to(top.saturating_sub(page / 2)),
+                (Some('g' | '<'), ..) | (_, Key::Home, _) => pager.scroll_to(0),
+                (Some('G' | '>'), ..) | (_, Key::End, _) => pager.scroll_to(usize::MAX),
+                (Some('n'), ..) => pager.find(false),
+                (Some('N'), ..) => pager.find(true),
+                (Some('/'), ..) => match ask(&mut pager, &receiver, &sender) {
+                    // An empty pattern looks for the last one again
+                    Some(pattern) if pattern.is_empty() => pager.find(false),
+                    Some(pattern) => match Regex::new(&pattern, false) {
+                        Ok(regex) => {
+                            pager.search = Some(regex);
+                            pager.find(false);
+                        }
+                        Err(e) => pager.message = e,
+                    },
+                    None => {}
+                },
+                _ => {}
+            }
+        }
+
+        send(&sender, TerminalMessage::LeaveAlternateScreen);
+        exit(0);
+    })
+}
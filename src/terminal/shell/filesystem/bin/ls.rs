@@ -0,0 +1,180 @@
+fn run() -> Program {
+    use crate::terminal::style::{Color, Span};
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    /// `name` coloured by what kind of file it is: folders blue, symlinks cyan and
+    /// executables green
+    fn colored(name: &str, file: &FSObject) -> Span {
+        let span = Span::from(name);
+        match file {
+            FSObject::Folder { .. } => span.with_fg_color(Color::new_rgb(60, 120, 230)).bold(),
+            FSObject::Symlink { .. } => span.with_fg_color(Color::new_rgb(40, 190, 200)),
+            FSObject::File { permissions, .. } if permissions.mode & 0o111 != 0 => {
+                span.with_fg_color(Color::new_rgb(60, 200, 90)).bold()
+            }
+            FSObject::File { .. } => span,
+        }
+    }
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let push = |span: Span| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushSpan(span),
+                    None,
+                ))
+                .unwrap();
+        };
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+        // Ends a line of spans, so what comes after isn't coloured too
+        let end_line = || {
+            push(Span::new());
+            print(String::new());
+        };
+
+        // -a shows names starting with `.`, and -l lists one entry per line, with its
+        // mode, owners, size and when it was modified
+        let mut all = false;
+        let mut long = false;
+        let mut names = Vec::new();
+        let mut options = true;
+        for arg in &args[1..] {
+            if options && arg == "--" {
+                options = false;
+            } else if options && arg.len() > 1 && arg.starts_with('-') {
+                for flag in arg.chars().skip(1) {
+                    match flag {
+                        'a' => all = true,
+                        'l' => long = true,
+                        _ => {
+                            print(format!("ls: invalid option -- '{flag}'"));
+                            sender
+                                .send(SessionMessage::Shell(ShellMessage::ExitCode(2), None))
+                                .unwrap();
+                            return;
+                        }
+                    }
+                }
+            } else {
+                names.push(arg.clone());
+            }
+        }
+        if names.is_empty() {
+            names.push(".".into());
+        }
+
+        let user = User::current(&sender);
+        // Shows one entry, ending the line only with -l
+        let describe = |name: &str, file: &FSObject| {
+            if !long {
+                push(colored(name, file));
+                return;
+            }
+            let permissions = file.get_permissions();
+            let kind = match file {
+                FSObject::Folder { .. } => 'd',
+                FSObject::Symlink { .. } => 'l',
+                FSObject::File { .. } => '-',
+            };
+            let metadata = file.metadata();
+            push(Span::new().with_text(format!(
+                "{kind}{} {:<8} {:<8} {:>8} {} ",
+                permissions.mode_string(),
+                permissions.owner,
+                permissions.group,
+                metadata.size,
+                &Times::format(metadata.times.modified)[..16],
+            )));
+            push(colored(name, file));
+            if let FSObject::Symlink { target, .. } = file {
+                push(Span::new().with_text(format!(" -> {target}")));
+            }
+            end_line();
+        };
+
+        let mut status = 0;
+        for (i, name) in names.iter().enumerate() {
+            // With -l, a symlink is shown rather than what it points to
+            let file = SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home))
+                .and_then(|path| {
+                    let file = if long {
+                        FILESYSTEM.read().unwrap().get_link_by_path(&path, &user)
+                    } else {
+                        FILESYSTEM.read().unwrap().get_by_path(&path, &user)
+                    }?;
+                    Ok((path, file))
+                });
+            let (path, file) = match file {
+                Ok(x) => x,
+                Err(e) => {
+                    print(format!("ls: cannot access '{name}': {e}"));
+                    status = 2;
+                    continue;
+                }
+            };
+
+            let file = file.read().unwrap();
+            let FSObject::Folder { ref contents, .. } = *file else {
+                describe(name, &file);
+                if !long {
+                    end_line();
+                }
+                continue;
+            };
+            if !file.allows(&user, Access::Read) {
+                print(format!(
+                    "ls: cannot open directory '{name}': {}",
+                    FsError::PermissionDenied
+                ));
+                status = 2;
+                continue;
+            }
+
+            if names.len() > 1 {
+                if i > 0 {
+                    print(String::new());
+                }
+                print(format!("{name}:"));
+            }
+            let mut entries: Vec<(String, PathBuf)> = contents
+                .keys()
+                .filter(|x| all || !x.starts_with('.'))
+                .map(|x| (x.clone(), path.join(x)))
+                .collect();
+            if all {
+                entries.push((".".into(), path.clone()));
+                entries.push(("..".into(), path.parent().unwrap_or(&path).to_path_buf()));
+            }
+            entries.sort();
+            let mut first = true;
+            for (entry, child) in entries {
+                let Ok(child) = FILESYSTEM.read().unwrap().get_link_by_path(&child, &user) else {
+                    continue;
+                };
+                if !long && !first {
+                    push(Span::from("  "));
+                }
+                first = false;
+                describe(&entry, &child.read().unwrap());
+            }
+            if !long && !first {
+                end_line();
+            }
+        }
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+            .unwrap();
+    })
+}
@@ -0,0 +1,84 @@
+fn run() -> Program {
+    use crate::terminal::shell::markdown;
+    use crate::terminal::shell::stdio::{read_stdin, terminal_size};
+    use crate::terminal::style::Span;
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let push = |span: Span| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushSpan(span),
+                    None,
+                ))
+                .unwrap();
+        };
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status: u32| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        let mut files = Vec::new();
+        for arg in &args[1..] {
+            if arg.len() > 1 && arg.starts_with('-') {
+                print(format!(
+                    "mdcat: invalid option -- '{}'",
+                    arg.chars().nth(1).unwrap()
+                ));
+                return exit(1);
+            }
+            files.push(arg.clone());
+        }
+        if files.is_empty() {
+            files.push("-".into());
+        }
+
+        let user = User::current(&sender);
+        let width = terminal_size(&sender).map_or(80, |x| x.width()).max(20);
+        let mut status = 0;
+        for (i, name) in files.iter().enumerate() {
+            let contents = if name == "-" {
+                match read_stdin(&receiver, &sender) {
+                    Some(x) => Ok(x),
+                    None => return,
+                }
+            } else {
+                SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home))
+                    .and_then(|path| FILESYSTEM.read().unwrap().read_file(&path, &user))
+            };
+            let contents = match contents {
+                Ok(x) => x,
+                Err(e) => {
+                    print(format!("mdcat: {name}: {e}"));
+                    status = 1;
+                    continue;
+                }
+            };
+
+            if i > 0 {
+                print(String::new());
+            }
+            for line in markdown::render(&String::from_utf8_lossy(&contents), width) {
+                line.into_iter().for_each(push);
+                // Ends the line, so what comes after isn't styled too
+                push(Span::new());
+                print(String::new());
+            }
+        }
+
+        exit(status);
+    })
+}
@@ -0,0 +1,73 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let error = |message: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("mkdir: {message}")),
+                    None,
+                ))
+                .unwrap();
+        };
+
+        // -p creates missing parents, and doesn't mind folders that already exist
+        let parents = args.iter().skip(1).any(|x| x == "-p");
+        let names: Vec<&String> = args.iter().skip(1).filter(|x| *x != "-p").collect();
+        if names.is_empty() {
+            error("missing operand".into());
+        }
+
+        let user = User::current(&sender);
+        let mut status = if names.is_empty() { 1 } else { 0 };
+        for name in names {
+            let path =
+                match SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home)) {
+                    Ok(path) => path,
+                    Err(e) => {
+                        error(format!("cannot create directory '{name}': {e}"));
+                        status = 1;
+                        continue;
+                    }
+                };
+
+            let to_create: Vec<&Path> = if parents {
+                let mut missing: Vec<&Path> = path
+                    .ancestors()
+                    .take_while(|x| FILESYSTEM.read().unwrap().get_by_path(x, &user).is_err())
+                    .collect();
+                missing.reverse();
+                missing
+            } else {
+                vec![&path]
+            };
+
+            let mut filesystem = FILESYSTEM.write().unwrap();
+            if let Some(e) = to_create
+                .into_iter()
+                .find_map(|x| filesystem.create_folder(x, &user).err())
+            {
+                error(format!("cannot create directory '{name}': {e}"));
+                status = 1;
+            } else if parents
+                && !filesystem
+                    .get_by_path(&path, &user)
+                    .is_ok_and(|x| x.read().unwrap().is_folder())
+            {
+                error(format!(
+                    "cannot create directory '{name}': {}",
+                    FsError::NotADirectory
+                ));
+                status = 1;
+            }
+        }
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+            .unwrap();
+    })
+}
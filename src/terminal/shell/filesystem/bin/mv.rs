@@ -0,0 +1,73 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+        let resolve =
+            |name: &str| SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home));
+
+        let error = |message: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("mv: {message}")),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        let (sources, target) = match &args[1..] {
+            [] => {
+                error("missing file operand".into());
+                return exit(1);
+            }
+            [source] => {
+                error(format!("missing destination file operand after '{source}'"));
+                return exit(1);
+            }
+            [sources @ .., target] => (sources, target),
+        };
+
+        // Sources are moved into the target if it's a folder, or renamed to it if not
+        let user = User::current(&sender);
+        let target_path = match resolve(target) {
+            Ok(path) => path,
+            Err(e) => {
+                error(format!("cannot stat '{target}': {e}"));
+                return exit(1);
+            }
+        };
+        let into_folder = FILESYSTEM
+            .read()
+            .unwrap()
+            .get_by_path(&target_path, &user)
+            .is_ok_and(|x| x.read().unwrap().is_folder());
+        if sources.len() > 1 && !into_folder {
+            error(format!("target '{target}' is not a directory"));
+            return exit(1);
+        }
+
+        let mut status = 0;
+        for source in sources {
+            let result = resolve(source).and_then(|from| {
+                let to = match from.file_name() {
+                    Some(name) if into_folder => target_path.join(name),
+                    _ => target_path.clone(),
+                };
+                FILESYSTEM.write().unwrap().rename(&from, &to, &user)
+            });
+            if let Err(e) = result {
+                error(format!("cannot move '{source}' to '{target}': {e}"));
+                status = 1;
+            }
+        }
+
+        exit(status)
+    })
+}
@@ -0,0 +1,131 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    /// A process, as `/proc` tells it
+    struct Entry {
+        pid: usize,
+        parent: usize,
+        started: u64,
+        command: String,
+    }
+
+    fn read(path: &Path, user: &User) -> Option<String> {
+        let file = FILESYSTEM.read().unwrap().get_by_path(path, user).ok()?;
+        let file = file.read().unwrap();
+        match *file {
+            FSObject::File {
+                contents: FileType::Binary(ref bytes),
+                ..
+            } => Some(String::from_utf8_lossy(bytes).into()),
+            _ => None,
+        }
+    }
+
+    fn entry(pid: usize, user: &User) -> Option<Entry> {
+        let folder = Path::new("/proc").join(pid.to_string());
+        let status = read(&folder.join("status"), user)?;
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|x| x.strip_prefix(name)?.strip_prefix(":\t"))
+                .and_then(|x| x.parse().ok())
+        };
+        Some(Entry {
+            pid,
+            parent: field("PPid")? as usize,
+            started: field("Started")?,
+            command: read(&folder.join("cmdline"), user)?.trim_end().into(),
+        })
+    }
+
+    /// Adds the children of `parent` to `out` depth first, with how deep each is
+    fn forest<'a>(
+        entries: &'a [Entry],
+        parent: usize,
+        depth: usize,
+        out: &mut Vec<(&'a Entry, usize)>,
+    ) {
+        for entry in entries.iter().filter(|x| x.parent == parent) {
+            out.push((entry, depth));
+            forest(entries, entry.pid, depth + 1, out);
+        }
+    }
+
+    Box::new(move |args, _env, _receiver, sender| {
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+
+        // --forest shows each process under the one that started it
+        let tree = args.iter().skip(1).any(|x| x == "--forest" || x == "-H");
+        let user = User::current(&sender);
+        let pids: Vec<usize> = match FILESYSTEM
+            .read()
+            .unwrap()
+            .get_by_path(Path::new("/proc"), &user)
+        {
+            Ok(folder) => match *folder.read().unwrap() {
+                FSObject::Folder { ref contents, .. } => {
+                    contents.keys().filter_map(|x| x.parse().ok()).collect()
+                }
+                _ => Vec::new(),
+            },
+            Err(e) => {
+                print(format!("ps: /proc: {e}"));
+                sender
+                    .send(SessionMessage::Shell(ShellMessage::ExitCode(1), None))
+                    .unwrap();
+                return;
+            }
+        };
+        let mut entries: Vec<Entry> = pids.into_iter().filter_map(|x| entry(x, &user)).collect();
+        entries.sort_by_key(|x| x.pid);
+
+        let mut rows = Vec::new();
+        if tree {
+            // Orphans, whose parent has gone, are roots too
+            let roots: Vec<usize> = entries
+                .iter()
+                .filter(|x| !entries.iter().any(|y| y.pid == x.parent))
+                .map(|x| x.parent)
+                .collect();
+            let mut seen = Vec::new();
+            for root in roots {
+                if !seen.contains(&root) {
+                    seen.push(root);
+                    forest(&entries, root, 0, &mut rows);
+                }
+            }
+        } else {
+            rows = entries.iter().map(|x| (x, 0)).collect();
+        }
+
+        print(format!("{:>5} {:>5} {:>6} CMD", "PID", "PPID", "TIME"));
+        let now = now();
+        for (entry, depth) in rows {
+            let seconds = now.saturating_sub(entry.started) / 1000;
+            let indent = match depth {
+                0 => String::new(),
+                depth => format!("{}\\_ ", "    ".repeat(depth - 1)),
+            };
+            print(format!(
+                "{:>5} {:>5} {:>3}:{:02} {indent}{}",
+                entry.pid,
+                entry.parent,
+                seconds / 60,
+                seconds % 60,
+                entry.command
+            ));
+        }
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(0), None))
+            .unwrap();
+    })
+}
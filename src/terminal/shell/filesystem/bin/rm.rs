@@ -0,0 +1,97 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    /// Removes a file, or a folder and everything in it. Symlinks are removed, not
+    /// followed.
+    fn remove_all(path: &Path, user: &User) -> Result<(), FsError> {
+        let file = FILESYSTEM.read().unwrap().get_link_by_path(path, user)?;
+        let children: Vec<String> = match *file.read().unwrap() {
+            FSObject::Folder { ref contents, .. } => contents.keys().cloned().collect(),
+            _ => Vec::new(),
+        };
+        if !file.read().unwrap().is_folder() {
+            return FILESYSTEM.write().unwrap().unlink(path, user);
+        }
+        for child in children {
+            remove_all(&path.join(child), user)?;
+        }
+        FILESYSTEM.write().unwrap().remove_folder(path, user)
+    }
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let error = |message: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("rm: {message}")),
+                    None,
+                ))
+                .unwrap();
+        };
+
+        let mut recursive = false;
+        let mut force = false;
+        let mut names = Vec::new();
+        let mut options = true;
+        for arg in &args[1..] {
+            if options && arg == "--" {
+                options = false;
+            } else if options && arg.len() > 1 && arg.starts_with('-') {
+                for flag in arg.chars().skip(1) {
+                    match flag {
+                        'r' | 'R' => recursive = true,
+                        'f' => force = true,
+                        _ => {
+                            error(format!("invalid option -- '{flag}'"));
+                            sender
+                                .send(SessionMessage::Shell(ShellMessage::ExitCode(1), None))
+                                .unwrap();
+                            return;
+                        }
+                    }
+                }
+            } else {
+                names.push(arg);
+            }
+        }
+
+        let user = User::current(&sender);
+        let mut status = 0;
+        if names.is_empty() && !force {
+            error("missing operand".into());
+            status = 1;
+        }
+
+        for name in names {
+            let path = SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home));
+            if recursive && path.as_deref() == Ok(Path::new("/")) {
+                error("it is dangerous to operate recursively on '/'".into());
+                status = 1;
+                continue;
+            }
+
+            let result = path.and_then(|path| {
+                if recursive {
+                    remove_all(&path, &user)
+                } else {
+                    FILESYSTEM.write().unwrap().unlink(&path, &user)
+                }
+            });
+            match result {
+                Err(FsError::NotFound) if force => {}
+                Err(e) => {
+                    error(format!("cannot remove '{name}': {e}"));
+                    status = 1;
+                }
+                Ok(()) => {}
+            }
+        }
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+            .unwrap();
+    })
+}
@@ -0,0 +1,110 @@
+fn run() -> Program {
+    use crate::terminal::shell::stdio::read_stdin;
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+    use std::cmp::Ordering;
+
+    /// The number a line starts with, for -n. Lines that don't start with one count as 0.
+    fn number(line: &str) -> f64 {
+        let line = line.trim_start();
+        let end = line
+            .char_indices()
+            .find(|(i, c)| !(c.is_ascii_digit() || *c == '.' || (*i == 0 && *c == '-')))
+            .map_or(line.len(), |(i, _)| i);
+        line[..end].parse().unwrap_or(0.0)
+    }
+
+    Box::new(move |args, env, receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status: u32| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        // -r reverses the order, -n compares the numbers lines start with, -f ignores
+        // case and -u leaves out lines that compare equal to the one before
+        let mut reverse = false;
+        let mut numeric = false;
+        let mut fold = false;
+        let mut unique = false;
+        let mut files = Vec::new();
+        for arg in &args[1..] {
+            if arg.len() > 1 && arg.starts_with('-') {
+                for flag in arg.chars().skip(1) {
+                    match flag {
+                        'r' => reverse = true,
+                        'n' => numeric = true,
+                        'f' => fold = true,
+                        'u' => unique = true,
+                        _ => {
+                            print(format!("sort: invalid option -- '{flag}'"));
+                            return exit(2);
+                        }
+                    }
+                }
+            } else {
+                files.push(arg.clone());
+            }
+        }
+        if files.is_empty() {
+            files.push("-".into());
+        }
+
+        let user = User::current(&sender);
+        let mut lines = Vec::new();
+        for name in files {
+            let contents = if name == "-" {
+                match read_stdin(&receiver, &sender) {
+                    Some(x) => Ok(x),
+                    None => return,
+                }
+            } else {
+                SendTree::resolve_path(Path::new(&name), Path::new(&cwd), Path::new(&home))
+                    .and_then(|path| FILESYSTEM.read().unwrap().read_file(&path, &user))
+            };
+            match contents {
+                Ok(x) => lines.extend(String::from_utf8_lossy(&x).lines().map(String::from)),
+                Err(e) => {
+                    print(format!("sort: cannot read: {name}: {e}"));
+                    return exit(2);
+                }
+            }
+        }
+
+        let compare = |a: &String, b: &String| {
+            if numeric {
+                number(a).total_cmp(&number(b))
+            } else if fold {
+                a.to_lowercase().cmp(&b.to_lowercase())
+            } else {
+                a.cmp(b)
+            }
+        };
+        // Lines that compare equal are put in byte order, unless only one of them is kept
+        lines.sort_by(|a, b| {
+            let order = compare(a, b).then_with(|| if unique { Ordering::Equal } else { a.cmp(b) });
+            if reverse {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        if unique {
+            lines.dedup_by(|a, b| compare(a, b) == Ordering::Equal);
+        }
+        lines.into_iter().for_each(print);
+
+        exit(0);
+    })
+}
@@ -1,11 +1,11 @@
-fn run() -> Box<dyn FnOnce(Vec<String>, Receiver<SessionMessage>, Sender<SessionMessage>)> {
+fn run() -> Program {
     use crate::key_events::*;
     use crate::terminal::{
         style::{Color, Span},
         ShellMessage, TerminalMessage,
     };
 
-    Box::new(move |args, receiver, sender| {
+    Box::new(move |args, _env, _receiver, sender| {
         if cfg!(target_arch = "wasm32") && args.len() >= 2 {
             let window = web_sys::window().expect("no `window` exists!");
             let location = window.location();
@@ -0,0 +1,93 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+
+        // -L tells about what symlinks point to, rather than the links
+        let follow = args.iter().skip(1).any(|x| x == "-L");
+        let names: Vec<&String> = args.iter().skip(1).filter(|x| *x != "-L").collect();
+        if names.is_empty() {
+            print("stat: missing operand".into());
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(1), None))
+                .unwrap();
+            return;
+        }
+
+        let user = User::current(&sender);
+        let mut status = 0;
+        for name in names {
+            let path = SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home));
+            let filesystem = FILESYSTEM.read().unwrap();
+            let metadata = path.as_ref().map_err(|e| *e).and_then(|path| {
+                if follow {
+                    filesystem.stat(path, &user)
+                } else {
+                    filesystem.lstat(path, &user)
+                }
+            });
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    print(format!("stat: cannot statx '{name}': {e}"));
+                    status = 1;
+                    continue;
+                }
+            };
+
+            let permissions = &metadata.permissions;
+            let (kind, shown) = match metadata.kind {
+                mime::DIRECTORY => ('d', name.to_string()),
+                mime::SYMLINK => {
+                    let target = path
+                        .ok()
+                        .and_then(|path| filesystem.get_link_by_path(&path, &user).ok())
+                        .and_then(|x| match *x.read().unwrap() {
+                            FSObject::Symlink { ref target, .. } => Some(target.clone()),
+                            _ => None,
+                        })
+                        .unwrap_or_default();
+                    ('l', format!("{name} -> {target}"))
+                }
+                _ => ('-', name.to_string()),
+            };
+            print(format!("  File: {shown}"));
+            print(format!(
+                "  Size: {:<15} Type: {}",
+                metadata.size, metadata.kind
+            ));
+            print(format!(
+                "Access: ({:04o}/{kind}{})  Uid: {:<8}  Gid: {}",
+                permissions.mode,
+                permissions.mode_string(),
+                permissions.owner,
+                permissions.group,
+            ));
+            print(format!(
+                "Access: {}",
+                Times::format(metadata.times.accessed)
+            ));
+            print(format!(
+                "Modify: {}",
+                Times::format(metadata.times.modified)
+            ));
+            print(format!(" Birth: {}", Times::format(metadata.times.created)));
+        }
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+            .unwrap();
+    })
+}
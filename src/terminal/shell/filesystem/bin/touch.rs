@@ -0,0 +1,48 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let error = |message: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(format!("touch: {message}")),
+                    None,
+                ))
+                .unwrap();
+        };
+
+        if args.len() < 2 {
+            error("missing file operand".into());
+        }
+
+        let user = User::current(&sender);
+        let mut status = if args.len() < 2 { 1 } else { 0 };
+        for name in &args[1..] {
+            let result = SendTree::resolve_path(Path::new(name), Path::new(&cwd), Path::new(&home))
+                .and_then(|path| {
+                    let existing = FILESYSTEM.read().unwrap().get_by_path(&path, &user);
+                    match existing {
+                        Ok(_) => Ok(()),
+                        Err(FsError::NotFound) => FILESYSTEM
+                            .write()
+                            .unwrap()
+                            .create_file(&path, FileType::Binary(Vec::new()), &user)
+                            .map(|_| ()),
+                        Err(e) => Err(e),
+                    }
+                });
+            if let Err(e) = result {
+                error(format!("cannot touch '{name}': {e}"));
+                status = 1;
+            }
+        }
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+            .unwrap();
+    })
+}
@@ -0,0 +1,228 @@
+fn run() -> Program {
+    use crate::terminal::style::{Color, Span};
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    /// One line of the listing
+    struct Line {
+        /// The lines and connectors drawn before the name
+        prefix: String,
+        name: Span,
+        /// Drawn after the name, like where a symlink points
+        suffix: String,
+        folder: bool,
+    }
+
+    struct Options {
+        all: bool,
+        folders_only: bool,
+        level: Option<usize>,
+    }
+
+    /// Coloured the way `ls` colours it
+    fn colored(name: &str, file: &FSObject) -> Span {
+        let span = Span::from(name);
+        match file {
+            FSObject::Folder { .. } => span.with_fg_color(Color::new_rgb(60, 120, 230)).bold(),
+            FSObject::Symlink { .. } => span.with_fg_color(Color::new_rgb(40, 190, 200)),
+            FSObject::File { permissions, .. } if permissions.mode & 0o111 != 0 => {
+                span.with_fg_color(Color::new_rgb(60, 200, 90)).bold()
+            }
+            FSObject::File { .. } => span,
+        }
+    }
+
+    /// Adds what's in the folder at `path` under `tree`, and what's in the folders in it
+    /// under them. Symlinks aren't followed.
+    fn branch(
+        tree: &mut SendTree<Line>,
+        path: &Path,
+        prefix: &str,
+        depth: usize,
+        options: &Options,
+        user: &User,
+    ) {
+        if options.level.is_some_and(|level| depth >= level) {
+            return;
+        }
+        let Ok(folder) = FILESYSTEM.read().unwrap().get_by_path(path, user) else {
+            return;
+        };
+        let mut names: Vec<String> = match *folder.read().unwrap() {
+            FSObject::Folder { ref contents, .. } => contents
+                .keys()
+                .filter(|x| options.all || !x.starts_with('.'))
+                .cloned()
+                .collect(),
+            _ => return,
+        };
+        names.sort();
+        let children: Vec<_> = names
+            .into_iter()
+            .filter_map(|name| {
+                let child = FILESYSTEM
+                    .read()
+                    .unwrap()
+                    .get_link_by_path(&path.join(&name), user)
+                    .ok()?;
+                let folder = child.read().unwrap().is_folder();
+                (folder || !options.folders_only).then_some((name, child))
+            })
+            .collect();
+
+        for (i, (name, child)) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let child = child.read().unwrap();
+            let suffix = match *child {
+                FSObject::Symlink { ref target, .. } => format!(" -> {target}"),
+                FSObject::Folder { .. } if !child.allows(user, Access::Read) => {
+                    " [error opening dir]".into()
+                }
+                _ => String::new(),
+            };
+            let mut node = SendTree::new(Line {
+                prefix: format!("{prefix}{}", if last { "└── " } else { "├── " }),
+                name: colored(name, &child),
+                suffix,
+                folder: child.is_folder(),
+            });
+            if child.is_folder() {
+                let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+                branch(
+                    &mut node,
+                    &path.join(name),
+                    &prefix,
+                    depth + 1,
+                    options,
+                    user,
+                );
+            }
+            tree.insert_child(node);
+        }
+    }
+
+    Box::new(move |args, env, _receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let push = |span: Span| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushSpan(span),
+                    None,
+                ))
+                .unwrap();
+        };
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status: u32| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        // -a shows names starting with `.`, -d only shows folders and -L stops that many
+        // folders down
+        let mut options = Options {
+            all: false,
+            folders_only: false,
+            level: None,
+        };
+        let mut names = Vec::new();
+        let mut args = args[1..].iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-a" => options.all = true,
+                "-d" => options.folders_only = true,
+                "-L" => match args.next().map(|x| x.parse()) {
+                    Some(Ok(level)) if level > 0 => options.level = Some(level),
+                    _ => {
+                        print("tree: Invalid level, must be greater than 0.".into());
+                        return exit(1);
+                    }
+                },
+                x if x.len() > 1 && x.starts_with('-') => {
+                    print(format!("tree: Invalid argument `{x}'."));
+                    return exit(1);
+                }
+                _ => names.push(arg.clone()),
+            }
+        }
+        if names.is_empty() {
+            names.push(".".into());
+        }
+
+        let user = User::current(&sender);
+        let mut status = 0;
+        let (mut folders, mut files) = (0, 0);
+        for name in names {
+            let file = SendTree::resolve_path(Path::new(&name), Path::new(&cwd), Path::new(&home))
+                .and_then(|path| {
+                    let file = FILESYSTEM.read().unwrap().get_by_path(&path, &user)?;
+                    Ok((path, file))
+                });
+            let (path, file) = match file {
+                Ok(x) => x,
+                Err(e) => {
+                    print(format!("tree: {name}: {e}"));
+                    status = 2;
+                    continue;
+                }
+            };
+
+            let file = file.read().unwrap();
+            let readable = file.allows(&user, Access::Read);
+            let mut tree = SendTree::new(Line {
+                prefix: String::new(),
+                name: colored(&name, &file),
+                suffix: if file.is_folder() && !readable {
+                    " [error opening dir]".into()
+                } else {
+                    String::new()
+                },
+                folder: file.is_folder(),
+            });
+            if file.is_folder() {
+                branch(&mut tree, &path, "", 0, &options, &user);
+            }
+
+            for (i, node) in tree.dfs_iter().enumerate() {
+                let line = node.get_value();
+                let line = line.read().unwrap();
+                if i > 0 {
+                    if line.folder {
+                        folders += 1;
+                    } else {
+                        files += 1;
+                    }
+                }
+                push(Span::from(line.prefix.as_str()));
+                push(line.name.clone());
+                push(Span::from(line.suffix.as_str()));
+                print(String::new());
+            }
+        }
+
+        let plural = |count: usize, one: &str, many: &str| {
+            format!("{count} {}", if count == 1 { one } else { many })
+        };
+        print(String::new());
+        if options.folders_only {
+            print(plural(folders, "directory", "directories"));
+        } else {
+            print(format!(
+                "{}, {}",
+                plural(folders, "directory", "directories"),
+                plural(files, "file", "files")
+            ));
+        }
+
+        exit(status);
+    })
+}
@@ -0,0 +1,100 @@
+fn run() -> Program {
+    use crate::terminal::shell::stdio::read_stdin;
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+        let exit = |status: u32| {
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+                .unwrap();
+        };
+
+        // -c counts each run of the same line, -d only shows lines that repeat, -u only
+        // those that don't, and -i ignores case
+        let mut count = false;
+        let mut repeated = false;
+        let mut single = false;
+        let mut ignore_case = false;
+        let mut files = Vec::new();
+        for arg in &args[1..] {
+            if arg.len() > 1 && arg.starts_with('-') {
+                for flag in arg.chars().skip(1) {
+                    match flag {
+                        'c' => count = true,
+                        'd' => repeated = true,
+                        'u' => single = true,
+                        'i' => ignore_case = true,
+                        _ => {
+                            print(format!("uniq: invalid option -- '{flag}'"));
+                            return exit(1);
+                        }
+                    }
+                }
+            } else {
+                files.push(arg.clone());
+            }
+        }
+        if files.len() > 1 {
+            print(format!("uniq: extra operand '{}'", files[1]));
+            return exit(1);
+        }
+        let name = files.pop().unwrap_or("-".into());
+
+        let contents = if name == "-" {
+            match read_stdin(&receiver, &sender) {
+                Some(x) => Ok(x),
+                None => return,
+            }
+        } else {
+            let user = User::current(&sender);
+            SendTree::resolve_path(Path::new(&name), Path::new(&cwd), Path::new(&home))
+                .and_then(|path| FILESYSTEM.read().unwrap().read_file(&path, &user))
+        };
+        let contents = match contents {
+            Ok(x) => x,
+            Err(e) => {
+                print(format!("uniq: {name}: {e}"));
+                return exit(1);
+            }
+        };
+
+        // Runs of lines that are the same, with how long each is
+        let mut runs: Vec<(&str, usize)> = Vec::new();
+        let text = String::from_utf8_lossy(&contents);
+        for line in text.lines() {
+            match runs.last_mut() {
+                Some((last, n))
+                    if *last == line
+                        || (ignore_case && last.to_lowercase() == line.to_lowercase()) =>
+                {
+                    *n += 1
+                }
+                _ => runs.push((line, 1)),
+            }
+        }
+        for (line, n) in runs {
+            if (repeated && n == 1) || (single && n > 1) {
+                continue;
+            }
+            if count {
+                print(format!("{n:>7} {line}"));
+            } else {
+                print(line.into());
+            }
+        }
+
+        exit(0);
+    })
+}
@@ -0,0 +1,59 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |_args, _env, _receiver, sender| {
+        let user = User::current(&sender);
+        let seconds = FILESYSTEM
+            .read()
+            .unwrap()
+            .get_by_path(Path::new("/proc/uptime"), &user)
+            .ok()
+            .and_then(|file| match *file.read().unwrap() {
+                FSObject::File {
+                    contents: FileType::Binary(ref bytes),
+                    ..
+                } => String::from_utf8_lossy(bytes).trim().parse::<f64>().ok(),
+                _ => None,
+            });
+
+        let Some(seconds) = seconds else {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine("uptime: cannot read /proc/uptime".into()),
+                    None,
+                ))
+                .unwrap();
+            sender
+                .send(SessionMessage::Shell(ShellMessage::ExitCode(1), None))
+                .unwrap();
+            return;
+        };
+
+        // Like `up 5 min`, `up 2:05` or `up 3 days, 2:05`
+        let minutes = seconds as u64 / 60;
+        let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+        let mut up = String::new();
+        if days > 0 {
+            up.push_str(&format!("{days} day{}, ", if days == 1 { "" } else { "s" }));
+        }
+        if days > 0 || hours > 0 {
+            up.push_str(&format!("{hours}:{minutes:02}"));
+        } else {
+            up.push_str(&format!("{minutes} min"));
+        }
+
+        sender
+            .send(SessionMessage::Terminal(
+                TerminalMessage::PushLine(format!(
+                    " {} up {up},  1 user",
+                    &Times::format(now())[11..]
+                )),
+                None,
+            ))
+            .unwrap();
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(0), None))
+            .unwrap();
+    })
+}
@@ -0,0 +1,113 @@
+fn run() -> Program {
+    use crate::terminal::shell::stdio::read_stdin;
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |args, env, receiver, sender| {
+        let cwd = env.get("PWD").cloned().unwrap_or("/".into());
+        let home = env.get("HOME").cloned().unwrap_or_default();
+
+        let print = |line: String| {
+            sender
+                .send(SessionMessage::Terminal(
+                    TerminalMessage::PushLine(line),
+                    None,
+                ))
+                .unwrap();
+        };
+
+        // -l counts lines, -w words and -c bytes. Without any, all three are counted.
+        let mut shown = [false; 3];
+        let mut files = Vec::new();
+        for arg in &args[1..] {
+            if arg.len() > 1 && arg.starts_with('-') {
+                for flag in arg.chars().skip(1) {
+                    match flag {
+                        'l' => shown[0] = true,
+                        'w' => shown[1] = true,
+                        'c' => shown[2] = true,
+                        _ => {
+                            print(format!("wc: invalid option -- '{flag}'"));
+                            sender
+                                .send(SessionMessage::Shell(ShellMessage::ExitCode(1), None))
+                                .unwrap();
+                            return;
+                        }
+                    }
+                }
+            } else {
+                files.push(arg.clone());
+            }
+        }
+        if shown == [false; 3] {
+            shown = [true; 3];
+        }
+        let named = !files.is_empty();
+        if !named {
+            files.push("-".into());
+        }
+
+        let user = User::current(&sender);
+        let mut status = 0;
+        let mut rows = Vec::new();
+        let mut total = [0; 3];
+        for name in files {
+            let contents = if name == "-" {
+                match read_stdin(&receiver, &sender) {
+                    Some(x) => Ok(x),
+                    None => return,
+                }
+            } else {
+                SendTree::resolve_path(Path::new(&name), Path::new(&cwd), Path::new(&home))
+                    .and_then(|path| FILESYSTEM.read().unwrap().read_file(&path, &user))
+            };
+            let contents = match contents {
+                Ok(x) => x,
+                Err(e) => {
+                    print(format!("wc: {name}: {e}"));
+                    status = 1;
+                    continue;
+                }
+            };
+
+            let text = String::from_utf8_lossy(&contents);
+            let counts = [
+                contents.iter().filter(|x| **x == b'\n').count(),
+                text.split_whitespace().count(),
+                contents.len(),
+            ];
+            for (total, count) in total.iter_mut().zip(counts) {
+                *total += count;
+            }
+            rows.push((counts, if named { name } else { String::new() }));
+        }
+        if rows.len() > 1 {
+            rows.push((total, "total".into()));
+        }
+
+        // Every column is as wide as the biggest number shown
+        let width = total
+            .iter()
+            .zip(shown)
+            .filter(|(_, shown)| *shown)
+            .map(|(count, _)| count.to_string().len())
+            .max()
+            .unwrap_or(1);
+        for (counts, name) in rows {
+            let mut columns: Vec<String> = counts
+                .iter()
+                .zip(shown)
+                .filter(|(_, shown)| *shown)
+                .map(|(count, _)| format!("{count:>width$}"))
+                .collect();
+            if !name.is_empty() {
+                columns.push(name);
+            }
+            print(columns.join(" "));
+        }
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+            .unwrap();
+    })
+}
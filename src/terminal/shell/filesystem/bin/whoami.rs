@@ -0,0 +1,45 @@
+fn run() -> Program {
+    use crate::terminal::ShellMessage;
+    use crate::terminal::TerminalMessage;
+
+    Box::new(move |_args, _env, _receiver, sender| {
+        let user = User::current(&sender);
+        let name = FILESYSTEM
+            .read()
+            .unwrap()
+            .get_by_path(Path::new("/proc/user"), &user)
+            .ok()
+            .and_then(|file| match *file.read().unwrap() {
+                FSObject::File {
+                    contents: FileType::Binary(ref bytes),
+                    ..
+                } => Some(String::from_utf8_lossy(bytes).trim().to_string()),
+                _ => None,
+            });
+
+        let status = match name {
+            Some(name) => {
+                sender
+                    .send(SessionMessage::Terminal(
+                        TerminalMessage::PushLine(name),
+                        None,
+                    ))
+                    .unwrap();
+                0
+            }
+            None => {
+                sender
+                    .send(SessionMessage::Terminal(
+                        TerminalMessage::PushLine("whoami: cannot find name for user".into()),
+                        None,
+                    ))
+                    .unwrap();
+                1
+            }
+        };
+
+        sender
+            .send(SessionMessage::Shell(ShellMessage::ExitCode(status), None))
+            .unwrap();
+    })
+}
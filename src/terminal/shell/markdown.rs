@@ -0,0 +1,519 @@
+//! Markdown, rendered as styled text for the terminal.
+//!
+//! Headings, emphasis, code, links, images, lists, block quotes, horizontal rules and
+//! code blocks are understood, more or less as CommonMark has them. Paragraphs are
+//! wrapped between words to fit the width they're given.
+
+use crate::terminal::style::{is_safe_link, Color, Decoration, Span};
+
+/// Renders `text` into lines of spans at most `width` characters wide
+pub fn render(text: &str, width: usize) -> Vec<Vec<Span>> {
+    let lines: Vec<String> = text.lines().map(String::from).collect();
+    blocks(&lines, width)
+}
+
+/// Heading colours, from `#` down to `######`
+const HEADINGS: [(u8, u8, u8); 6] = [
+    (230, 120, 60),
+    (220, 170, 60),
+    (120, 190, 90),
+    (90, 160, 220),
+    (170, 120, 210),
+    (150, 150, 150),
+];
+const CODE: (u8, u8, u8) = (230, 150, 90);
+const LINK: (u8, u8, u8) = (80, 150, 230);
+const BULLET: (u8, u8, u8) = (90, 160, 220);
+const QUOTE: (u8, u8, u8) = (170, 170, 170);
+const GREY: (u8, u8, u8) = (110, 110, 110);
+
+fn decorated(style: &Span, decoration: Decoration) -> Span {
+    let mut style = style.clone();
+    style.decoration |= decoration as u8;
+    style
+}
+
+/// Adds `text` in `style`, onto the last span if that's in the same style
+fn append(spans: &mut Vec<Span>, text: &str, style: &Span) {
+    match spans.last_mut() {
+        Some(last)
+            if last.fg_color == style.fg_color
+                && last.bg_color == style.bg_color
+                && last.decoration == style.decoration
+                && last.link == style.link =>
+        {
+            last.text.push_str(text)
+        }
+        _ => spans.push(style.clone().with_text(text.into())),
+    }
+}
+
+/// The text and destination of a `[text](destination)` starting at `at`, and where
+/// it ends
+fn link(chars: &[char], at: usize) -> Option<(String, String, usize)> {
+    let mut depth = 0;
+    let mut i = at;
+    let close = loop {
+        match chars.get(i)? {
+            '\\' => i += 1,
+            '[' => depth += 1,
+            ']' if depth == 1 => break i,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    };
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = close + 2 + chars[close + 2..].iter().position(|x| *x == ')')?;
+    let label = chars[at + 1..close].iter().collect();
+    // Anything after the destination is a title, which isn't shown
+    let inside: String = chars[close + 2..end].iter().collect();
+    let destination = inside.split_whitespace().next().unwrap_or_default();
+    let destination = destination.trim_start_matches('<').trim_end_matches('>');
+    Some((label, destination.into(), end + 1))
+}
+
+fn push_link(label: &str, url: &str, style: &Span, spans: &mut Vec<Span>) {
+    // Links to anything but web pages and email are shown as text
+    let safe = is_safe_link(url);
+    if safe {
+        let link = decorated(style, Decoration::Underline)
+            .with_fg_color(Color::new_rgb_tuple(LINK))
+            .with_link(url.into());
+        inline(label, &link, spans);
+    } else {
+        inline(label, style, spans);
+    }
+    // Links can only be clicked in the browser, so elsewhere where they go is shown
+    if (!safe || !cfg!(target_arch = "wasm32")) && label != url {
+        let note = Span::new().with_fg_color(Color::new_rgb_tuple(GREY));
+        append(spans, &format!(" ({url})"), &note);
+    }
+}
+
+/// Adds `text` to `spans` in `style`, with its emphasis, code, links and escapes
+fn inline(text: &str, style: &Span, spans: &mut Vec<Span>) {
+    let chars: Vec<char> = text.chars().collect();
+    // How many of the same character there are in a row from `at`
+    let run = |at: usize| chars[at..].iter().take_while(|x| **x == chars[at]).count();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '\\' if next.is_some_and(|x| x.is_ascii_punctuation()) => {
+                append(spans, &next.unwrap().to_string(), style);
+                i += 2;
+                continue;
+            }
+            '`' => {
+                let n = run(i);
+                let close = (i + n..chars.len())
+                    .find(|j| chars[*j] == '`' && chars[j - 1] != '`' && run(*j) == n);
+                if let Some(j) = close {
+                    let code: String = chars[i + n..j].iter().collect();
+                    let code = match code.strip_prefix(' ').and_then(|x| x.strip_suffix(' ')) {
+                        Some(x) if !x.trim().is_empty() => x,
+                        _ => &code,
+                    };
+                    let mut code_style = style.clone().with_fg_color(Color::new_rgb_tuple(CODE));
+                    code_style.decoration = 0;
+                    append(spans, code, &code_style);
+                    i = j + n;
+                    continue;
+                }
+                append(spans, &"`".repeat(n), style);
+                i += n;
+                continue;
+            }
+            '*' | '_' => {
+                // `_` doesn't count in the middle of a word, like in snake_case
+                let n = run(i);
+                let opens = n <= 3
+                    && chars.get(i + n).is_some_and(|x| !x.is_whitespace())
+                    && (c == '*' || i == 0 || !chars[i - 1].is_alphanumeric());
+                let close = (i + n + 1..chars.len()).filter(|_| opens).find(|j| {
+                    chars[*j] == c
+                        && chars[j - 1] != c
+                        && !chars[j - 1].is_whitespace()
+                        && run(*j) == n
+                        && (c == '*' || chars.get(j + n).is_none_or(|x| !x.is_alphanumeric()))
+                });
+                if let Some(j) = close {
+                    let mut emphasis = style.clone();
+                    if n != 2 {
+                        emphasis = decorated(&emphasis, Decoration::Italic);
+                    }
+                    if n >= 2 {
+                        emphasis = decorated(&emphasis, Decoration::Bold);
+                    }
+                    inline(
+                        &chars[i + n..j].iter().collect::<String>(),
+                        &emphasis,
+                        spans,
+                    );
+                    i = j + n;
+                    continue;
+                }
+                append(spans, &c.to_string().repeat(n), style);
+                i += n;
+                continue;
+            }
+            '!' if next == Some('[') => {
+                if let Some((label, url, end)) = link(&chars, i + 1) {
+                    let label = if label.is_empty() {
+                        "image".into()
+                    } else {
+                        label
+                    };
+                    push_link(&label, &url, &decorated(style, Decoration::Italic), spans);
+                    i = end;
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some((label, url, end)) = link(&chars, i) {
+                    push_link(&label, &url, style, spans);
+                    i = end;
+                    continue;
+                }
+            }
+            '<' => {
+                // Autolinks, like <https://example.com> or <someone@example.com>
+                let end = chars[i..].iter().position(|x| *x == '>').map(|x| i + x);
+                if let Some(end) = end {
+                    let url: String = chars[i + 1..end].iter().collect();
+                    let href = if url.contains("://") {
+                        url.clone()
+                    } else {
+                        format!("mailto:{url}")
+                    };
+                    if !url.contains(char::is_whitespace)
+                        && (url.contains("://") || url.contains('@'))
+                        && is_safe_link(&href)
+                    {
+                        let link = decorated(style, Decoration::Underline)
+                            .with_fg_color(Color::new_rgb_tuple(LINK))
+                            .with_link(href);
+                        append(spans, &url, &link);
+                        i = end + 1;
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+        append(spans, &c.to_string(), style);
+        i += 1;
+    }
+}
+
+/// Breaks `spans` into lines of at most `width` characters, between words where it
+/// can. A newline always starts a new line.
+fn wrap(spans: &[Span], width: usize) -> Vec<Vec<Span>> {
+    let width = width.max(1);
+    // Each character with the index of the span it's from
+    let mut lines: Vec<Vec<(char, usize)>> = vec![Vec::new()];
+    let mut word = Vec::new();
+    let mut space = None;
+    let chars = spans
+        .iter()
+        .enumerate()
+        .flat_map(|(i, x)| x.text.chars().map(move |c| (c, i)))
+        .chain([('\n', 0)]);
+    for (c, style) in chars {
+        if !c.is_whitespace() {
+            word.push((c, style));
+            continue;
+        }
+        if !word.is_empty() {
+            let line = lines.last().unwrap();
+            if !line.is_empty() && line.len() + 1 + word.len() > width {
+                lines.push(Vec::new());
+                space = None;
+            }
+            let line = lines.last_mut().unwrap();
+            if let Some(style) = space.take() {
+                line.push((' ', style));
+            }
+            for x in word.drain(..) {
+                if lines.last().unwrap().len() >= width {
+                    lines.push(Vec::new());
+                }
+                lines.last_mut().unwrap().push(x);
+            }
+        }
+        if c == '\n' {
+            lines.push(Vec::new());
+            space = None;
+        } else if !lines.last().unwrap().is_empty() {
+            space = Some(style);
+        }
+    }
+    // The newline added to the end leaves an extra line
+    lines.pop();
+
+    lines
+        .into_iter()
+        .map(|line| {
+            let mut out: Vec<Span> = Vec::new();
+            let mut last = None;
+            for (c, style) in line {
+                if last == Some(style) {
+                    out.last_mut().unwrap().text.push(c);
+                } else {
+                    out.push(spans[style].clone().with_text(c.to_string()));
+                    last = Some(style);
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+/// The level and text of an ATX heading, like `## Usage`
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let line = line.trim_start();
+    let level = line.chars().take_while(|x| *x == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    // A closing run of `#`s isn't part of it
+    let text = rest.trim();
+    let without = text.trim_end_matches('#');
+    if without.is_empty() || without.ends_with(' ') {
+        Some((level, without.trim_end()))
+    } else {
+        Some((level, text))
+    }
+}
+
+/// The ``` or ~~~ that opens a fenced code block, which closes it too
+fn fence(line: &str) -> Option<String> {
+    let line = line.trim_start();
+    let c = line.chars().next().filter(|x| *x == '`' || *x == '~')?;
+    let n = line.chars().take_while(|x| *x == c).count();
+    (n >= 3).then(|| c.to_string().repeat(n))
+}
+
+/// Whether the line is a horizontal rule, like `---` or `* * *`
+fn rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|x| !x.is_whitespace()).collect();
+    marks.len() >= 3 && ['-', '*', '_'].iter().any(|c| marks.iter().all(|x| x == c))
+}
+
+/// The marker to show for a list item, and the column its text starts at
+fn list_item(line: &str) -> Option<(String, usize)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let rest = &line[indent..];
+    if indent > 3 {
+        return None;
+    }
+    if ["- ", "* ", "+ "].iter().any(|x| rest.starts_with(x)) {
+        return Some(("•".into(), indent + 2));
+    }
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    let after = &rest[digits..];
+    if (1..=9).contains(&digits) && (after.starts_with(". ") || after.starts_with(") ")) {
+        return Some((format!("{}.", &rest[..digits]), indent + digits + 2));
+    }
+    None
+}
+
+/// How many spaces and tabs the line starts with. Other whitespace doesn't count, so
+/// this is always where a character starts.
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+fn quote(line: &str) -> bool {
+    line.trim_start().starts_with('>')
+}
+
+fn indented(line: &str) -> bool {
+    line.starts_with("    ") || line.starts_with('\t')
+}
+
+/// Whether the line starts something other than a paragraph, ending any paragraph
+/// before it
+fn starts_block(line: &str) -> bool {
+    heading(line).is_some()
+        || fence(line).is_some()
+        || rule(line)
+        || quote(line)
+        || list_item(line).is_some()
+}
+
+fn code_line(line: &str) -> Vec<Span> {
+    let text = format!("  {}", line.replace('\t', "    "));
+    vec![Span::from(text.as_str()).with_fg_color(Color::new_rgb_tuple(CODE))]
+}
+
+/// Renders markdown lines into lines of spans, `width` characters wide. Lists and quotes
+/// render what's in them the same way, narrower, and put their markers in front.
+fn blocks(lines: &[String], width: usize) -> Vec<Vec<Span>> {
+    let mut out: Vec<Vec<Span>> = Vec::new();
+    // Whether a blank line goes before the next block
+    let mut gap = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = &lines[i];
+        if line.trim().is_empty() {
+            gap = true;
+            i += 1;
+            continue;
+        }
+        if gap && !out.is_empty() {
+            out.push(Vec::new());
+        }
+        gap = false;
+
+        if let Some(marker) = fence(line) {
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with(&marker) {
+                out.push(code_line(&lines[i]));
+                i += 1;
+            }
+            i += 1;
+        } else if let Some((level, text)) = heading(line) {
+            let mut style = Span::new()
+                .with_fg_color(Color::new_rgb_tuple(HEADINGS[level - 1]))
+                .bold();
+            if level == 1 {
+                style = style.underline();
+            }
+            let mut spans = Vec::new();
+            inline(text, &style, &mut spans);
+            out.extend(wrap(&spans, width));
+            i += 1;
+        } else if rule(line) {
+            let bar = "─".repeat(width);
+            out.push(vec![
+                Span::from(bar.as_str()).with_fg_color(Color::new_rgb_tuple(GREY))
+            ]);
+            i += 1;
+        } else if quote(line) {
+            let mut inner = Vec::new();
+            while i < lines.len() && quote(&lines[i]) {
+                let rest = &lines[i].trim_start()[1..];
+                inner.push(rest.strip_prefix(' ').unwrap_or(rest).to_string());
+                i += 1;
+            }
+            for mut line in blocks(&inner, width.saturating_sub(2)) {
+                for span in &mut line {
+                    span.fg_color.get_or_insert(Color::new_rgb_tuple(QUOTE));
+                    span.decoration |= Decoration::Italic as u8;
+                }
+                line.insert(
+                    0,
+                    Span::from("│ ").with_fg_color(Color::new_rgb_tuple(GREY)),
+                );
+                out.push(line);
+            }
+        } else if let Some((marker, column)) = list_item(line) {
+            // The item goes on while lines are indented to its text, or carry on its
+            // last paragraph
+            let mut inner = vec![line[column..].trim_start().to_string()];
+            i += 1;
+            while let Some(next) = lines.get(i) {
+                let depth = indent(next);
+                if next.trim().is_empty() {
+                    let more = lines[i..]
+                        .iter()
+                        .find(|x| !x.trim().is_empty())
+                        .is_some_and(|x| indent(x) >= column);
+                    if !more {
+                        break;
+                    }
+                    inner.push(String::new());
+                } else if depth >= column {
+                    inner.push(next[column..].to_string());
+                } else if !starts_block(next) && inner.last().is_some_and(|x| !x.trim().is_empty())
+                {
+                    inner.push(next.trim_start().to_string());
+                } else {
+                    break;
+                }
+                i += 1;
+            }
+            let bullet = format!("  {marker} ");
+            let indent = " ".repeat(bullet.chars().count());
+            let rendered = blocks(&inner, width.saturating_sub(indent.len()));
+            for (j, mut line) in rendered.into_iter().enumerate() {
+                let prefix = if j == 0 {
+                    Span::from(bullet.as_str()).with_fg_color(Color::new_rgb_tuple(BULLET))
+                } else {
+                    Span::from(indent.as_str())
+                };
+                line.insert(0, prefix);
+                out.push(line);
+            }
+        } else if indented(line) {
+            // Blank lines inside an indented code block are part of it, but not ones
+            // after it
+            let mut end = i;
+            while end < lines.len() && (indented(&lines[end]) || lines[end].trim().is_empty()) {
+                end += 1;
+            }
+            while lines[end - 1].trim().is_empty() {
+                end -= 1;
+            }
+            for line in &lines[i..end] {
+                let line = line.strip_prefix('\t').unwrap_or(line);
+                out.push(code_line(line.strip_prefix("    ").unwrap_or(line)));
+            }
+            i = end;
+        } else {
+            // A paragraph, which is a heading if it's underlined with `=` or `-`. A
+            // line ending in two spaces or `\` breaks there.
+            let mut text = String::new();
+            let mut level = None;
+            while let Some(line) = lines.get(i) {
+                let trimmed = line.trim();
+                if let Some(x) = trimmed.strip_suffix('\\') {
+                    text.push_str(x);
+                    text.push('\n');
+                } else {
+                    text.push_str(trimmed);
+                    text.push(if line.ends_with("  ") { '\n' } else { ' ' });
+                }
+                i += 1;
+                let Some(next) = lines.get(i).map(|x| x.trim()) else {
+                    break;
+                };
+                if !next.is_empty() && next.chars().all(|x| x == '=') {
+                    level = Some(1);
+                } else if !next.is_empty() && next.chars().all(|x| x == '-') {
+                    level = Some(2);
+                }
+                if level.is_some() {
+                    i += 1;
+                    break;
+                }
+                if next.is_empty() || starts_block(&lines[i]) {
+                    break;
+                }
+            }
+            let style = match level {
+                Some(level) => {
+                    let style = Span::new()
+                        .with_fg_color(Color::new_rgb_tuple(HEADINGS[level - 1]))
+                        .bold();
+                    if level == 1 {
+                        style.underline()
+                    } else {
+                        style
+                    }
+                }
+                None => Span::new(),
+            };
+            let mut spans = Vec::new();
+            inline(text.trim_end(), &style, &mut spans);
+            out.extend(wrap(&spans, width));
+        }
+    }
+    out
+}
@@ -0,0 +1,456 @@
+//! Extended regular expressions, the kind `grep -E` takes.
+//!
+//! `.` matches any character, `[abc]`, `[a-z]` and `[^abc]` one character that is, or
+//! isn't, in the set, and `\d`, `\w` and `\s` a digit, word character or space. `*`, `+`,
+//! `?` and `{n,m}` repeat what's before them, `|` separates alternatives, `( )` groups
+//! and `^` and `$` match at the start and end of the text. A backslash makes any other
+//! character mean itself. The first alternative that fits wins, like with backtracking,
+//! but every way of matching is followed at once, so the time taken grows with the
+//! length of the text times the size of the pattern and never needs more stack.
+
+/// A compiled regular expression
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>,
+    ignore_case: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+impl Regex {
+    /// Compiles `pattern`, or says what's wrong with it the way `grep` does
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Self, String> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut parser = Parser { chars, i: 0 };
+        let alternatives = parser.alternatives()?;
+        if parser.i < parser.chars.len() {
+            return Err("Unmatched ) or \\)".into());
+        }
+        let mut compiler = Compiler {
+            program: Vec::new(),
+        };
+        compiler.alternatives(&alternatives)?;
+        compiler.push(Inst::Match)?;
+        Ok(Self {
+            program: compiler.program,
+            ignore_case,
+        })
+    }
+
+    /// A pattern that matches `text` and nothing else
+    pub fn literal(text: &str, ignore_case: bool) -> Self {
+        Self {
+            program: text.chars().map(Inst::Char).chain([Inst::Match]).collect(),
+            ignore_case,
+        }
+    }
+
+    /// Whether the pattern matches anywhere in `text`
+    pub fn is_match(&self, text: &str) -> bool {
+        self.find(text, 0).is_some()
+    }
+
+    /// Where the first match starting at or after the byte `from` is, as byte offsets
+    pub fn find(&self, text: &str, from: usize) -> Option<(usize, usize)> {
+        let offsets: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .collect();
+        let first = offsets.iter().position(|x| *x >= from)?;
+        let mut matcher = Matcher {
+            program: &self.program,
+            text: &text.chars().collect::<Vec<_>>(),
+            ignore_case: self.ignore_case,
+            seen: vec![0; self.program.len()],
+        };
+        let (start, end) = matcher.find(first)?;
+        Some((offsets[start], offsets[end]))
+    }
+
+    /// Every match in `text` that doesn't overlap one before it, as byte offsets
+    pub fn find_all(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        let mut from = 0;
+        while let Some((start, end)) = self.find(text, from) {
+            if end > start {
+                found.push((start, end));
+                from = end;
+            } else {
+                // Skip past an empty match, so it isn't found again
+                match text[start..].chars().next() {
+                    Some(c) => from = start + c.len_utf8(),
+                    None => break,
+                }
+            }
+        }
+        found
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    i: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.i).copied()
+    }
+
+    /// Alternatives separated by `|`, up to a `)` or the end
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.i += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            self.i += 1;
+            let node = match c {
+                '.' => Node::Any,
+                '^' => Node::Start,
+                '$' => Node::End,
+                '(' => {
+                    let group = self.alternatives()?;
+                    if self.peek() != Some(')') {
+                        return Err("Unmatched ( or \\(".into());
+                    }
+                    self.i += 1;
+                    Node::Group(group)
+                }
+                '[' => self.class()?,
+                '\\' => {
+                    let c = self.peek().ok_or("Trailing backslash")?;
+                    self.i += 1;
+                    escaped(c)
+                }
+                '*' | '+' | '?' if nodes.is_empty() => Node::Char(c),
+                '*' | '+' | '?' | '{' => {
+                    let (min, max) = match c {
+                        '*' => (0, None),
+                        '+' => (1, None),
+                        '?' => (0, Some(1)),
+                        _ => match self.bounds()? {
+                            Some(x) => x,
+                            None => {
+                                nodes.push(Node::Char('{'));
+                                continue;
+                            }
+                        },
+                    };
+                    let node = nodes.pop().ok_or("Invalid preceding regular expression")?;
+                    Node::Repeat {
+                        node: Box::new(node),
+                        min,
+                        max,
+                    }
+                }
+                c => Node::Char(c),
+            };
+            nodes.push(node);
+        }
+        Ok(nodes)
+    }
+
+    /// Reads `n}`, `n,}` or `n,m}` following a `{`. Anything else isn't a bound, and the
+    /// `{` means itself.
+    fn bounds(&mut self) -> Result<Option<(usize, Option<usize>)>, String> {
+        let rest: String = self.chars[self.i..].iter().collect();
+        let Some(end) = rest.find('}') else {
+            return Ok(None);
+        };
+        let number = |x: &str| x.parse::<usize>().ok();
+        let bounds = match rest[..end].split_once(',') {
+            None => number(&rest[..end]).map(|x| (x, Some(x))),
+            Some((min, "")) => number(min).map(|x| (x, None)),
+            Some((min, max)) => number(min).zip(number(max)).map(|(x, y)| (x, Some(y))),
+        };
+        let Some((min, max)) = bounds else {
+            return Ok(None);
+        };
+        // The most a bound can be, as with GNU grep
+        if min.max(max.unwrap_or(0)) > 0x7fff {
+            return Err("Regular expression too big".into());
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err("Invalid content of \\{\\}".into());
+        }
+        self.i += rest[..=end].chars().count();
+        Ok(Some((min, max)))
+    }
+
+    /// Reads a set like `abc]`, `a-z]` or `^abc]` following a `[`. A `]` straight after
+    /// the `[` is part of the set.
+    fn class(&mut self) -> Result<Node, String> {
+        let unmatched = || "Unmatched [, [^, [:, [., or [=".to_string();
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.i += 1;
+        }
+        let start = self.i;
+        let mut ranges = Vec::new();
+        loop {
+            let low = self.peek().ok_or_else(unmatched)?;
+            self.i += 1;
+            if low == ']' && self.i - 1 > start {
+                return Ok(Node::Class { negated, ranges });
+            }
+            match (self.peek(), self.chars.get(self.i + 1)) {
+                (Some('-'), Some(&high)) if high != ']' => {
+                    if high < low {
+                        return Err("Invalid range end".into());
+                    }
+                    ranges.push((low, high));
+                    self.i += 2;
+                }
+                _ => ranges.push((low, low)),
+            }
+        }
+    }
+}
+
+/// What `\c` means
+fn escaped(c: char) -> Node {
+    let class = |negated, ranges: &[(char, char)]| Node::Class {
+        negated,
+        ranges: ranges.to_vec(),
+    };
+    const DIGITS: [(char, char); 1] = [('0', '9')];
+    const WORD: [(char, char); 4] = [('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+    const SPACE: [(char, char); 3] = [(' ', ' '), ('\t', '\r'), ('\u{a0}', '\u{a0}')];
+    match c {
+        'd' => class(false, &DIGITS),
+        'D' => class(true, &DIGITS),
+        'w' => class(false, &WORD),
+        'W' => class(true, &WORD),
+        's' => class(false, &SPACE),
+        'S' => class(true, &SPACE),
+        't' => Node::Char('\t'),
+        'n' => Node::Char('\n'),
+        c => Node::Char(c),
+    }
+}
+
+/// One step of a compiled pattern
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+    Start,
+    End,
+    /// Goes both ways, preferring the first
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+/// The most instructions a pattern may compile to
+const MAX_PROGRAM: usize = 1 << 20;
+
+/// Turns the parsed pattern into instructions
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.program.len() >= MAX_PROGRAM {
+            return Err("Regular expression too big".into());
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    fn alternatives(&mut self, alternatives: &[Vec<Node>]) -> Result<(), String> {
+        // Each alternative but the last is tried before going on to the next split
+        let mut jumps = Vec::new();
+        for (i, nodes) in alternatives.iter().enumerate() {
+            let split = (i + 1 < alternatives.len())
+                .then(|| self.push(Inst::Split(0, 0)))
+                .transpose()?;
+            for node in nodes {
+                self.node(node)?;
+            }
+            if let Some(split) = split {
+                jumps.push(self.push(Inst::Jump(0))?);
+                self.program[split] = Inst::Split(split + 1, self.program.len());
+            }
+        }
+        let end = self.program.len();
+        for jump in jumps {
+            self.program[jump] = Inst::Jump(end);
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Char(c) => self.push(Inst::Char(*c)).map(drop),
+            Node::Any => self.push(Inst::Any).map(drop),
+            Node::Class { negated, ranges } => self
+                .push(Inst::Class {
+                    negated: *negated,
+                    ranges: ranges.clone(),
+                })
+                .map(drop),
+            Node::Start => self.push(Inst::Start).map(drop),
+            Node::End => self.push(Inst::End).map(drop),
+            Node::Group(alternatives) => self.alternatives(alternatives),
+            Node::Repeat { node, min, max } => {
+                for _ in 0..*min {
+                    self.node(node)?;
+                }
+                match max {
+                    // Any more, taking another as long as it can
+                    None => {
+                        let split = self.push(Inst::Split(0, 0))?;
+                        self.node(node)?;
+                        self.push(Inst::Jump(split))?;
+                        self.program[split] = Inst::Split(split + 1, self.program.len());
+                    }
+                    // Up to `max - min` more, each skipping to the end if it isn't there
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.push(Inst::Split(0, 0))?);
+                            self.node(node)?;
+                        }
+                        let end = self.program.len();
+                        for split in splits {
+                            self.program[split] = Inst::Split(split + 1, end);
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A place in the program a match is being tried from, and where that match started
+#[derive(Clone, Copy)]
+struct Thread {
+    pc: usize,
+    start: usize,
+}
+
+/// Runs a program over some text, following every way through it at once so that
+/// nothing is ever tried twice at the same place. Threads are kept in order of
+/// preference, which makes the match found the one backtracking would find.
+struct Matcher<'a> {
+    program: &'a [Inst],
+    text: &'a [char],
+    ignore_case: bool,
+    /// The step each instruction was last added to a list in, plus one
+    seen: Vec<usize>,
+}
+
+impl Matcher<'_> {
+    /// Adds the threads `pc` leads to at `at` without reading anything, in order
+    fn add(&mut self, list: &mut Vec<Thread>, pc: usize, start: usize, at: usize) {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if self.seen[pc] == at + 1 {
+                continue;
+            }
+            self.seen[pc] = at + 1;
+            match self.program[pc] {
+                Inst::Jump(x) => stack.push(x),
+                Inst::Split(x, y) => {
+                    stack.push(y);
+                    stack.push(x);
+                }
+                Inst::Start if at == 0 => stack.push(pc + 1),
+                Inst::End if at == self.text.len() => stack.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                _ => list.push(Thread { pc, start }),
+            }
+        }
+    }
+
+    /// The first match starting at or after the character `first`, as character indices
+    fn find(&mut self, first: usize) -> Option<(usize, usize)> {
+        let mut found = None;
+        let mut threads = Vec::new();
+        for at in first..=self.text.len() {
+            // A match that starts here is liked less than one that started before
+            if found.is_none() {
+                self.add(&mut threads, 0, at, at);
+            }
+            if threads.is_empty() {
+                if found.is_some() {
+                    break;
+                }
+                continue;
+            }
+            let mut next = Vec::new();
+            for thread in std::mem::take(&mut threads) {
+                match &self.program[thread.pc] {
+                    // Threads after this one are liked less, so they're dropped
+                    Inst::Match => {
+                        found = Some((thread.start, at));
+                        break;
+                    }
+                    inst => {
+                        if self.text.get(at).is_some_and(|c| self.char(inst, *c)) {
+                            self.add(&mut next, thread.pc + 1, thread.start, at + 1);
+                        }
+                    }
+                }
+            }
+            threads = next;
+        }
+        found
+    }
+
+    /// Whether the instruction for one character matches `c`
+    fn char(&self, inst: &Inst, c: char) -> bool {
+        match inst {
+            Inst::Any => c != '\n',
+            Inst::Char(x) if self.ignore_case => fold(*x) == fold(c),
+            Inst::Char(x) => *x == c,
+            Inst::Class { negated, ranges } => {
+                let contains =
+                    |c: char| ranges.iter().any(|(low, high)| (*low..=*high).contains(&c));
+                let upper = c.to_uppercase().next().unwrap_or(c);
+                let found =
+                    contains(c) || (self.ignore_case && (contains(fold(c)) || contains(upper)));
+                found != *negated
+            }
+            _ => false,
+        }
+    }
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
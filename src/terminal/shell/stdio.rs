@@ -0,0 +1,137 @@
+//! Standard input and output for programs.
+//!
+//! Programs write their output as `TerminalMessage`s, which the shell running them may
+//! turn back into text with `output_text` and hand to the next program in a pipeline
+//! as `ShellMessage::Stdin`. `read_stdin` reads that input back, or lets the user type
+//! it when nothing was piped in.
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::{
+    key_events::{Key, KeyEvent, Modifier},
+    session::{ReturnValue, SessionMessage, ShellMessage, TerminalMessage},
+    terminal::style::Span,
+    utils::pos::Size,
+};
+
+/// Reads a program's standard input to the end.
+///
+/// Piped and redirected input arrives as `ShellMessage::Stdin` chunks, ended by
+/// `ShellMessage::EndOfInput`. Anything typed at the terminal in the meantime is
+/// echoed back and read a line at a time, until Ctrl+D.
+/// Returns `None` if the shell hangs up first.
+pub fn read_stdin(
+    receiver: &Receiver<SessionMessage>,
+    sender: &Sender<SessionMessage>,
+) -> Option<Vec<u8>> {
+    let mut input = Vec::new();
+    // The line being typed, if the user has started one
+    let mut line: Option<String> = None;
+
+    loop {
+        let SessionMessage::Shell(message, _) = receiver.recv().ok()? else {
+            continue;
+        };
+        match message {
+            ShellMessage::Stdin(mut bytes) => input.append(&mut bytes),
+            ShellMessage::EndOfInput => break,
+            ShellMessage::InputKeyEvent(key_event) => {
+                if line.is_none() {
+                    echo(sender, TerminalMessage::PushSpan(Span::new()));
+                    line = Some(String::new());
+                }
+                if continue_line(sender, &mut line, &mut input, &key_event) {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(input)
+}
+
+/// Applies a key typed into standard input to the current line.
+/// Returns whether it ended the input.
+fn continue_line(
+    sender: &Sender<SessionMessage>,
+    line: &mut Option<String>,
+    input: &mut Vec<u8>,
+    key_event: &KeyEvent,
+) -> bool {
+    let Some(text) = line.as_mut() else {
+        return false;
+    };
+
+    match (&key_event.key_type, &key_event.modifier) {
+        (Key::Char('d'), Some(Modifier::Ctrl)) => {
+            input.extend_from_slice(text.as_bytes());
+            finish_line(sender, line);
+            return true;
+        }
+        (Key::Enter, _) => {
+            text.push('\n');
+            input.extend_from_slice(text.as_bytes());
+            finish_line(sender, line);
+            return false;
+        }
+        (Key::Backspace, _) => {
+            text.pop();
+        }
+        _ => {
+            if let Some(ch) = key_event.typed_char() {
+                text.push(ch);
+            }
+        }
+    }
+
+    let cursor = text.chars().count();
+    echo(
+        sender,
+        TerminalMessage::SetSpan(0, Span::from(text.as_str()).with_cursor(cursor)),
+    );
+    echo(sender, TerminalMessage::ForceUpdate);
+    false
+}
+
+/// Echoes the finished line without a cursor and moves on to the next one
+fn finish_line(sender: &Sender<SessionMessage>, line: &mut Option<String>) {
+    if let Some(text) = line.take() {
+        let text = format!("{}\r\n", text.trim_end_matches('\n'));
+        echo(
+            sender,
+            TerminalMessage::SetSpan(0, Span::from(text.as_str())),
+        );
+        echo(sender, TerminalMessage::ForceUpdate);
+    }
+}
+
+fn echo(sender: &Sender<SessionMessage>, message: TerminalMessage) {
+    sender
+        .send(SessionMessage::Shell(ShellMessage::Echo(message), None))
+        .ok();
+}
+
+/// The size of the terminal, if the frontend has said what it is
+pub fn terminal_size(sender: &Sender<SessionMessage>) -> Option<Size> {
+    let (tx, rx) = channel();
+    sender
+        .send(SessionMessage::Shell(ShellMessage::GetSize, Some(tx)))
+        .ok()?;
+    match rx.recv().ok()? {
+        SessionMessage::Return(ReturnValue::Size(size)) => size,
+        _ => None,
+    }
+}
+
+/// The text a message writes to the terminal, as it would appear in a file.
+/// Messages that don't write text, like `Clear` or `SetSpan`, have none.
+pub fn output_text(message: &TerminalMessage) -> Option<String> {
+    let text = match message {
+        TerminalMessage::Push(text) => text.clone(),
+        TerminalMessage::PushLine(text) => format!("{text}\n"),
+        TerminalMessage::PushSpan(span) => span.text.clone(),
+        _ => return None,
+    };
+    Some(text.replace('\r', ""))
+}
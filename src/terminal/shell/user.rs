@@ -1,13 +1,23 @@
-use std::{collections::HashMap, sync::LazyLock};
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{channel, Sender},
+        LazyLock,
+    },
+};
 
 use macro_hash::hash;
 
+use crate::session::{ReturnValue, SessionMessage, ShellMessage};
+
 #[derive(Clone, Debug)]
 pub struct User {
     name: String,
     home_dir: Option<String>,
     path: String,
     password: Option<u64>,
+    /// May read and write anything, and give files away with `chown`
+    superuser: bool,
 }
 
 impl Default for User {
@@ -17,6 +27,7 @@ impl Default for User {
             home_dir: None,
             path: "/bin/".into(),
             password: None,
+            superuser: false,
         }
     }
 }
@@ -29,6 +40,7 @@ pub static USERS: LazyLock<HashMap<&str, User>> = LazyLock::new(|| {
                 name: "cold".into(),
                 password: Some(hash!("TestingPasswordThisIsntGoingIntoProduction")),
                 home_dir: Some("/root/".into()),
+                superuser: true,
                 ..Default::default()
             },
         ),
@@ -66,6 +78,35 @@ impl User {
         &self.name
     }
 
+    /// Every user has a group of their own, named after them
+    pub fn get_group(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_superuser(&self) -> bool {
+        self.superuser
+    }
+
+    /// Asks the shell who is signed in, through the sender a program was given
+    pub fn current(sender: &Sender<SessionMessage>) -> User {
+        let (tx, rx) = channel();
+
+        sender
+            .send(SessionMessage::Shell(
+                ShellMessage::GetCurrentUser,
+                Some(tx),
+            ))
+            .unwrap();
+
+        if let SessionMessage::Return(ReturnValue::User(Some(user))) =
+            rx.recv().expect("Couldn't get current user")
+        {
+            user
+        } else {
+            unreachable!("User::current did not receive a return message");
+        }
+    }
+
     pub fn get_path(&self) -> &str {
         &self.path
     }
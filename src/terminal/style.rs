@@ -44,6 +44,10 @@ pub struct Span {
     pub fg_color: Option<Color>,
     pub bg_color: Option<Color>,
     pub decoration: u8,
+    /// Character index to draw the cursor at. May be one past the end of the text.
+    pub cursor: Option<usize>,
+    /// Where the text links to. Clickable in the HTML output on wasm.
+    pub link: Option<String>,
 }
 
 impl Span {
@@ -53,6 +57,8 @@ impl Span {
             fg_color: None,
             bg_color: None,
             decoration: 0,
+            cursor: None,
+            link: None,
         }
     }
 
@@ -85,11 +91,102 @@ impl Span {
         self.decoration ^= Decoration::Underline as u8;
         self
     }
+
+    pub fn with_cursor(mut self, cursor: usize) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    /// Links somewhere, unless `link` isn't one `is_safe_link` allows
+    pub fn with_link(mut self, link: String) -> Self {
+        self.link = is_safe_link(&link).then_some(link);
+        self
+    }
+}
+
+/// Whether `url` may be linked to: only `http:`, `https:` and `mailto:` URLs are, so text
+/// someone wrote can't link to a script
+pub fn is_safe_link(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    ["http:", "https:", "mailto:"]
+        .iter()
+        .any(|x| url.starts_with(x))
+}
+
+/// `text` with the characters HTML gives meaning to escaped
+#[cfg(target_arch = "wasm32")]
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 impl Display for Span {
+    /// A `<span>` styled inline, inside an `<a>` if it links somewhere. The character
+    /// under the cursor gets its own span with the `cursor` class.
     #[cfg(target_arch = "wasm32")]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {}
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut style = String::new();
+        if self.decoration & Decoration::Bold as u8 != 0 {
+            style.push_str("font-weight:bold;");
+        }
+        if self.decoration & Decoration::Italic as u8 != 0 {
+            style.push_str("font-style:italic;");
+        }
+        if self.decoration & Decoration::Underline as u8 != 0 {
+            style.push_str("text-decoration:underline;");
+        }
+        if let Some(fg) = self.fg_color {
+            style.push_str(&format!("color:rgb({},{},{});", fg.r(), fg.g(), fg.b()));
+        }
+        if let Some(bg) = self.bg_color {
+            style.push_str(&format!(
+                "background-color:rgb({},{},{});",
+                bg.r(),
+                bg.g(),
+                bg.b()
+            ));
+        }
+
+        if let Some(link) = &self.link {
+            write!(
+                f,
+                "<a href=\"{}\" target=\"_blank\" rel=\"noopener\">",
+                escape_html(link)
+            )?;
+        }
+        write!(f, "<span style=\"{style}\">")?;
+        match self.cursor {
+            Some(cursor) => {
+                let mut chars = self.text.chars();
+                let before: String = chars.by_ref().take(cursor).collect();
+                let under = chars.next().unwrap_or(' ');
+                let after: String = chars.collect();
+                write!(
+                    f,
+                    "{}<span class=\"cursor\">{}</span>{}",
+                    escape_html(&before),
+                    escape_html(&under.to_string()),
+                    escape_html(&after)
+                )?;
+            }
+            None => write!(f, "{}", escape_html(&self.text))?,
+        }
+        write!(f, "</span>")?;
+        if self.link.is_some() {
+            write!(f, "</a>")?;
+        }
+        Ok(())
+    }
     #[cfg(not(target_arch = "wasm32"))]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.decoration & Decoration::Bold as u8 != 0 {
@@ -107,7 +204,16 @@ impl Display for Span {
         if let Some(bg) = self.bg_color {
             write!(f, "\x1b[48;2;{};{};{}m", bg.r(), bg.g(), bg.b())?;
         }
-        write!(f, "{}\x1b[0m", self.text)
+        match self.cursor {
+            Some(cursor) => {
+                let mut chars = self.text.chars();
+                let before: String = chars.by_ref().take(cursor).collect();
+                let under = chars.next().unwrap_or(' ');
+                let after: String = chars.collect();
+                write!(f, "{before}\x1b[7m{under}\x1b[27m{after}\x1b[0m")
+            }
+            None => write!(f, "{}\x1b[0m", self.text),
+        }
     }
 }
 
@@ -125,6 +231,75 @@ impl SpanSet {
     pub fn new() -> Self {
         Self(Vec::new())
     }
+
+    /// Splits the set into display lines, breaking on newlines and, if given a width,
+    /// wrapping any line longer than that many characters. Spans crossing a line
+    /// boundary are split into pieces that keep their styling.
+    pub fn lines(&self, width: Option<usize>) -> Vec<SpanSet> {
+        let width = width.filter(|w| *w > 0);
+        let mut lines = vec![SpanSet::new()];
+        let mut line_len = 0usize;
+
+        for span in &self.0 {
+            let mut piece = span.clone().with_text(String::new());
+            piece.cursor = None;
+            for (i, c) in span.text.chars().enumerate() {
+                if c == '\r' {
+                    continue;
+                }
+
+                if c == '\n' || width.is_some_and(|w| line_len >= w) {
+                    lines.last_mut().unwrap().push(piece.clone());
+                    lines.push(SpanSet::new());
+                    piece.text.clear();
+                    piece.cursor = None;
+                    line_len = 0;
+                }
+
+                if span.cursor == Some(i) {
+                    piece.cursor = Some(piece.text.chars().count());
+                }
+
+                if c != '\n' {
+                    piece.text.push(c);
+                    line_len += 1;
+                }
+            }
+            if span.cursor == Some(span.text.chars().count()) {
+                piece.cursor = Some(piece.text.chars().count());
+            }
+            lines.last_mut().unwrap().push(piece);
+        }
+
+        lines
+    }
+
+    /// Drops text from the front of the set until at most `max_lines` lines are left.
+    /// The last span is never removed, so pushes to the current line keep their styling.
+    pub fn truncate_front(&mut self, max_lines: usize) {
+        let newlines: usize = self.0.iter().map(|x| x.text.matches('\n').count()).sum();
+        let mut excess = (newlines + 1).saturating_sub(max_lines.max(1));
+
+        while excess > 0 && !self.0.is_empty() {
+            let last = self.0.len() == 1;
+            let first = &mut self.0[0];
+            let count = first.text.matches('\n').count();
+            if count >= excess || last {
+                if let Some((cut, _)) = first.text.match_indices('\n').nth(excess - 1) {
+                    first.text.drain(..=cut);
+                }
+                return;
+            }
+            excess -= count;
+            self.0.remove(0);
+        }
+    }
+}
+
+impl Default for SpanSet {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Deref for SpanSet {
@@ -0,0 +1,86 @@
+use crate::session::{ReturnValue, SessionMessage, ShellMessage};
+use crate::terminal::shell::files::{FSObject, FileSystem, FileType, FILESYSTEM};
+use crate::terminal::shell::stdio::output_text;
+use crate::terminal::shell::user::User;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::mpsc::channel;
+use std::thread;
+
+/// Runs `script` with `/bin/cash` as cold in `/`, answering what it asks the shell.
+/// Returns what it printed, the folders it told the shell to change to and its exit code.
+fn run_script(name: &str, script: &str) -> (String, Vec<String>, u32) {
+    let cold = User::from_name("cold");
+    let path = format!("/tmp/{name}");
+    let mut filesystem = FILESYSTEM.write().unwrap();
+    filesystem
+        .write_file(Path::new(&path), script.as_bytes(), false, &cold)
+        .unwrap();
+    let cash = filesystem
+        .get_by_path(Path::new("/bin/cash"), &cold)
+        .ok()
+        .and_then(|x| match *x.read().unwrap() {
+            FSObject::File {
+                contents: FileType::Program(program),
+                ..
+            } => Some(program),
+            _ => None,
+        })
+        .expect("/bin/cash isn't a program");
+    drop(filesystem);
+
+    let (events_tx, events) = channel();
+    let (shell_tx, shell_rx) = channel();
+    let env = BTreeMap::from([
+        ("PWD".to_string(), "/".to_string()),
+        ("PATH".to_string(), "/bin".to_string()),
+    ]);
+    thread::spawn(move || cash()(vec!["cash".into(), path], env, events, shell_tx));
+
+    let mut output = String::new();
+    let mut cwds = Vec::new();
+    loop {
+        let reply = match shell_rx.recv().unwrap() {
+            SessionMessage::Shell(ShellMessage::ExitCode(code), _) => {
+                drop(events_tx);
+                return (output, cwds, code);
+            }
+            SessionMessage::Shell(ShellMessage::GetCurrentUser, ret) => (
+                SessionMessage::Return(ReturnValue::User(Some(cold.clone()))),
+                ret,
+            ),
+            SessionMessage::Shell(ShellMessage::GetSize, ret) => {
+                (SessionMessage::Return(ReturnValue::Size(None)), ret)
+            }
+            SessionMessage::Shell(ShellMessage::ChangeCwd(path), ret) => {
+                cwds.push(path.to_string_lossy().into());
+                (SessionMessage::Ack(None), ret)
+            }
+            SessionMessage::Terminal(message, ret) => {
+                output.extend(output_text(&message));
+                (SessionMessage::Ack(None), ret)
+            }
+            _ => continue,
+        };
+        if let (message, Some(ret)) = reply {
+            ret.send(message).ok();
+        }
+    }
+}
+
+#[test]
+fn cash_pipeline_builtins() {
+    // Each part of a pipeline runs in a copy of the shell, so builtins there don't
+    // change the shell itself
+    let (output, cwds, code) = run_script(
+        "pipeline-builtins",
+        "cd /tmp | cat\npwd\nexport X=1 | true\necho \"[$X]\"\nexit 3 | cat\necho done\n",
+    );
+    assert_eq!(output, "/\n[]\ndone\n");
+    assert!(cwds.is_empty());
+    assert_eq!(code, 0);
+
+    let (output, cwds, _) = run_script("cd", "cd /tmp\npwd\n");
+    assert_eq!(output, "/tmp\n");
+    assert_eq!(cwds, ["/tmp"]);
+}
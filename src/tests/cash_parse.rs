@@ -0,0 +1,124 @@
+use crate::terminal::shell::cash_parse::*;
+use crate::terminal::shell::files::Environment;
+
+fn parse_line(line: &str) -> Result<Option<Ast>, String> {
+    parse(tokenize(line).unwrap())
+}
+
+fn run(words: &[&str]) -> Ast {
+    Ast::Pipeline(vec![Command {
+        args: words.iter().map(|x| x.to_string()).collect(),
+        stdin: None,
+        stdout: None,
+    }])
+}
+
+fn expand(raw: &str, status: u32, env: &Environment) -> Option<String> {
+    let args = ["script".to_string(), "first".into()];
+    let variables = Variables {
+        status,
+        args: &args,
+        env,
+    };
+    expand_quotes(raw, &variables).ok().map(|x| x.text)
+}
+
+#[test]
+fn cash_parse_lists() {
+    // `&&` and `||` group from the left, and bind tighter than `;`
+    assert_eq!(
+        parse_line("a && b || c; d"),
+        Ok(Some(Ast::Sequence(
+            Box::new(Ast::Or(
+                Box::new(Ast::And(Box::new(run(&["a"])), Box::new(run(&["b"])))),
+                Box::new(run(&["c"]))
+            )),
+            Box::new(run(&["d"]))
+        )))
+    );
+    assert_eq!(
+        parse_line("a && (b || c)"),
+        Ok(Some(Ast::And(
+            Box::new(run(&["a"])),
+            Box::new(Ast::Or(Box::new(run(&["b"])), Box::new(run(&["c"]))))
+        )))
+    );
+    assert_eq!(
+        parse_line("cat < in | sort >> out"),
+        Ok(Some(Ast::Pipeline(vec![
+            Command {
+                args: vec!["cat".into()],
+                stdin: Some("in".into()),
+                stdout: None,
+            },
+            Command {
+                args: vec!["sort".into()],
+                stdin: None,
+                stdout: Some(("out".into(), true)),
+            },
+        ])))
+    );
+    assert_eq!(
+        parse_line("if a\nthen b; elif c; then d; fi"),
+        Ok(Some(Ast::If(
+            Box::new(run(&["a"])),
+            Box::new(run(&["b"])),
+            Some(Box::new(Ast::If(
+                Box::new(run(&["c"])),
+                Box::new(run(&["d"])),
+                None
+            )))
+        )))
+    );
+    assert_eq!(parse_line("  # just a comment"), Ok(None));
+}
+
+#[test]
+fn cash_parse_errors() {
+    let error = |token: &str| Err(format!("syntax error near unexpected token `{token}'"));
+    assert_eq!(parse_line("a &&"), error("newline"));
+    assert_eq!(parse_line("| a"), error("|"));
+    assert_eq!(parse_line("(a"), error("newline"));
+    assert_eq!(parse_line("a)"), error(")"));
+    assert_eq!(parse_line("a >"), error("newline"));
+    assert_eq!(parse_line("if a; then b"), error("newline"));
+    assert_eq!(parse_line("for 1 in a; do b; done"), error("1"));
+    assert!(tokenize("echo 'open").is_err());
+    assert!(tokenize("echo \"a\"b").is_err());
+    assert!(tokenize("echo \\").is_err());
+}
+
+#[test]
+fn cash_parse_quoting() {
+    assert_eq!(
+        tokenize("echo 'a b'|grep \"x;y\"&&ls>>out"),
+        Ok(vec![
+            Token::Word("echo".into()),
+            Token::Word("'a b'".into()),
+            Token::Pipe,
+            Token::Word("grep".into()),
+            Token::Word("\"x;y\"".into()),
+            Token::And,
+            Token::Word("ls".into()),
+            Token::RedirectAppend,
+            Token::Word("out".into()),
+        ])
+    );
+
+    let mut env = Environment::new();
+    env.insert("NAME".into(), "cold".into());
+    assert_eq!(expand("$?", 2, &env).as_deref(), Some("2"));
+    assert_eq!(expand("\"$?\"x", 0, &env).as_deref(), Some("0x"));
+    assert_eq!(
+        expand("$NAME-${NAME}s", 0, &env).as_deref(),
+        Some("cold-colds")
+    );
+    assert_eq!(expand("'$NAME'", 0, &env).as_deref(), Some("$NAME"));
+    assert_eq!(expand("\"a\\$b\\n\"", 0, &env).as_deref(), Some("a$b\\n"));
+    assert_eq!(
+        expand("$# $1 $@ $9", 0, &env).as_deref(),
+        Some("1 first first ")
+    );
+    assert_eq!(expand("cost: $", 0, &env).as_deref(), Some("cost: $"));
+    assert_eq!(expand("${NAME", 0, &env), None);
+}
@@ -1,42 +1,522 @@
 use crate::terminal::shell::files::*;
-use tree::Tree;
+use crate::terminal::shell::user::User;
+use std::collections::BTreeSet;
+use std::path::Path;
+use tree::send_tree::SendTree;
 
 #[test]
 fn filesystem() {
-    let filesystem: Tree<FSObject> = Tree::new_filesystem();
-
-    filesystem.dfs_iter().for_each(|x| match *(x.get_value()) {
-        FSObject::File {
-            ref name,
-            ref contents,
-        } => {
-            println!("File \"{name}\"");
-            match contents {
-                FileType::Program(x) => {
-                    println!("Running {name}:");
-                    x();
-                }
-                FileType::Binary(v) => {
-                    println!("{}: {}", name, String::from_utf8_lossy(v));
-                }
-            };
-        }
-        FSObject::Folder {
-            ref name,
-            contents: _,
-        } => {
-            println!("Folder \"{name}\":");
-        }
-    });
-
-    assert_eq!(
-        filesystem.get_child(0).and_then(|x| {
-            if let FSObject::Folder { ref name, .. } = *x.get_value() {
-                Some(name.clone())
-            } else {
-                None
+    let filesystem: SendTree<FSObject> = SendTree::new_filesystem();
+
+    filesystem
+        .dfs_iter()
+        .for_each(|x| match *(x.get_value().read().unwrap()) {
+            FSObject::File {
+                ref name,
+                ref contents,
+                ..
+            } => {
+                println!("File \"{name}\"");
+                match contents {
+                    FileType::Program(x) => {
+                        println!("Running {name}:");
+                        x();
+                    }
+                    FileType::Binary(v) => {
+                        println!("{}: {}", name, String::from_utf8_lossy(v));
+                    }
+                };
+            }
+            FSObject::Folder { ref name, .. } => {
+                println!("Folder \"{name}\":");
+            }
+            FSObject::Symlink {
+                ref name,
+                ref target,
+                ..
+            } => {
+                println!("Symlink \"{name}\" -> \"{target}\"");
+            }
+        });
+
+    assert!(filesystem
+        .get_by_path(Path::new("/home"), &User::from_name("guest"))
+        .is_ok_and(|x| x.read().unwrap().is_folder()));
+}
+
+#[test]
+fn filesystem_mutation() {
+    let mut filesystem: SendTree<FSObject> = SendTree::new_root();
+    let cold = User::from_name("cold");
+    let read = |filesystem: &SendTree<FSObject>, path: &str| {
+        filesystem
+            .get_by_path(Path::new(path), &cold)
+            .ok()
+            .map(|x| match *x.read().unwrap() {
+                FSObject::File {
+                    contents: FileType::Binary(ref bytes),
+                    ..
+                } => String::from_utf8_lossy(bytes).to_string(),
+                _ => String::new(),
+            })
+    };
+
+    filesystem.create_folder(Path::new("/a"), &cold).unwrap();
+    filesystem.create_folder(Path::new("/b"), &cold).unwrap();
+    filesystem
+        .write_file(Path::new("/a/x"), b"x", false, &cold)
+        .unwrap();
+    filesystem
+        .write_file(Path::new("/a/x"), b"y", true, &cold)
+        .unwrap();
+    filesystem
+        .write_file(Path::new("/a/z"), b"z", false, &cold)
+        .unwrap();
+    assert_eq!(read(&filesystem, "/a/x").as_deref(), Some("xy"));
+    assert_eq!(
+        filesystem.create_folder(Path::new("/a"), &cold).err(),
+        Some(FsError::AlreadyExists)
+    );
+    assert_eq!(
+        filesystem.create_folder(Path::new("/a/x/y"), &cold).err(),
+        Some(FsError::NotADirectory)
+    );
+
+    // Removing the first entry moves the last one into its place
+    filesystem.unlink(Path::new("/a/x"), &cold).unwrap();
+    assert_eq!(read(&filesystem, "/a/x"), None);
+    assert_eq!(read(&filesystem, "/a/z").as_deref(), Some("z"));
+
+    filesystem
+        .rename(Path::new("/a/z"), Path::new("/b/w"), &cold)
+        .unwrap();
+    assert_eq!(read(&filesystem, "/a/z"), None);
+    assert_eq!(read(&filesystem, "/b/w").as_deref(), Some("z"));
+    assert_eq!(
+        filesystem
+            .rename(Path::new("/b"), Path::new("/b/c"), &cold)
+            .err(),
+        Some(FsError::InvalidArgument)
+    );
+
+    assert_eq!(
+        filesystem.remove_folder(Path::new("/b"), &cold).err(),
+        Some(FsError::NotEmpty)
+    );
+    filesystem.remove_folder(Path::new("/a"), &cold).unwrap();
+    assert_eq!(read(&filesystem, "/b/w").as_deref(), Some("z"));
+}
+
+#[test]
+fn filesystem_permissions() {
+    let filesystem: SendTree<FSObject> = SendTree::new_filesystem();
+    let guest = User::from_name("guest");
+    let cold = User::from_name("cold");
+
+    let root = filesystem.get_by_path(Path::new("/root"), &cold).unwrap();
+    assert_eq!(
+        *root.read().unwrap().get_permissions(),
+        Permissions::new("cold", "cold", 0o700)
+    );
+    assert!(!root.read().unwrap().allows(&guest, Access::Read));
+    assert_eq!(
+        filesystem
+            .get_by_path(Path::new("/root/.cashrc"), &guest)
+            .err(),
+        Some(FsError::PermissionDenied)
+    );
+    assert!(filesystem
+        .get_by_path(Path::new("/root/.cashrc"), &cold)
+        .is_ok());
+
+    let home = filesystem
+        .get_by_path(Path::new("/home/guest"), &guest)
+        .unwrap();
+    assert_eq!(home.read().unwrap().get_permissions().owner, "guest");
+    assert_eq!(
+        Permissions::new("guest", "guest", 0o754).mode_string(),
+        "rwxr-xr--"
+    );
+
+    // Only the owner may chmod, and only a superuser may chown, in mounts too
+    let mut filesystem = vfs::Vfs::new(filesystem).with_mount(
+        "/tmp",
+        tmpfs::Tmpfs::new(Permissions::new("cold", "cold", 0o777)),
+    );
+    let open = Permissions::new("cold", "cold", 0o777);
+    assert_eq!(
+        filesystem.set_permissions(Path::new("/root"), open.clone(), &guest),
+        Err(FsError::NotPermitted)
+    );
+    assert_eq!(
+        filesystem.set_permissions(
+            Path::new("/home/guest"),
+            Permissions::new("guest", "guest", 0o700),
+            &guest
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        filesystem.set_permissions(
+            Path::new("/home/guest"),
+            Permissions::new("cold", "guest", 0o700),
+            &guest
+        ),
+        Err(FsError::NotPermitted)
+    );
+    filesystem
+        .write_file(Path::new("/tmp/notes"), b"hi", false, &cold)
+        .unwrap();
+    assert_eq!(
+        filesystem.set_permissions(Path::new("/tmp/notes"), open.clone(), &guest),
+        Err(FsError::NotPermitted)
+    );
+    assert_eq!(
+        filesystem.set_permissions(Path::new("/root"), open, &cold),
+        Ok(())
+    );
+}
+
+#[test]
+fn filesystem_sticky() {
+    let guest = User::from_name("guest");
+    let cold = User::from_name("cold");
+    let mut filesystem = vfs::Vfs::new(SendTree::new_filesystem()).with_mount(
+        "/tmp",
+        tmpfs::Tmpfs::new(Permissions::new("cold", "cold", 0o1777)),
+    );
+    assert_eq!(
+        Permissions::new("cold", "cold", 0o1777).mode_string(),
+        "rwxrwxrwt"
+    );
+    assert_eq!(
+        Permissions::new("cold", "cold", 0o1776).mode_string(),
+        "rwxrwxrwT"
+    );
+
+    // Anyone may add to /tmp, but only remove or rename their own things
+    filesystem
+        .write_file(Path::new("/tmp/notes"), b"hi", false, &cold)
+        .unwrap();
+    filesystem
+        .write_file(Path::new("/tmp/mine"), b"hi", false, &guest)
+        .unwrap();
+    assert_eq!(
+        filesystem.unlink(Path::new("/tmp/notes"), &guest),
+        Err(FsError::NotPermitted)
+    );
+    assert_eq!(
+        filesystem.rename(Path::new("/tmp/notes"), Path::new("/tmp/moved"), &guest),
+        Err(FsError::NotPermitted)
+    );
+    assert_eq!(
+        filesystem.rename(Path::new("/tmp/mine"), Path::new("/tmp/notes"), &guest),
+        Err(FsError::NotPermitted)
+    );
+    assert_eq!(
+        filesystem.rename(Path::new("/tmp/mine"), Path::new("/tmp/moved"), &guest),
+        Ok(())
+    );
+    assert_eq!(filesystem.unlink(Path::new("/tmp/moved"), &guest), Ok(()));
+    // The folder's owner may remove anything in it
+    filesystem
+        .write_file(Path::new("/tmp/mine"), b"hi", false, &guest)
+        .unwrap();
+    assert_eq!(filesystem.unlink(Path::new("/tmp/mine"), &cold), Ok(()));
+    assert_eq!(filesystem.unlink(Path::new("/tmp/notes"), &cold), Ok(()));
+}
+
+#[test]
+fn filesystem_symlinks() {
+    let cold = User::from_name("cold");
+    let link = |name: &str, target: &str| {
+        SendTree::new(FSObject::Symlink {
+            name: name.into(),
+            target: target.into(),
+            permissions: Permissions::new("cold", "cold", 0o777),
+            times: Times::now(),
+        })
+    };
+    let mut filesystem: SendTree<FSObject> = SendTree::new_root()
+        .with_child(link("loop", "loop"))
+        .with_child(link("etc", "/home/../real"))
+        .indexed();
+    filesystem.create_folder(Path::new("/home"), &cold).unwrap();
+    filesystem.create_folder(Path::new("/real"), &cold).unwrap();
+    filesystem
+        .write_file(Path::new("/etc/profile"), b"x", false, &cold)
+        .unwrap();
+
+    assert_eq!(
+        filesystem.canonicalize(Path::new("/etc/profile"), &cold),
+        Ok("/real/profile".into())
+    );
+    assert!(filesystem
+        .get_link_by_path(Path::new("/etc"), &cold)
+        .is_ok_and(|x| matches!(*x.read().unwrap(), FSObject::Symlink { .. })));
+    assert_eq!(
+        filesystem.get_by_path(Path::new("/loop"), &cold).err(),
+        Some(FsError::TooManyLinks)
+    );
+
+    // Removing a link leaves what it points to alone
+    filesystem.unlink(Path::new("/etc"), &cold).unwrap();
+    assert!(filesystem
+        .get_by_path(Path::new("/real/profile"), &cold)
+        .is_ok());
+
+    let filesystem: SendTree<FSObject> = SendTree::new_filesystem();
+    assert!(filesystem
+        .get_by_path(Path::new("/bin/sh"), &cold)
+        .is_ok_and(|x| matches!(
+            *x.read().unwrap(),
+            FSObject::File {
+                contents: FileType::Program(_),
+                ..
             }
-        }),
-        Some(String::from("home"))
-    )
+        )));
+}
+
+#[test]
+fn filesystem_overlay() {
+    let cold = User::from_name("cold");
+    let mut filesystem = vfs::Vfs::new_filesystem();
+    filesystem
+        .create_folder(Path::new("/home/guest/my notes"), &cold)
+        .unwrap();
+    filesystem
+        .write_file(
+            Path::new("/home/guest/my notes/todo"),
+            b"milk\n",
+            false,
+            &cold,
+        )
+        .unwrap();
+    filesystem
+        .rename(Path::new("/bin/ls"), Path::new("/bin/list"), &cold)
+        .unwrap();
+
+    let overlay = overlay::serialize(&filesystem.root, &filesystem.changed);
+    assert!(overlay.contains("delete /bin/ls\n"));
+    assert!(overlay.lines().any(|x| x
+        .starts_with("file /home/guest/my%20notes/todo cold cold 644 ")
+        && x.ends_with(" 6d696c6b0a")));
+    assert!(overlay
+        .lines()
+        .any(|x| x.starts_with("program /bin/list ") && x.ends_with(" /bin/ls")));
+    // Nothing else has changed, but for the folders entries were added to or removed from
+    assert_eq!(overlay.lines().count(), 7);
+
+    // Merging it notes the same paths as changed, so it's saved again as it was
+    let mut merged: SendTree<FSObject> = SendTree::new_filesystem();
+    let mut changed = BTreeSet::new();
+    overlay::merge(&mut merged, &overlay, &mut changed).unwrap();
+    assert_eq!(changed, filesystem.changed);
+    assert_eq!(overlay::serialize(&merged, &changed), overlay);
+    assert!(merged.get_by_path(Path::new("/bin/ls"), &cold).is_err());
+    assert!(overlay::merge(&mut merged, "something else", &mut changed).is_err());
+}
+
+#[test]
+fn filesystem_metadata() {
+    let cold = User::from_name("cold");
+    let mut filesystem: SendTree<FSObject> = SendTree::new_filesystem();
+    let readme = filesystem
+        .stat(Path::new("/home/guest/README.md"), &cold)
+        .unwrap();
+    assert_eq!(readme.kind, "text/markdown");
+    assert_eq!(readme.times.created, readme.times.modified);
+    assert_eq!(
+        filesystem.stat(Path::new("/bin"), &cold).unwrap().kind,
+        "inode/directory"
+    );
+    assert_eq!(
+        filesystem.lstat(Path::new("/bin/sh"), &cold).unwrap().kind,
+        "inode/symlink"
+    );
+    assert_eq!(
+        filesystem.stat(Path::new("/bin/sh"), &cold).unwrap().kind,
+        "application/x-executable"
+    );
+
+    filesystem
+        .write_file(Path::new("/root/notes"), b"\x89PNG\r\n\x1a\n", false, &cold)
+        .unwrap();
+    let notes = filesystem.stat(Path::new("/root/notes"), &cold).unwrap();
+    assert_eq!((notes.kind, notes.size), ("image/png", 8));
+    assert!(notes.times.created >= readme.times.created);
+
+    assert_eq!(Times::format(0), "1970-01-01 00:00:00");
+    assert_eq!(Times::format(1_709_294_400_000), "2024-03-01 12:00:00");
+}
+
+#[test]
+fn filesystem_mounts() {
+    let guest = User::from_name("guest");
+    let mut filesystem = vfs::Vfs::new(SendTree::new_filesystem())
+        .with_mount(
+            "/tmp",
+            tmpfs::Tmpfs::new(Permissions::new("cold", "cold", 0o777)),
+        )
+        .with_mount("/proc", proc::Proc);
+
+    assert!(!filesystem.take_changed());
+    filesystem
+        .write_file(Path::new("/tmp/notes"), b"hi", false, &guest)
+        .unwrap();
+    // What's mounted isn't saved, so only changes to the compiled-in tree count
+    assert!(!filesystem.take_changed());
+    filesystem
+        .write_file(Path::new("/home/guest/notes"), b"hi", false, &guest)
+        .unwrap();
+    assert!(filesystem.take_changed());
+    assert!(filesystem.changed.contains(Path::new("/home/guest/notes")));
+    assert!(!filesystem.changed.contains(Path::new("/tmp/notes")));
+    assert_eq!(
+        filesystem
+            .stat(Path::new("/tmp/notes"), &guest)
+            .map(|x| (x.size, x.permissions.owner)),
+        Ok((2, "guest".into()))
+    );
+    assert_eq!(
+        filesystem.read_file(Path::new("/tmp/notes"), &guest),
+        Ok(b"hi".to_vec())
+    );
+    assert_eq!(
+        filesystem.read_file(Path::new("/tmp"), &guest),
+        Err(FsError::IsADirectory)
+    );
+    // Code written against `FileSystem` crosses mount points too
+    fn size<'a>(filesystem: &impl FileSystem<'a>, path: &str, user: &User) -> Option<usize> {
+        filesystem.stat(Path::new(path), user).ok().map(|x| x.size)
+    }
+    assert_eq!(size(&filesystem, "/tmp/notes", &guest), Some(2));
+    assert_eq!(size(&filesystem.root, "/tmp/notes", &guest), None);
+    // What's mounted isn't part of the tree it's mounted on
+    assert!(filesystem
+        .root
+        .get_by_path(Path::new("/tmp/notes"), &guest)
+        .is_err());
+
+    assert_eq!(
+        filesystem.rename(
+            Path::new("/tmp/notes"),
+            Path::new("/home/guest/notes"),
+            &guest
+        ),
+        Err(FsError::CrossDevice)
+    );
+    assert_eq!(
+        filesystem.remove_folder(Path::new("/tmp"), &guest),
+        Err(FsError::Busy)
+    );
+    assert!(filesystem
+        .get_by_path(Path::new("/proc/uptime"), &guest)
+        .is_ok());
+    assert_eq!(
+        filesystem.write_file(Path::new("/proc/uptime"), b"0", false, &guest),
+        Err(FsError::ReadOnly)
+    );
+    // Only what succeeds counts as a change, so there's nothing new to save
+    assert!(!filesystem.take_changed());
+}
+
+#[test]
+fn filesystem_processes() {
+    let guest = User::from_name("guest");
+    let filesystem = vfs::Vfs::new(SendTree::new_filesystem()).with_mount("/proc", proc::Proc);
+    let read = |path: &str| match filesystem.get_by_path(Path::new(path), &guest) {
+        Ok(file) => match *file.read().unwrap() {
+            FSObject::File {
+                contents: FileType::Binary(ref bytes),
+                ..
+            } => Some(String::from_utf8_lossy(bytes).into_owned()),
+            _ => None,
+        },
+        Err(_) => None,
+    };
+
+    let parent = proc::spawn(None, "cash".into());
+    let child = proc::spawn(Some(parent), "cat notes".into());
+    assert_eq!(
+        read(&format!("/proc/{child}/cmdline")).as_deref(),
+        Some("cat notes\n")
+    );
+    assert!(read(&format!("/proc/{child}/status"))
+        .unwrap()
+        .contains(&format!("PPid:\t{parent}\n")));
+
+    proc::exit(child);
+    assert_eq!(read(&format!("/proc/{child}/cmdline")), None);
+    proc::exit(parent);
+}
+
+#[test]
+fn filesystem_errors() {
+    let guest = User::from_name("guest");
+    let filesystem: SendTree<FSObject> = SendTree::new_filesystem();
+
+    let error = filesystem
+        .get_by_path(Path::new("/bin/nothing"), &guest)
+        .err();
+    assert_eq!(error, Some(FsError::NotFound));
+    assert_eq!(error.unwrap().to_string(), "No such file or directory");
+    assert_eq!(
+        filesystem
+            .get_by_path(Path::new("/bin/ls/nothing"), &guest)
+            .err(),
+        Some(FsError::NotADirectory)
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9");
+        assert_eq!(
+            SendTree::<FSObject>::resolve_path(Path::new(name), Path::new("/"), Path::new("/")),
+            Err(FsError::InvalidUtf8)
+        );
+    }
+}
+
+#[test]
+fn filesystem_glob() {
+    assert!(glob::matches("*.md", "notes.md"));
+    assert!(!glob::matches("*.md", "notes.mdx"));
+    assert!(glob::matches("c?t", "cat"));
+    assert!(glob::matches("[bc]at", "cat"));
+    assert!(glob::matches("[a-c]at", "bat"));
+    assert!(!glob::matches("[!a-c]at", "bat"));
+    assert!(glob::matches("\\*", "*"));
+    assert!(!glob::matches("\\*", "x"));
+    assert!(glob::matches("[", "["));
+    assert!(glob::matches("*a*b*", "xxaxxbxx"));
+    assert!(glob::matches("a*", "a"));
+    assert!(!glob::matches("a*b", "acbd"));
+    // Many stars don't make matching take exponential time
+    let name = "a".repeat(200);
+    assert!(!glob::matches(&format!("{}b", "*a".repeat(20)), &name));
+    assert!(glob::matches(&format!("{}*", "*a".repeat(20)), &name));
+    assert!(!glob::has_wildcards("\\*.md"));
+    assert!(!glob::has_wildcards("[oops"));
+
+    let guest = User::from_name("guest");
+    let filesystem = vfs::Vfs::new(SendTree::new_filesystem());
+    let expand = |pattern: &str, cwd: &str| {
+        glob::expand(
+            &filesystem,
+            pattern,
+            Path::new(cwd),
+            Path::new("/home/guest"),
+            &guest,
+        )
+    };
+    let found = expand("/bin/c[ah]*", "/");
+    assert!(found.contains(&"/bin/cash".to_string()));
+    assert!(found.contains(&"/bin/chmod".to_string()));
+    assert!(!found.contains(&"/bin/clear".to_string()));
+    assert_eq!(expand("c?t", "/bin"), vec!["cat"]);
+    assert_eq!(expand("/b*/", "/"), vec!["/bin/"]);
+    assert!(expand("/**/cat", "/").contains(&"/bin/cat".to_string()));
+    assert!(expand("/bin/*.nothing", "/").is_empty());
 }
@@ -0,0 +1,46 @@
+use crate::terminal::shell::markdown::render;
+use crate::terminal::Span;
+
+fn text(lines: &[Vec<Span>]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|x| x.iter().map(|x| x.text.as_str()).collect())
+        .collect()
+}
+
+#[test]
+fn markdown_blocks() {
+    let lines = render(
+        "# Title\n\nSome *words* that wrap\n\n- one\n  - two\n> quoted",
+        12,
+    );
+    assert_eq!(
+        text(&lines),
+        [
+            "Title",
+            "",
+            "Some words",
+            "that wrap",
+            "",
+            "  • one",
+            "      • two",
+            "│ quoted"
+        ]
+    );
+
+    // Whitespace other than spaces and tabs doesn't indent a list item's text
+    let lines = render("- item\n \u{a0}x", 80);
+    assert_eq!(text(&lines), ["  • item x"]);
+}
+
+#[test]
+fn markdown_links() {
+    let lines = render(
+        "[site](https://example.com) [x](javascript:alert(1)) <javascript://x>",
+        80,
+    );
+    let links: Vec<_> = lines[0].iter().filter_map(|x| x.link.clone()).collect();
+    assert_eq!(links, ["https://example.com"]);
+    assert!(text(&lines)[0].ends_with("<javascript://x>"));
+    assert_eq!(Span::new().with_link("JavaScript:x".into()).link, None);
+}
@@ -1,2 +1,8 @@
+mod cash;
+mod cash_parse;
 mod filesystem;
 mod hash;
+mod markdown;
+mod regex;
+mod stdio;
+mod terminal;
@@ -0,0 +1,42 @@
+use crate::terminal::shell::regex::Regex;
+
+#[test]
+fn regex_matching() {
+    let regex = Regex::new("^(ab|c)+d?$", false).unwrap();
+    assert!(regex.is_match("ababc"));
+    assert!(regex.is_match("cd"));
+    assert!(!regex.is_match("abx"));
+
+    let regex = Regex::new("[a-c]\\d{2,}", true).unwrap();
+    assert_eq!(regex.find_all("B12 c3 a456"), vec![(0, 3), (7, 11)]);
+    assert_eq!(
+        Regex::new("é+", false).unwrap().find("café", 0),
+        Some((3, 5))
+    );
+    assert!(Regex::literal("a.b", false).is_match("xa.b"));
+    assert!(!Regex::literal("a.b", false).is_match("axb"));
+
+    assert_eq!(
+        Regex::new("(a", false).err(),
+        Some("Unmatched ( or \\(".into())
+    );
+    assert!(Regex::new("[a", false).is_err());
+}
+
+#[test]
+fn regex_long_lines() {
+    // Matching doesn't recurse per character, so long lines can't run out of stack
+    let line = "x".repeat(100_000);
+    assert!(!Regex::new("x.*y", false).unwrap().is_match(&line));
+    assert_eq!(
+        Regex::new("x.*", false).unwrap().find(&line, 0),
+        Some((0, line.len()))
+    );
+    assert!(Regex::new("^(x|xx)*$", false).unwrap().is_match(&line));
+    assert!(Regex::new("x{1000}", false).unwrap().is_match(&line));
+    assert!(!Regex::new("(a*)*b", false).unwrap().is_match(&line));
+    assert_eq!(
+        Regex::new("a{100000}", false).err(),
+        Some("Regular expression too big".into())
+    );
+}
@@ -0,0 +1,39 @@
+use crate::session::{SessionMessage, ShellMessage, TerminalMessage};
+use crate::terminal::shell::stdio::{output_text, read_stdin};
+use crate::terminal::Span;
+use std::sync::mpsc::channel;
+
+#[test]
+fn read_stdin_chunks() {
+    let (tx, rx) = channel();
+    let (echo_tx, echo_rx) = channel();
+    for chunk in ["hello ", "world\n"] {
+        tx.send(SessionMessage::Shell(
+            ShellMessage::Stdin(chunk.as_bytes().to_vec()),
+            None,
+        ))
+        .unwrap();
+    }
+    tx.send(SessionMessage::Shell(ShellMessage::EndOfInput, None))
+        .unwrap();
+
+    assert_eq!(read_stdin(&rx, &echo_tx), Some(b"hello world\n".to_vec()));
+    // Nothing was typed, so nothing is echoed
+    assert!(echo_rx.try_recv().is_err());
+
+    drop(tx);
+    assert_eq!(read_stdin(&rx, &echo_tx), None);
+}
+
+#[test]
+fn output_text_messages() {
+    assert_eq!(
+        output_text(&TerminalMessage::PushLine("a".into())),
+        Some("a\n".into())
+    );
+    assert_eq!(
+        output_text(&TerminalMessage::PushSpan(Span::from("b\r\n"))),
+        Some("b\n".into())
+    );
+    assert_eq!(output_text(&TerminalMessage::ForceUpdate), None);
+}
@@ -0,0 +1,190 @@
+use crate::session::{EventLoop, OutputMessage, SessionMessage, TerminalMessage};
+use crate::terminal::{DefaultTerminal, Screen, Span, SpanSet, Terminal};
+use crate::utils::pos::Size;
+use std::sync::mpsc::channel;
+use std::thread;
+
+fn line_text(line: &SpanSet) -> String {
+    line.iter().map(|span| span.text.as_str()).collect()
+}
+
+/// Runs a `DefaultTerminal` 10 columns wide and `height` lines high through `messages`.
+/// Returns the lines of each screen it shows after being sized, without their styles.
+fn screens(height: usize, messages: Vec<TerminalMessage>) -> Vec<Vec<String>> {
+    let (tx, rx) = channel();
+    let (out_tx, out_rx) = channel();
+    let terminal = thread::spawn(move || DefaultTerminal::new().event_loop(rx, out_tx).ok());
+    tx.send(SessionMessage::Resize(10, height)).unwrap();
+    for message in messages {
+        tx.send(SessionMessage::Terminal(message, None)).unwrap();
+    }
+    drop(tx);
+    terminal.join().unwrap();
+
+    out_rx
+        .iter()
+        .skip(1)
+        .map(|message| match message {
+            SessionMessage::Output(OutputMessage::Display(text), _) => strip_styles(&text)
+                .split("\r\n")
+                .map(String::from)
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+fn strip_styles(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            chars.by_ref().find(|x| x.is_ascii_alphabetic());
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+/// Six numbered lines, leaving the cursor on a seventh, empty one
+fn numbered_lines() -> Vec<TerminalMessage> {
+    (1..=6)
+        .map(|i| TerminalMessage::PushLine(i.to_string()))
+        .collect()
+}
+
+#[test]
+fn span_set_lines() {
+    let mut set = SpanSet::new();
+    set.push(Span::from("hello\r\nwor"));
+    set.push(Span::from("ld\r\n"));
+
+    let lines: Vec<String> = set.lines(None).iter().map(line_text).collect();
+    assert_eq!(lines, vec!["hello", "world", ""]);
+}
+
+#[test]
+fn span_set_lines_wrapped() {
+    let mut set = SpanSet::new();
+    set.push(Span::from("abcdefg"));
+    set.push(Span::from("hi\r\nj"));
+
+    let lines: Vec<String> = set.lines(Some(3)).iter().map(line_text).collect();
+    assert_eq!(lines, vec!["abc", "def", "ghi", "j"]);
+}
+
+#[test]
+fn span_set_truncate_front() {
+    let mut set = SpanSet::new();
+    set.push(Span::from("one\r\n"));
+    set.push(Span::from("two\r\nthree\r\n"));
+    set.push(Span::from("four"));
+
+    set.truncate_front(2);
+    let lines: Vec<String> = set.lines(None).iter().map(line_text).collect();
+    assert_eq!(lines, vec!["three", "four"]);
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn span_set_lines_cursor() {
+    let mut set = SpanSet::new();
+    set.push(Span::from("abcdef").with_cursor(4));
+    set.push(Span::from("gh").with_cursor(2));
+
+    let lines = set.lines(Some(3));
+    let cursors: Vec<Vec<Option<usize>>> = lines
+        .iter()
+        .map(|line| line.iter().map(|span| span.cursor).collect())
+        .collect();
+    assert_eq!(
+        cursors,
+        vec![vec![None], vec![Some(1), None], vec![Some(2)]]
+    );
+}
+
+#[test]
+fn screen_writes_at_cursor() {
+    let mut screen = Screen::new(Size::new(5, 3));
+    screen.write("hello world\nab");
+    screen.move_cursor(1, 0);
+    screen.write_span(Span::from("EY").bold());
+    screen.move_cursor(9, 9);
+    screen.write("!");
+
+    let lines: Vec<String> = screen.lines().iter().map(line_text).collect();
+    assert_eq!(lines, vec!["hEYlo", "ab", "    !"]);
+    assert_eq!(screen.lines()[0].len(), 3);
+}
+
+#[test]
+fn terminal_viewport() {
+    let mut messages = numbered_lines();
+    messages.push(TerminalMessage::ForceUpdate);
+    messages.push(TerminalMessage::Push("a long line that wraps".into()));
+    messages.push(TerminalMessage::ForceUpdate);
+    // Only as many lines as fit are shown, from the bottom, wrapped to the width
+    assert_eq!(
+        screens(3, messages),
+        [vec!["5", "6", ""], vec!["a long lin", "e that wra", "ps"]]
+    );
+}
+
+#[test]
+fn terminal_scroll() {
+    let mut messages = numbered_lines();
+    messages.extend([
+        TerminalMessage::ScrollUp(2),
+        TerminalMessage::ScrollUp(100),
+        TerminalMessage::ScrollDown(1),
+        TerminalMessage::ScrollDown(100),
+        TerminalMessage::ScrollTop,
+        TerminalMessage::ScrollBottom,
+        TerminalMessage::ScrollTop,
+        // Writing anything goes back to the bottom
+        TerminalMessage::Push("7".into()),
+        TerminalMessage::ForceUpdate,
+    ]);
+    // Scrolling stops at the first line and the last
+    assert_eq!(
+        screens(3, messages),
+        [
+            vec!["3", "4", "5"],
+            vec!["1", "2", "3"],
+            vec!["2", "3", "4"],
+            vec!["5", "6", ""],
+            vec!["1", "2", "3"],
+            vec!["5", "6", ""],
+            vec!["1", "2", "3"],
+            vec!["5", "6", "7"],
+        ]
+    );
+}
+
+#[test]
+fn terminal_scrollback() {
+    let mut messages = numbered_lines();
+    messages.extend([
+        TerminalMessage::ScrollTop,
+        TerminalMessage::SetScrollback(4),
+        // Scrolled past what's left, so it's back as far up as it can be
+        TerminalMessage::ForceUpdate,
+        TerminalMessage::ScrollTop,
+        TerminalMessage::PushLine("7".into()),
+        TerminalMessage::PushLine("8".into()),
+        TerminalMessage::ScrollTop,
+        TerminalMessage::SetScrollback(0),
+        TerminalMessage::ForceUpdate,
+    ]);
+    assert_eq!(
+        screens(3, messages),
+        [
+            vec!["1", "2", "3"],
+            vec!["4", "5", "6"],
+            vec!["4", "5", "6"],
+            vec!["6", "7", "8"],
+            vec![""],
+        ]
+    );
+}
@@ -1,6 +1,6 @@
 use std::ops::AddAssign;
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug)]
 pub struct Point {
     x: usize,
     y: usize,
@@ -13,12 +13,26 @@ impl AddAssign for Point {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 pub struct Size {
     height: usize,
     width: usize,
 }
 
+impl Size {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { height, width }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
 impl AddAssign for Size {
     fn add_assign(&mut self, rhs: Self) {
         self.height += rhs.height;
@@ -35,7 +49,7 @@ impl From<Size> for Rect {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug)]
 pub struct Rect {
     origin: Point,
     size: Size,
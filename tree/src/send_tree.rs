@@ -22,7 +22,7 @@ impl<'a, T> SendTree<'a, T> {
         }
     }
 
-    pub fn get_value(&'a self) -> Arc<RwLock<T>> {
+    pub fn get_value(&self) -> Arc<RwLock<T>> {
         self.value.clone()
     }
 
@@ -64,6 +64,12 @@ impl<'a, T> SendTree<'a, T> {
         }
     }
 
+    /// Removes the child at `index`, moving the last child into its place.
+    /// Only that one child changes index, instead of every child after `index`.
+    pub fn swap_remove(&mut self, index: usize) -> SendTree<'a, T> {
+        self.children.swap_remove(index)
+    }
+
     pub fn dfs_iter(&'a self) -> DfsSendTreeIterator<'a, T> {
         DfsSendTreeIterator::new(self)
     }